@@ -0,0 +1,282 @@
+/* Copyright 2026 The Reflection Developers
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use std::cell::{OnceCell, RefCell};
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{gio, glib, glib::clone};
+use tracing::error;
+
+use crate::utils::format_datetime;
+use reflection_doc::document::{ActionItem, Document};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::ActionItemsPanel)]
+    #[template(file = "src/action_items_panel/action_items_panel.blp")]
+    pub struct ActionItemsPanel {
+        #[template_child]
+        assigned_to_me_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        items_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        new_item_entry: TemplateChild<gtk::Entry>,
+        #[property(get, set = Self::set_document, nullable)]
+        document: RefCell<Option<Document>>,
+        document_handlers: OnceCell<glib::SignalGroup>,
+        /// All action items of the document, the actions refer to them by their position.
+        items: RefCell<Vec<ActionItem>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ActionItemsPanel {
+        const NAME: &'static str = "ReflectionActionItemsPanel";
+        type Type = super::ActionItemsPanel;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+
+            klass.install_action(
+                "action-items.assign-to-me",
+                Some(glib::VariantTy::UINT32),
+                |panel, _, parameter| {
+                    let Some(document) = panel.document() else {
+                        return;
+                    };
+                    let me = document.service().private_key().public_key();
+                    panel.imp().update_item(parameter, |item| {
+                        document.set_action_item_assignee(item, Some(&me))
+                    });
+                },
+            );
+
+            klass.install_action(
+                "action-items.unassign",
+                Some(glib::VariantTy::UINT32),
+                |panel, _, parameter| {
+                    let Some(document) = panel.document() else {
+                        return;
+                    };
+                    panel.imp().update_item(parameter, |item| {
+                        document.set_action_item_assignee(item, None)
+                    });
+                },
+            );
+
+            klass.install_action(
+                "action-items.remove",
+                Some(glib::VariantTy::UINT32),
+                |panel, _, parameter| {
+                    let Some(document) = panel.document() else {
+                        return;
+                    };
+                    panel
+                        .imp()
+                        .update_item(parameter, |item| document.remove_action_item(item));
+                },
+            );
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for ActionItemsPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let document_handlers = glib::SignalGroup::with_type(Document::static_type());
+            document_handlers.connect_local(
+                "action-items-changed",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.update_items();
+                        None
+                    }
+                ),
+            );
+            self.document_handlers.set(document_handlers).unwrap();
+
+            self.assigned_to_me_button.connect_toggled(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    this.update_items();
+                }
+            ));
+
+            self.new_item_entry.connect_activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |entry| {
+                    let text = entry.text();
+                    let Some(document) = this.obj().document() else {
+                        return;
+                    };
+                    if text.trim().is_empty() {
+                        return;
+                    }
+
+                    if let Err(error) = document.add_action_item(text.trim()) {
+                        error!("Failed to add action item: {error}");
+                        return;
+                    }
+                    entry.set_text("");
+                }
+            ));
+        }
+    }
+
+    impl ActionItemsPanel {
+        fn set_document(&self, document: Option<Document>) {
+            self.document_handlers
+                .get()
+                .unwrap()
+                .set_target(document.as_ref());
+            self.new_item_entry.set_sensitive(
+                document
+                    .as_ref()
+                    .is_some_and(|document| !document.read_only()),
+            );
+            self.document.replace(document);
+            self.update_items();
+        }
+
+        /// Change the action item at the position in `parameter` with `update`.
+        fn update_item<E: std::fmt::Display>(
+            &self,
+            parameter: Option<&glib::Variant>,
+            update: impl FnOnce(&ActionItem) -> Result<(), E>,
+        ) {
+            let Some(index) = parameter.and_then(|parameter| parameter.get::<u32>()) else {
+                return;
+            };
+            let Some(item) = self.items.borrow().get(index as usize).cloned() else {
+                return;
+            };
+
+            if let Err(error) = update(&item) {
+                error!("Failed to change action item: {error}");
+            }
+        }
+
+        fn update_items(&self) {
+            self.items_list.remove_all();
+
+            let Some(document) = self.obj().document() else {
+                self.items.replace(Vec::new());
+                return;
+            };
+            let me = document.service().private_key().public_key();
+            let only_mine = self.assigned_to_me_button.is_active();
+
+            let items = document.action_items();
+            for (index, item) in items.iter().enumerate() {
+                if only_mine && item.assignee.as_ref() != Some(&me) {
+                    continue;
+                }
+
+                self.items_list
+                    .append(&self.item_row(&document, index as u32, item));
+            }
+            self.items.replace(items);
+        }
+
+        fn item_row(&self, document: &Document, index: u32, item: &ActionItem) -> adw::ActionRow {
+            let row = adw::ActionRow::builder()
+                .title(&item.text)
+                .use_markup(false)
+                .build();
+
+            let mut details = Vec::new();
+            if let Some(assignee) = &item.assignee {
+                let name = document
+                    .authors()
+                    .author(assignee)
+                    .map_or_else(|| gettext("Unknown author"), |author| author.name());
+                details.push(name);
+            }
+            if let Some(due) = &item.due {
+                details.push(format_datetime(&gettext("Due"), due));
+            }
+            row.set_subtitle(&details.join(" · "));
+
+            let done_button = gtk::CheckButton::builder()
+                .active(item.done)
+                .valign(gtk::Align::Center)
+                .sensitive(!document.read_only())
+                .tooltip_text(gettext("Done"))
+                .build();
+            let item = item.clone();
+            done_button.connect_toggled(clone!(
+                #[weak]
+                document,
+                move |button| {
+                    if let Err(error) = document.set_action_item_done(&item, button.is_active()) {
+                        error!("Failed to change action item: {error}");
+                    }
+                }
+            ));
+            row.add_prefix(&done_button);
+            row.set_activatable_widget(Some(&done_button));
+
+            let menu = gio::Menu::new();
+            for (label, action) in [
+                (gettext("Assign to _Me"), "action-items.assign-to-me"),
+                (gettext("_Unassign"), "action-items.unassign"),
+                (gettext("_Remove"), "action-items.remove"),
+            ] {
+                let menu_item = gio::MenuItem::new(Some(&label), None);
+                menu_item.set_action_and_target_value(Some(action), Some(&index.to_variant()));
+                menu.append_item(&menu_item);
+            }
+            let menu_button = gtk::MenuButton::builder()
+                .icon_name("view-more-symbolic")
+                .valign(gtk::Align::Center)
+                .menu_model(&menu)
+                .visible(!document.read_only())
+                .tooltip_text(gettext("More"))
+                .build();
+            menu_button.add_css_class("flat");
+            row.add_suffix(&menu_button);
+
+            row
+        }
+    }
+
+    impl WidgetImpl for ActionItemsPanel {}
+    impl BinImpl for ActionItemsPanel {}
+}
+
+glib::wrapper! {
+    pub struct ActionItemsPanel(ObjectSubclass<imp::ActionItemsPanel>)
+        @extends gtk::Widget, adw::Bin,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
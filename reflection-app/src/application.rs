@@ -22,7 +22,12 @@ use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gtk::{gdk, gio, glib, glib::Properties, glib::clone};
-use reflection_doc::{document::DocumentId, identity::PrivateKey, service::Service};
+use reflection_doc::{
+    document::{Document, DocumentId},
+    export::ExportFormat,
+    identity::PrivateKey,
+    service::Service,
+};
 use std::{cell::RefCell, fs};
 use thiserror::Error;
 use tracing::error;
@@ -31,8 +36,22 @@ use crate::config;
 use crate::open_dialog::OpenDialog;
 use crate::secret;
 use crate::system_settings::SystemSettings;
+use crate::utils::{format_datetime, invite_code_from_input};
 use crate::window::Window;
 
+/// Suffix of the files pads are exported to.
+const PAD_FILE_SUFFIX: &str = "reflection";
+
+fn pad_file_filters() -> gio::ListStore {
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some(&gettext("Reflection Pads")));
+    filter.add_suffix(PAD_FILE_SUFFIX);
+
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+    filters.append(&filter);
+    filters
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
@@ -128,9 +147,10 @@ impl ReflectionApplication {
             .into_iter()
             .filter_map(|window| window.downcast::<Window>().ok())
             .find(|window| {
+                // Windows showing an old version of the document don't count
                 window
                     .document()
-                    .is_some_and(|document| &document.id() == document_id)
+                    .is_some_and(|document| &document.id() == document_id && !document.read_only())
             })
     }
 
@@ -232,6 +252,69 @@ impl ReflectionApplication {
             })
             .build();
 
+        let export_document_action = gio::ActionEntry::builder("export-document")
+            .parameter_type(Some(&glib::VariantType::new_array(
+                &DocumentId::static_variant_type(),
+            )))
+            .activate(move |app: &Self, _, parameter| {
+                let parameter = parameter.unwrap();
+
+                for i in 0..parameter.n_children() {
+                    if let Some(document_id) = parameter.child_value(i).get() {
+                        app.export_document(&document_id);
+                        break;
+                    } else {
+                        error!("Failed to export pad: Invalid pad id specified");
+                    }
+                }
+            })
+            .build();
+
+        let export_to_file_action = gio::ActionEntry::builder("export-to-file")
+            .parameter_type(Some(&glib::VariantType::new_array(
+                &DocumentId::static_variant_type(),
+            )))
+            .activate(move |app: &Self, _, parameter| {
+                let parameter = parameter.unwrap();
+
+                for i in 0..parameter.n_children() {
+                    if let Some(document_id) = parameter.child_value(i).get() {
+                        if let Some(document) = app
+                            .service()
+                            .and_then(|service| service.documents().document(&document_id))
+                        {
+                            app.export_to_file(&document);
+                        }
+                        break;
+                    } else {
+                        error!("Failed to export pad to file: Invalid pad id specified");
+                    }
+                }
+            })
+            .build();
+
+        let open_version_action = gio::ActionEntry::builder("open-version")
+            .parameter_type(Some(&glib::VariantType::new_array(
+                &DocumentId::static_variant_type(),
+            )))
+            .activate(move |app: &Self, _, parameter| {
+                let parameter = parameter.unwrap();
+
+                for i in 0..parameter.n_children() {
+                    if let Some(document_id) = parameter.child_value(i).get() {
+                        app.open_version(&document_id);
+                        break;
+                    } else {
+                        error!("Failed to open version of pad: Invalid pad id specified");
+                    }
+                }
+            })
+            .build();
+
+        let open_document_file_action = gio::ActionEntry::builder("open-document-file")
+            .activate(move |app: &Self, _, _| app.open_document_file())
+            .build();
+
         let temporary_identity_action = gio::ActionEntry::builder("new-temporary-identity")
             .activate(move |app: &Self, _, _| {
                 glib::spawn_future_local(clone!(
@@ -253,8 +336,16 @@ impl ReflectionApplication {
             join_document_in_new_window_action,
             delete_document_action,
             copy_document_id_action,
+            export_document_action,
+            export_to_file_action,
+            open_version_action,
+            open_document_file_action,
             temporary_identity_action,
         ]);
+
+        // Toggles syncing closed pads in the background
+        let settings = gio::Settings::new(config::APP_ID);
+        self.add_action(&settings.create_action("background-sync"));
     }
 
     async fn create_service(&self) -> Result<Service, Error> {
@@ -266,7 +357,8 @@ impl ReflectionApplication {
         fs::create_dir_all(&data_path)?;
         let data_dir = gio::File::for_path(data_path);
 
-        let service = Service::new(&private_key, Some(&data_dir));
+        let settings = gio::Settings::new(config::APP_ID);
+        let service = Service::new(&private_key, Some(&data_dir), Some(&settings));
         service.startup().await?;
 
         Ok(service)
@@ -318,7 +410,12 @@ impl ReflectionApplication {
     }
 
     fn new_document(&self) {
-        self.join_document(&DocumentId::new(), false);
+        let Some(service) = self.service() else {
+            return;
+        };
+
+        let document = service.create_document();
+        self.join_document(&document.id(), false);
     }
 
     fn open_join_document_dialog(&self, new_window: bool) {
@@ -342,6 +439,13 @@ impl ReflectionApplication {
                 return;
             };
 
+            // Documents need to be known to the service, new documents are added either via
+            // `Self::new_document()` or via an invite code
+            let Some(document) = service.documents().document(document_id) else {
+                error!("Failed to join pad: Unknown pad id specified");
+                return;
+            };
+
             let window = if new_window {
                 self.new_window()
             } else if let Some(active) = self.active_window().and_downcast::<Window>() {
@@ -349,7 +453,6 @@ impl ReflectionApplication {
             } else {
                 self.new_window()
             };
-            let document = service.join_document(document_id);
             window.set_document(Some(&document));
             let hold_guard = self.hold();
             glib::spawn_future_local(clone!(
@@ -402,12 +505,282 @@ impl ReflectionApplication {
         let Some(display) = gdk::Display::default() else {
             return;
         };
-        display.clipboard().set_text(&document_id.to_string());
+        let Some(invite_code) = self
+            .service()
+            .and_then(|service| service.documents().document(document_id))
+            .and_then(|document| document.invite_code())
+        else {
+            return;
+        };
+        display.clipboard().set_text(&invite_code);
+    }
+
+    fn export_document(&self, document_id: &DocumentId) {
+        let Some(document) = self
+            .service()
+            .and_then(|service| service.documents().document(document_id))
+        else {
+            return;
+        };
+
+        let name = document.name().unwrap_or_else(|| gettext("Untitled"));
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Save Backup"))
+            .initial_name(format!("{name}.{PAD_FILE_SUFFIX}"))
+            .filters(&pad_file_filters())
+            .build();
+
+        let hold_guard = self.hold();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let window = this.active_window();
+                // The dialog was cancelled
+                let Ok(file) = dialog.save_future(window.as_ref()).await else {
+                    return;
+                };
+
+                let bundle = match document.export().await {
+                    Ok(bundle) => bundle,
+                    Err(error) => {
+                        error!("Failed to export pad: {error}");
+                        return;
+                    }
+                };
+
+                if let Err(error) = file
+                    .replace_contents_future(
+                        bundle,
+                        None,
+                        false,
+                        gio::FileCreateFlags::REPLACE_DESTINATION,
+                    )
+                    .await
+                {
+                    error!("Failed to write pad file: {}", error.1);
+                }
+                drop(hold_guard);
+            }
+        ));
+    }
+
+    /// Let the user choose a Markdown, HTML or plain text file and write the text of `document`
+    /// to it.
+    ///
+    /// Read-only versions of a document are exported as they are, not the current text.
+    pub fn export_to_file(&self, document: &Document) {
+        let document = document.clone();
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        for (format, name) in [
+            (ExportFormat::Markdown, gettext("Markdown")),
+            (ExportFormat::Html, gettext("Web Page")),
+            (ExportFormat::PlainText, gettext("Plain Text")),
+        ] {
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some(&name));
+            filter.add_suffix(format.suffix());
+            filter.add_mime_type(format.mime_type());
+            filters.append(&filter);
+        }
+
+        let name = document.name().unwrap_or_else(|| gettext("Untitled"));
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Export to File"))
+            .initial_name(format!("{name}.{}", ExportFormat::Markdown.suffix()))
+            .filters(&filters)
+            .build();
+
+        let hold_guard = self.hold();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let window = this.active_window();
+                // The dialog was cancelled
+                let Ok(file) = dialog.save_future(window.as_ref()).await else {
+                    return;
+                };
+
+                // The format is chosen via the suffix of the file name
+                let format = file
+                    .basename()
+                    .and_then(|basename| ExportFormat::from_suffix(basename.extension()?.to_str()?))
+                    .unwrap_or(ExportFormat::Markdown);
+
+                // The text is part of the content, which isn't loaded until the pad is opened
+                document.load().await;
+                let text = document.export_as(format);
+
+                if let Err(error) = file
+                    .replace_contents_future(
+                        text.into_bytes(),
+                        None,
+                        false,
+                        gio::FileCreateFlags::REPLACE_DESTINATION,
+                    )
+                    .await
+                {
+                    error!("Failed to write exported pad: {}", error.1);
+                }
+                drop(hold_guard);
+            }
+        ));
+    }
+
+    /// Let the user choose a named version of the document and open it read-only.
+    fn open_version(&self, document_id: &DocumentId) {
+        let Some(document) = self
+            .service()
+            .and_then(|service| service.documents().document(document_id))
+        else {
+            return;
+        };
+
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                // The tags are part of the content, which isn't loaded until the pad is opened
+                document.load().await;
+                let tags = document.tags();
+
+                let dialog = adw::AlertDialog::builder()
+                    .heading(gettext("Open Version"))
+                    .close_response("cancel")
+                    .build();
+                dialog.add_response("cancel", &gettext("Cancel"));
+
+                if tags.is_empty() {
+                    dialog.set_body(&gettext("This pad has no named versions yet."));
+                } else {
+                    let list = gtk::ListBox::builder()
+                        .selection_mode(gtk::SelectionMode::None)
+                        .css_classes(["boxed-list"])
+                        .build();
+
+                    for tag in tags {
+                        let row = adw::ActionRow::builder()
+                            .title(&tag.name)
+                            .activatable(true)
+                            .build();
+                        if let Some(timestamp) = tag.checkpoint.timestamp.as_ref() {
+                            row.set_subtitle(&format_datetime(&gettext("Last changed"), timestamp));
+                        }
+
+                        row.connect_activated(clone!(
+                            #[weak]
+                            this,
+                            #[weak]
+                            dialog,
+                            #[weak]
+                            document,
+                            move |_| {
+                                dialog.close();
+
+                                match document.checkout(&tag.checkpoint) {
+                                    Ok(version) => {
+                                        this.new_window().set_document(Some(&version));
+                                    }
+                                    Err(error) => {
+                                        error!("Failed to open version of pad: {error}");
+                                    }
+                                }
+                            }
+                        ));
+                        list.append(&row);
+                    }
+
+                    dialog.set_extra_child(Some(&list));
+                }
+
+                dialog.present(this.active_window().as_ref());
+            }
+        ));
+    }
+
+    fn open_document_file(&self) {
+        let Some(service) = self.service() else {
+            return;
+        };
+
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Open Pad File"))
+            .filters(&pad_file_filters())
+            .build();
+
+        let hold_guard = self.hold();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let window = this.active_window();
+                // The dialog was cancelled
+                let Ok(file) = dialog.open_future(window.as_ref()).await else {
+                    return;
+                };
+
+                let bundle = match file.load_contents_future().await {
+                    Ok((bundle, _)) => bundle,
+                    Err(error) => {
+                        error!("Failed to read pad file: {error}");
+                        return;
+                    }
+                };
+
+                // Pad files don't contain the secret of the pad, it comes from the invite code
+                let entry = gtk::Entry::builder()
+                    .placeholder_text(gettext("Invite code"))
+                    .activates_default(true)
+                    .build();
+                let dialog = adw::AlertDialog::builder()
+                    .heading(gettext("Enter Invite Code"))
+                    .body(gettext(
+                        "Pad files can only be opened with the invite code of the pad",
+                    ))
+                    .extra_child(&entry)
+                    .default_response("open")
+                    .close_response("cancel")
+                    .build();
+                dialog.add_response("cancel", &gettext("Cancel"));
+                dialog.add_response("open", &gettext("_Open"));
+                dialog.set_response_appearance("open", adw::ResponseAppearance::Suggested);
+                dialog.set_response_enabled("open", false);
+
+                entry.connect_changed(clone!(
+                    #[weak]
+                    dialog,
+                    move |entry| {
+                        let invite_code = invite_code_from_input(&entry.text());
+                        dialog.set_response_enabled(
+                            "open",
+                            Document::parse_invite_code(&invite_code).is_ok(),
+                        );
+                    }
+                ));
+
+                if dialog.choose_future(window.as_ref()).await != "open" {
+                    return;
+                }
+                let Ok(secret) =
+                    Document::parse_invite_code(&invite_code_from_input(&entry.text()))
+                else {
+                    return;
+                };
+
+                match service.import_document(bundle.to_vec(), &secret).await {
+                    Ok(document) => this.join_document(&document.id(), false),
+                    Err(error) => error!("Failed to open pad file: {error}"),
+                }
+                drop(hold_guard);
+            }
+        ));
     }
 
     async fn new_temporary_identity(&self) {
         let private_key = PrivateKey::new();
-        let service = Service::new(&private_key, None);
+        let settings = gio::Settings::new(config::APP_ID);
+        let service = Service::new(&private_key, None, Some(&settings));
 
         if let Err(error) = service.startup().await {
             let error = error.into();
@@ -0,0 +1,283 @@
+/* Copyright 2026 The Reflection Developers
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use std::cell::{OnceCell, RefCell};
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{glib, glib::clone};
+use tracing::error;
+
+use crate::utils::format_datetime;
+use reflection_doc::author::Author;
+use reflection_doc::document::{Comment, Document};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::CommentsSidebar)]
+    #[template(file = "src/comments_sidebar/comments_sidebar.blp")]
+    pub struct CommentsSidebar {
+        #[template_child]
+        show_resolved_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        comments_list: TemplateChild<gtk::ListBox>,
+        #[property(get, set = Self::set_document, nullable)]
+        document: RefCell<Option<Document>>,
+        document_handlers: OnceCell<glib::SignalGroup>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CommentsSidebar {
+        const NAME: &'static str = "ReflectionCommentsSidebar";
+        type Type = super::CommentsSidebar;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for CommentsSidebar {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let document_handlers = glib::SignalGroup::with_type(Document::static_type());
+            document_handlers.connect_local(
+                "comments-changed",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.update_comments();
+                        None
+                    }
+                ),
+            );
+            self.document_handlers.set(document_handlers).unwrap();
+
+            self.show_resolved_button.connect_toggled(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    this.update_comments();
+                }
+            ));
+        }
+    }
+
+    impl CommentsSidebar {
+        fn set_document(&self, document: Option<Document>) {
+            self.document_handlers
+                .get()
+                .unwrap()
+                .set_target(document.as_ref());
+            self.document.replace(document);
+            self.update_comments();
+        }
+
+        fn update_comments(&self) {
+            self.comments_list.remove_all();
+
+            let comments = self
+                .obj()
+                .document()
+                .map(|document| (document.text(), document.comments(), document));
+            let mut n_comments = 0;
+            if let Some((text, comments, document)) = comments {
+                let show_resolved = self.show_resolved_button.is_active();
+
+                for comment in comments {
+                    if comment.resolved && !show_resolved {
+                        continue;
+                    }
+
+                    let quote: String = text
+                        .chars()
+                        .skip(comment.start as usize)
+                        .take((comment.end - comment.start) as usize)
+                        .collect();
+                    self.comments_list
+                        .append(&comment_row(&document, &quote, comment));
+                    n_comments += 1;
+                }
+            }
+
+            self.stack
+                .set_visible_child_name(if n_comments > 0 { "comments" } else { "empty" });
+        }
+    }
+
+    impl WidgetImpl for CommentsSidebar {}
+    impl BinImpl for CommentsSidebar {}
+}
+
+glib::wrapper! {
+    pub struct CommentsSidebar(ObjectSubclass<imp::CommentsSidebar>)
+        @extends gtk::Widget, adw::Bin,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+/// A row with the comment, the text it's about and its replies.
+fn comment_row(document: &Document, quote: &str, comment: Comment) -> gtk::ListBoxRow {
+    let content = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    if comment.resolved {
+        content.add_css_class("dim-label");
+    }
+
+    let quote_label = gtk::Label::builder()
+        .label(quote.lines().next().unwrap_or_default())
+        .ellipsize(gtk::pango::EllipsizeMode::End)
+        .xalign(0.0)
+        .build();
+    quote_label.add_css_class("monospace");
+    quote_label.add_css_class("dim-label");
+    content.append(&quote_label);
+
+    append_message(
+        &content,
+        comment.author.as_ref(),
+        &format_time(&gettext("Commented"), comment.timestamp.as_ref()),
+        &comment.text,
+    );
+    for reply in &comment.replies {
+        append_message(
+            &content,
+            reply.author.as_ref(),
+            &format_time(&gettext("Replied"), reply.timestamp.as_ref()),
+            &reply.text,
+        );
+    }
+
+    let actions = gtk::Box::builder()
+        .spacing(6)
+        .visible(!document.read_only())
+        .build();
+
+    let reply_entry = gtk::Entry::builder()
+        .placeholder_text(gettext("Reply"))
+        .hexpand(true)
+        .build();
+    let resolve_button = gtk::Button::builder()
+        .label(if comment.resolved {
+            gettext("_Reopen")
+        } else {
+            gettext("_Resolve")
+        })
+        .use_underline(true)
+        .build();
+    let remove_button = gtk::Button::builder()
+        .icon_name("user-trash-symbolic")
+        .tooltip_text(gettext("Remove Comment"))
+        .build();
+    remove_button.add_css_class("flat");
+
+    let comment_clone = comment.clone();
+    reply_entry.connect_activate(clone!(
+        #[weak]
+        document,
+        move |entry| {
+            let text = entry.text();
+            if text.trim().is_empty() {
+                return;
+            }
+
+            if let Err(error) = document.reply_to_comment(&comment_clone, text.trim()) {
+                error!("Failed to reply to comment: {error}");
+            }
+        }
+    ));
+    let comment_clone = comment.clone();
+    resolve_button.connect_clicked(clone!(
+        #[weak]
+        document,
+        move |_| {
+            if let Err(error) =
+                document.set_comment_resolved(&comment_clone, !comment_clone.resolved)
+            {
+                error!("Failed to resolve comment: {error}");
+            }
+        }
+    ));
+    remove_button.connect_clicked(clone!(
+        #[weak]
+        document,
+        move |_| {
+            if let Err(error) = document.remove_comment(&comment) {
+                error!("Failed to remove comment: {error}");
+            }
+        }
+    ));
+
+    actions.append(&reply_entry);
+    actions.append(&resolve_button);
+    actions.append(&remove_button);
+    content.append(&actions);
+
+    gtk::ListBoxRow::builder()
+        .activatable(false)
+        .child(&content)
+        .build()
+}
+
+/// Append who wrote `text` and when to `content`.
+fn append_message(content: &gtk::Box, author: Option<&Author>, time: &str, text: &str) {
+    let name = author.map_or_else(|| gettext("Unknown author"), |author| author.name());
+    let header = gtk::Label::builder()
+        .label(format!("{name} · {time}"))
+        .wrap(true)
+        .xalign(0.0)
+        .build();
+    header.add_css_class("caption-heading");
+    content.append(&header);
+
+    let text = gtk::Label::builder()
+        .label(text)
+        .wrap(true)
+        .wrap_mode(gtk::pango::WrapMode::WordChar)
+        .selectable(true)
+        .xalign(0.0)
+        .build();
+    content.append(&text);
+}
+
+fn format_time(last_string: &str, timestamp: Option<&glib::DateTime>) -> String {
+    timestamp.map_or_else(
+        || gettext("At an unknown time"),
+        |timestamp| format_datetime(last_string, timestamp),
+    )
+}
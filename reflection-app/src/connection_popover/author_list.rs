@@ -75,6 +75,16 @@ mod imp {
                     return gtk::Ordering::Larger;
                 }
 
+                // Authors removed from the document go last
+                match (author_a.removed_at(), author_b.removed_at()) {
+                    (Some(removed_a), Some(removed_b)) => {
+                        return removed_b.cmp(&removed_a).into();
+                    }
+                    (Some(_), None) => return gtk::Ordering::Larger,
+                    (None, Some(_)) => return gtk::Ordering::Smaller,
+                    (None, None) => {}
+                }
+
                 if author_a.is_online() && author_b.is_online() {
                     return gtk::Ordering::Equal;
                 } else if author_a.is_online() {
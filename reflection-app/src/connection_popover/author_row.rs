@@ -61,7 +61,9 @@ mod imp {
             let author_borrow = self.author.borrow();
             let author = author_borrow.as_ref()?;
 
-            if author.is_online() {
+            if let Some(removed_at) = author.removed_at() {
+                Some(format_datetime(&gettext("Removed"), &removed_at))
+            } else if author.is_online() {
                 Some(gettext("Online"))
             } else if let Some(last_seen) = author.last_seen() {
                 if author.is_this_device() {
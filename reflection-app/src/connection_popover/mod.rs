@@ -20,11 +20,16 @@
 
 use std::cell::RefCell;
 
+use adw::prelude::*;
 use adw::subclass::prelude::*;
-use gtk::prelude::*;
+use futures_util::StreamExt;
+use gettextrs::{gettext, ngettext};
 use gtk::{gio, glib, glib::clone};
 
-use reflection_doc::{document::Document, service::ConnectionMode};
+use reflection_doc::{
+    document::Document,
+    service::{ConnectionMode, PeerConnectionType, PeerInfo},
+};
 
 mod author_list;
 mod author_row;
@@ -51,11 +56,22 @@ mod imp {
         no_network_revealer: TemplateChild<gtk::Revealer>,
         #[template_child]
         network_toggle_image: TemplateChild<gtk::Image>,
+        #[template_child]
+        sync_directory_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        sync_directory_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        popover_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        peer_list: TemplateChild<gtk::ListBox>,
         #[property(get, set = Self::set_document)]
         document: RefCell<Option<Document>>,
         #[property(get, set)]
         popover: RefCell<Option<Document>>,
         connection_mode_binding: RefCell<Option<glib::Binding>>,
+        sync_directory_binding: RefCell<Option<glib::Binding>>,
+        /// Updates the peer list while the network details are shown.
+        peers_task: RefCell<Option<glib::JoinHandle<()>>>,
     }
 
     #[glib::object_subclass]
@@ -68,6 +84,7 @@ mod imp {
             AuthorList::static_type();
             AuthorsStack::static_type();
             klass.bind_template();
+            klass.bind_template_callbacks();
         }
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
             obj.init_template();
@@ -101,11 +118,56 @@ mod imp {
         }
     }
 
+    #[gtk::template_callbacks]
+    impl ConnectionPopover {
+        #[template_callback]
+        fn show_network_details(&self) {
+            self.popover_stack.set_visible_child_name("network-details");
+            self.watch_peers();
+        }
+
+        #[template_callback]
+        fn show_connection_mode(&self) {
+            self.popover_stack.set_visible_child_name("connection-mode");
+            if let Some(peers_task) = self.peers_task.take() {
+                peers_task.abort();
+            }
+        }
+
+        #[template_callback]
+        fn choose_sync_directory(&self) {
+            let Some(document) = self.document.borrow().clone() else {
+                return;
+            };
+
+            let dialog = gtk::FileDialog::builder()
+                .title(gettext("Choose Sync Folder"))
+                .modal(true)
+                .build();
+            if let Some(directory) = document.service().sync_directory() {
+                dialog.set_initial_folder(Some(&directory));
+            }
+
+            let window = self.obj().root().and_downcast::<gtk::Window>();
+            glib::spawn_future_local(async move {
+                // The dialog was cancelled
+                let Ok(directory) = dialog.select_folder_future(window.as_ref()).await else {
+                    return;
+                };
+
+                document.service().set_sync_directory(Some(&directory));
+            });
+        }
+    }
+
     impl ConnectionPopover {
         fn set_document(&self, document: Option<Document>) {
             if let Some(binding) = self.connection_mode_binding.take() {
                 binding.unbind();
             }
+            if let Some(binding) = self.sync_directory_binding.take() {
+                binding.unbind();
+            }
 
             let Some(document) = document else {
                 self.document.replace(document);
@@ -128,6 +190,8 @@ mod imp {
                         ConnectionMode::None => "offline",
                         ConnectionMode::Bluetooth => "bluetooth",
                         ConnectionMode::Network => "network",
+                        ConnectionMode::LocalNetwork => "local-network",
+                        ConnectionMode::Directory => "directory",
                     };
                     Some(active_name)
                 })
@@ -136,6 +200,8 @@ mod imp {
                         "offline" => ConnectionMode::None,
                         "bluetooth" => ConnectionMode::Bluetooth,
                         "network" => ConnectionMode::Network,
+                        "local-network" => ConnectionMode::LocalNetwork,
+                        "directory" => ConnectionMode::Directory,
                         _ => return None,
                     };
                     Some(mode)
@@ -143,9 +209,77 @@ mod imp {
                 .build();
 
             self.connection_mode_binding.replace(Some(binding));
+
+            let binding = document
+                .service()
+                .bind_property("sync-directory", &self.sync_directory_label.get(), "label")
+                .sync_create()
+                .transform_to(|_, directory: Option<gio::File>| {
+                    let label = directory
+                        .and_then(|directory| directory.path())
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_else(|| gettext("No Folder Chosen"));
+                    Some(label)
+                })
+                .build();
+
+            self.sync_directory_binding.replace(Some(binding));
             self.document.replace(Some(document));
         }
 
+        fn watch_peers(&self) {
+            let Some(document) = self.document.borrow().clone() else {
+                return;
+            };
+
+            let mut peers_changed = document.service().peers_changed();
+            let obj = self.obj().downgrade();
+            let peers_task = glib::spawn_future_local(async move {
+                while let Some(peers) = peers_changed.next().await {
+                    let Some(obj) = obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().update_peer_list(&peers);
+                }
+            });
+
+            if let Some(old_peers_task) = self.peers_task.replace(Some(peers_task)) {
+                old_peers_task.abort();
+            }
+        }
+
+        fn update_peer_list(&self, peers: &[PeerInfo]) {
+            self.peer_list.remove_all();
+
+            for peer in peers {
+                let id = peer.id.to_hex();
+                let row = adw::ExpanderRow::builder()
+                    .title(&id[..8])
+                    .subtitle(format_peer_subtitle(peer))
+                    .build();
+
+                let id_row = adw::ActionRow::builder()
+                    .title(gettext("Endpoint ID"))
+                    .subtitle(&id)
+                    .subtitle_selectable(true)
+                    .css_classes(["property"])
+                    .build();
+                row.add_row(&id_row);
+
+                for address in &peer.addresses {
+                    let address_row = adw::ActionRow::builder()
+                        .title(gettext("Address"))
+                        .subtitle(address)
+                        .subtitle_selectable(true)
+                        .css_classes(["property"])
+                        .build();
+                    row.add_row(&address_row);
+                }
+
+                self.peer_list.append(&row);
+            }
+        }
+
         fn update_no_network_revealer(&self) {
             let monitor = gio::NetworkMonitor::default();
             let wants_network = self
@@ -155,6 +289,11 @@ mod imp {
             let is_offline = !monitor.is_network_available() && wants_network;
 
             self.no_network_revealer.set_reveal_child(is_offline);
+            self.sync_directory_revealer.set_reveal_child(
+                self.connection_mode_switch
+                    .active_name()
+                    .is_some_and(|name| name.as_str() == "directory"),
+            );
             if monitor.is_network_available() {
                 self.network_toggle_image
                     .set_icon_name(Some("network-symbolic"));
@@ -168,6 +307,8 @@ mod imp {
                     "offline" => Some("offline"),
                     "network" if !monitor.is_network_available() => Some("no-network"),
                     "network" => Some("network"),
+                    "local-network" => Some("local-network"),
+                    "directory" => Some("directory"),
                     _ => None,
                 };
                 if let Some(page_name) = page_name {
@@ -181,6 +322,29 @@ mod imp {
     impl BinImpl for ConnectionPopover {}
 }
 
+fn format_peer_subtitle(peer: &PeerInfo) -> String {
+    let connection_type = match peer.connection_type {
+        PeerConnectionType::None => gettext("Not connected"),
+        PeerConnectionType::Direct => gettext("Direct"),
+        PeerConnectionType::LocalNetwork => gettext("Local network"),
+        PeerConnectionType::Relay => gettext("Relay"),
+        PeerConnectionType::Mixed => gettext("Relay, switching to direct"),
+        PeerConnectionType::InMemory => gettext("In memory"),
+    };
+
+    let mut parts = vec![connection_type];
+    if let Some(round_trip_time) = peer.round_trip_time {
+        parts.push(gettext("{} ms").replace("{}", &round_trip_time.as_millis().to_string()));
+    }
+    let topics = peer.topics.len() as u32;
+    parts.push(
+        ngettext("{} shared document", "{} shared documents", topics)
+            .replace("{}", &topics.to_string()),
+    );
+
+    parts.join(" · ")
+}
+
 glib::wrapper! {
     pub struct ConnectionPopover(ObjectSubclass<imp::ConnectionPopover>)
         @extends gtk::Widget, adw::Bin,
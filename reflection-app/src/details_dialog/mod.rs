@@ -0,0 +1,181 @@
+/* Copyright 2026 The Reflection Developers
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use std::cell::RefCell;
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{gdk, glib, glib::clone};
+use tracing::error;
+
+use crate::utils::format_datetime;
+use reflection_doc::document::Document;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(file = "src/details_dialog/details_dialog.blp")]
+    pub struct DetailsDialog {
+        #[template_child]
+        title_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        icon_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        description_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        color_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        color_button: TemplateChild<gtk::ColorDialogButton>,
+        #[template_child]
+        created_row: TemplateChild<adw::ActionRow>,
+        document: RefCell<Option<Document>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DetailsDialog {
+        const NAME: &'static str = "ReflectionDetailsDialog";
+        type Type = super::DetailsDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for DetailsDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.title_row.connect_apply(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |row| {
+                    this.update(|document| document.set_title(Some(&row.text())));
+                }
+            ));
+
+            self.icon_row.connect_apply(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |row| {
+                    this.update(|document| document.set_icon(Some(&row.text())));
+                }
+            ));
+
+            self.description_row.connect_apply(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |row| {
+                    this.update(|document| document.set_description(Some(&row.text())));
+                }
+            ));
+
+            self.color_button.connect_rgba_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |button| {
+                    let color = hex_color(&button.rgba());
+                    this.update(|document| {
+                        if document.color().as_deref() == Some(color.as_str()) {
+                            return Ok(());
+                        }
+
+                        document.set_color(Some(&color))
+                    });
+                }
+            ));
+        }
+    }
+
+    impl DetailsDialog {
+        pub(super) fn set_document(&self, document: &Document) {
+            self.document.replace(Some(document.clone()));
+
+            self.title_row
+                .set_text(&document.title().unwrap_or_default());
+            self.icon_row.set_text(&document.icon().unwrap_or_default());
+            self.description_row
+                .set_text(&document.description().unwrap_or_default());
+            if let Some(color) = document
+                .color()
+                .and_then(|color| gdk::RGBA::parse(color).ok())
+            {
+                self.color_button.set_rgba(&color);
+            }
+            self.created_row.set_title(&document.created().map_or_else(
+                || gettext("Creation date unknown"),
+                |created| format_datetime(&gettext("Created"), &created),
+            ));
+
+            let editable = !document.read_only();
+            for row in [
+                self.title_row.upcast_ref::<gtk::Widget>(),
+                self.icon_row.upcast_ref(),
+                self.description_row.upcast_ref(),
+                self.color_row.upcast_ref(),
+            ] {
+                row.set_sensitive(editable);
+            }
+        }
+
+        /// Change the metadata of the document with `update`.
+        fn update<E: std::fmt::Display>(&self, update: impl FnOnce(&Document) -> Result<(), E>) {
+            let Some(document) = self.document.borrow().clone() else {
+                return;
+            };
+
+            if let Err(error) = update(&document) {
+                error!("Failed to change details of document: {error}");
+            }
+        }
+    }
+
+    impl WidgetImpl for DetailsDialog {}
+    impl AdwDialogImpl for DetailsDialog {}
+}
+
+glib::wrapper! {
+    pub struct DetailsDialog(ObjectSubclass<imp::DetailsDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl DetailsDialog {
+    /// Show the title, emoji, description, color and creation date of `document`.
+    pub fn new(document: &Document) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().set_document(document);
+        obj
+    }
+}
+
+/// The color in hex format, e.g. `#3584e4`.
+fn hex_color(color: &gdk::RGBA) -> String {
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        (color.red() * 255.0).round() as u8,
+        (color.green() * 255.0).round() as u8,
+        (color.blue() * 255.0).round() as u8,
+    )
+}
@@ -0,0 +1,197 @@
+/* Copyright 2026 The Reflection Developers
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gtk::{gdk, glib, glib::clone};
+use tracing::error;
+
+use crate::history_sidebar::describe_checkpoint;
+use reflection_doc::author::Author;
+use reflection_doc::document::{Checkpoint, DiffKind, Document};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(file = "src/diff_dialog/diff_dialog.blp")]
+    pub struct DiffDialog {
+        #[template_child]
+        from_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        to_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        diff_view: TemplateChild<gtk::TextView>,
+        document: RefCell<Option<Document>>,
+        checkpoints: RefCell<Vec<Checkpoint>>,
+        tags: RefCell<HashMap<String, gtk::TextTag>>,
+        show_diff_handle: RefCell<Option<glib::JoinHandle<()>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DiffDialog {
+        const NAME: &'static str = "ReflectionDiffDialog";
+        type Type = super::DiffDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for DiffDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            for dropdown in [&*self.from_dropdown, &*self.to_dropdown] {
+                dropdown.connect_selected_notify(clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |_| {
+                        this.show_diff();
+                    }
+                ));
+            }
+        }
+    }
+
+    impl DiffDialog {
+        pub(super) fn set_document(&self, document: &Document, from: u32) {
+            let checkpoints = document.checkpoints();
+            let labels: Vec<String> = checkpoints
+                .iter()
+                .map(|checkpoint| {
+                    let (author, changed) = describe_checkpoint(checkpoint);
+                    format!("{changed} ({author})")
+                })
+                .collect();
+            let model =
+                gtk::StringList::new(&labels.iter().map(String::as_str).collect::<Vec<_>>());
+            let to = checkpoints.len().saturating_sub(1) as u32;
+
+            self.checkpoints.replace(checkpoints);
+
+            self.from_dropdown.set_model(Some(&model));
+            self.to_dropdown.set_model(Some(&model));
+            self.from_dropdown.set_selected(from);
+            self.to_dropdown.set_selected(to);
+            // Compare the versions only once, not on every change of the selection above
+            self.document.replace(Some(document.clone()));
+            self.show_diff();
+        }
+
+        fn show_diff(&self) {
+            // The result of comparing the previously selected versions isn't needed anymore
+            if let Some(handle) = self.show_diff_handle.take() {
+                handle.abort();
+            }
+            self.diff_view.buffer().set_text("");
+
+            let Some(document) = self.document.borrow().clone() else {
+                return;
+            };
+            let checkpoints = self.checkpoints.borrow();
+            let (Some(from), Some(to)) = (
+                checkpoints
+                    .get(self.from_dropdown.selected() as usize)
+                    .cloned(),
+                checkpoints
+                    .get(self.to_dropdown.selected() as usize)
+                    .cloned(),
+            ) else {
+                return;
+            };
+
+            let handle = glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    let spans = match document.diff(&from, &to).await {
+                        Ok(spans) => spans,
+                        Err(error) => {
+                            error!("Failed to compare versions of document: {error}");
+                            return;
+                        }
+                    };
+
+                    let buffer = this.diff_view.buffer();
+                    for span in spans {
+                        let mut end = buffer.end_iter();
+                        if span.kind == DiffKind::Unchanged {
+                            buffer.insert(&mut end, &span.text);
+                        } else {
+                            let tag = this.tag(span.kind, span.author.as_ref());
+                            buffer.insert_with_tags(&mut end, &span.text, &[&tag]);
+                        }
+                    }
+                }
+            ));
+            self.show_diff_handle.replace(Some(handle));
+        }
+
+        /// Tag highlighting inserted or deleted text with the color of its author, like the
+        /// selections of others in the text view.
+        fn tag(&self, kind: DiffKind, author: Option<&Author>) -> gtk::TextTag {
+            let hex_color = author.map(|author| author.hex_color());
+            let name = format!("{kind:?}-{}", hex_color.as_deref().unwrap_or_default());
+            let mut tags = self.tags.borrow_mut();
+            if let Some(tag) = tags.get(&name) {
+                return tag.clone();
+            }
+
+            let color = hex_color
+                .and_then(|hex_color| gdk::RGBA::parse(hex_color).ok())
+                .unwrap_or(gdk::RGBA::BLACK)
+                .with_alpha(0.5);
+            let tag = gtk::TextTag::builder()
+                .background_rgba(&color)
+                .strikethrough(kind == DiffKind::Deleted)
+                .build();
+            self.diff_view.buffer().tag_table().add(&tag);
+            tags.insert(name, tag.clone());
+
+            tag
+        }
+    }
+
+    impl WidgetImpl for DiffDialog {}
+    impl AdwDialogImpl for DiffDialog {}
+}
+
+glib::wrapper! {
+    pub struct DiffDialog(ObjectSubclass<imp::DiffDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl DiffDialog {
+    /// Dialog comparing two versions of `document`, initially the checkpoint at index `from` of
+    /// its history with the most recent one.
+    pub fn new(document: &Document, from: u32) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().set_document(document, from);
+        obj
+    }
+}
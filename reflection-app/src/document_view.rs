@@ -18,16 +18,22 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
-use std::cell::{Cell, RefCell};
+use std::cell::{Cell, OnceCell, RefCell};
 
-use reflection_doc::document::{Document, DocumentId};
+use reflection_doc::document::{Document, SyncState};
 
 use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
 use gtk::{gdk, glib, glib::clone};
+use tracing::error;
 
 use crate::{
     ConnectionPopover, ReflectionApplication, ReflectionTextBuffer, TextView,
+    action_items_panel::ActionItemsPanel,
+    comments_sidebar::CommentsSidebar,
     components::{MultilineEntry, ZoomLevelSelector},
+    details_dialog::DetailsDialog,
+    history_sidebar::HistorySidebar,
 };
 
 const BASE_TEXT_FONT_SIZE: f64 = 11.0;
@@ -56,6 +62,7 @@ mod imp {
         pub zoom_level: Cell<f64>,
         #[property(get, set = Self::set_document, nullable)]
         document: RefCell<Option<Document>>,
+        document_handlers: OnceCell<glib::SignalGroup>,
     }
 
     #[glib::object_subclass]
@@ -69,8 +76,39 @@ mod imp {
             MultilineEntry::static_type();
             TextView::static_type();
             ConnectionPopover::static_type();
+            HistorySidebar::static_type();
+            ActionItemsPanel::static_type();
+            CommentsSidebar::static_type();
 
             klass.bind_template();
+            klass.bind_template_callbacks();
+
+            klass.install_action("document.dismiss-changes", None, |window, _, _| {
+                if let Some(document) = window.document() {
+                    document.dismiss_changes_since_last_seen();
+                }
+            });
+
+            klass.install_action("document.create-tag", None, |window, _, _| {
+                window.imp().create_tag();
+            });
+
+            klass.install_action("document.edit-details", None, |window, _, _| {
+                if let Some(document) = window.document() {
+                    DetailsDialog::new(&document).present(Some(window));
+                }
+            });
+
+            klass.install_action("document.export-to-file", None, |window, _, _| {
+                // The document may be a read-only version, which isn't known by its id
+                if let Some(document) = window.document() {
+                    ReflectionApplication::default().export_to_file(&document);
+                }
+            });
+
+            klass.install_action("document.add-comment", None, |window, _, _| {
+                window.imp().add_comment();
+            });
 
             klass.install_action("window.zoom-in", None, |window, _, _| {
                 window.set_font_scale(window.font_scale() + 1.0);
@@ -135,6 +173,33 @@ mod imp {
             self.text_view.set_buffer(Some(&buffer));
 
             buffer.tag_table().add(&self.zoom_tag);
+            buffer.connect_has_selection_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    this.update_add_comment_action();
+                }
+            ));
+            self.update_add_comment_action();
+
+            // Comments are highlighted in the text
+            let document_handlers = glib::SignalGroup::with_type(Document::static_type());
+            document_handlers.connect_local(
+                "comments-changed",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.text_view.queue_draw();
+                        None
+                    }
+                ),
+            );
+            self.document_handlers.set(document_handlers).unwrap();
+
             buffer.connect_changed(clone!(
                 #[weak(rename_to = this)]
                 self,
@@ -202,18 +267,39 @@ mod imp {
                 #[weak(rename_to = this)]
                 self,
                 move |button| {
-                    let Some(document) = this.obj().document() else {
+                    let Some(invite_code) = this
+                        .obj()
+                        .document()
+                        .and_then(|document| document.invite_code())
+                    else {
                         return;
                     };
-                    let document_id = Self::format_document_id(&document.id());
+                    let invite_code = Self::format_invite_code(&invite_code);
                     let clipboard = button.display().clipboard();
-                    clipboard.set(&document_id);
+                    clipboard.set(&invite_code);
                     this.share_popover.popdown();
                 }
             ));
         }
     }
 
+    #[gtk::template_callbacks(functions)]
+    impl DocumentView {
+        #[template_callback]
+        fn format_sync_state(sync_state: SyncState, syncing_peers: u32) -> String {
+            match sync_state {
+                SyncState::Idle => String::new(),
+                SyncState::Syncing => ngettext(
+                    "Syncing with {} peer…",
+                    "Syncing with {} peers…",
+                    syncing_peers,
+                )
+                .replace("{}", &syncing_peers.to_string()),
+                SyncState::UpToDate => gettext("Up to date"),
+            }
+        }
+    }
+
     impl DocumentView {
         fn set_font_scale(&self, value: f64) {
             let font_size = self.font_size.get();
@@ -235,8 +321,23 @@ mod imp {
 
         fn set_document(&self, document: Option<Document>) {
             if let Some(ref document) = document {
-                let document_id = Self::format_document_id(&document.id());
-                self.share_code_label.set_text(&document_id);
+                // Pads created before pads were encrypted can't be joined by anyone else
+                let invite_code = document.invite_code();
+                if let Some(ref invite_code) = invite_code {
+                    self.share_code_label
+                        .set_text(&Self::format_invite_code(invite_code));
+                } else {
+                    self.share_code_label.set_text(&gettext(
+                        "This pad was created with an older version and can't be shared, copy its text into a new pad to share it",
+                    ));
+                }
+                self.share_code_label
+                    .set_css_classes(if invite_code.is_some() {
+                        &["invite-code", "monospace"]
+                    } else {
+                        &[]
+                    });
+                self.copy_code_button.set_visible(invite_code.is_some());
             }
 
             self.text_view
@@ -244,8 +345,13 @@ mod imp {
                 .downcast::<ReflectionTextBuffer>()
                 .unwrap()
                 .set_document(document.as_ref());
+            self.document_handlers
+                .get()
+                .unwrap()
+                .set_target(document.as_ref());
 
             let old_document = self.document.replace(document);
+            self.update_add_comment_action();
 
             if let Some(old_document) = old_document {
                 // We need to make sure that unsubscribe runs
@@ -260,9 +366,115 @@ mod imp {
             self.obj().notify("document");
         }
 
-        fn format_document_id(document_id: &DocumentId) -> String {
-            document_id
-                .to_hex()
+        /// Ask the user for a name for the current version of the document.
+        fn create_tag(&self) {
+            let Some(document) = self.obj().document() else {
+                return;
+            };
+
+            let entry = gtk::Entry::builder()
+                .placeholder_text(gettext("Agreed minutes"))
+                .activates_default(true)
+                .build();
+            let dialog = adw::AlertDialog::builder()
+                .heading(gettext("Name This Version"))
+                .body(gettext("Everyone with access to the pad sees the name"))
+                .extra_child(&entry)
+                .default_response("create")
+                .close_response("cancel")
+                .build();
+            dialog.add_response("cancel", &gettext("Cancel"));
+            dialog.add_response("create", &gettext("_Name Version"));
+            dialog.set_response_appearance("create", adw::ResponseAppearance::Suggested);
+            dialog.set_response_enabled("create", false);
+
+            entry.connect_changed(clone!(
+                #[weak]
+                dialog,
+                move |entry| {
+                    dialog.set_response_enabled("create", !entry.text().trim().is_empty());
+                }
+            ));
+
+            dialog.connect_response(
+                Some("create"),
+                clone!(
+                    #[weak]
+                    entry,
+                    move |_, _| {
+                        if let Err(error) = document.create_tag(&entry.text()) {
+                            error!("Failed to name version of document: {error}");
+                        }
+                    }
+                ),
+            );
+
+            dialog.present(Some(&*self.obj()));
+        }
+
+        fn update_add_comment_action(&self) {
+            let editable = self
+                .obj()
+                .document()
+                .is_some_and(|document| !document.read_only());
+            self.obj().action_set_enabled(
+                "document.add-comment",
+                editable && self.text_view.buffer().has_selection(),
+            );
+        }
+
+        /// Ask the user for a comment on the selected text.
+        fn add_comment(&self) {
+            let Some(document) = self.obj().document() else {
+                return;
+            };
+            let Some((start, end)) = self.text_view.buffer().selection_bounds() else {
+                return;
+            };
+
+            let entry = gtk::Entry::builder()
+                .placeholder_text(gettext("Question or remark"))
+                .activates_default(true)
+                .build();
+            let dialog = adw::AlertDialog::builder()
+                .heading(gettext("Comment on Selection"))
+                .body(gettext("Everyone with access to the pad can reply"))
+                .extra_child(&entry)
+                .default_response("comment")
+                .close_response("cancel")
+                .build();
+            dialog.add_response("cancel", &gettext("Cancel"));
+            dialog.add_response("comment", &gettext("_Comment"));
+            dialog.set_response_appearance("comment", adw::ResponseAppearance::Suggested);
+            dialog.set_response_enabled("comment", false);
+
+            entry.connect_changed(clone!(
+                #[weak]
+                dialog,
+                move |entry| {
+                    dialog.set_response_enabled("comment", !entry.text().trim().is_empty());
+                }
+            ));
+
+            let (start, end) = (start.offset(), end.offset());
+            dialog.connect_response(
+                Some("comment"),
+                clone!(
+                    #[weak]
+                    entry,
+                    move |_, _| {
+                        if let Err(error) = document.add_comment(start, end, entry.text().trim()) {
+                            error!("Failed to comment on document: {error}");
+                        }
+                    }
+                ),
+            );
+
+            dialog.present(Some(&*self.obj()));
+        }
+
+        fn format_invite_code(invite_code: &str) -> String {
+            invite_code
                 .chars()
                 .enumerate()
                 .flat_map(|(i, c)| {
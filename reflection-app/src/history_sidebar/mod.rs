@@ -0,0 +1,204 @@
+/* Copyright 2026 The Reflection Developers
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use std::cell::RefCell;
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{glib, glib::clone};
+use tracing::error;
+
+use crate::diff_dialog::DiffDialog;
+use crate::utils::format_datetime;
+use reflection_doc::document::{Checkpoint, Document};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::HistorySidebar)]
+    #[template(file = "src/history_sidebar/history_sidebar.blp")]
+    pub struct HistorySidebar {
+        #[template_child]
+        timeline_adjustment: TemplateChild<gtk::Adjustment>,
+        #[template_child]
+        checkpoint_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        preview: TemplateChild<gtk::TextView>,
+        #[template_child]
+        compare_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        restore_button: TemplateChild<gtk::Button>,
+        #[property(get, set = Self::set_document, nullable)]
+        document: RefCell<Option<Document>>,
+        checkpoints: RefCell<Vec<Checkpoint>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HistorySidebar {
+        const NAME: &'static str = "ReflectionHistorySidebar";
+        type Type = super::HistorySidebar;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for HistorySidebar {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.timeline_adjustment.connect_value_changed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    this.show_selected_checkpoint();
+                }
+            ));
+
+            self.restore_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    let (Some(document), Some(checkpoint)) =
+                        (this.obj().document(), this.selected_checkpoint())
+                    else {
+                        return;
+                    };
+
+                    if let Err(error) = document.restore(&checkpoint) {
+                        error!("Failed to restore version of document: {error}");
+                    }
+
+                    this.load_checkpoints();
+                }
+            ));
+
+            self.compare_button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    let Some(document) = this.obj().document() else {
+                        return;
+                    };
+
+                    let from = this.timeline_adjustment.value().round() as u32;
+                    DiffDialog::new(&document, from).present(Some(&*this.obj()));
+                }
+            ));
+
+            // The history grows while the document is edited, only load it when it's shown
+            self.obj().connect_map(|obj| {
+                obj.imp().load_checkpoints();
+            });
+        }
+    }
+
+    impl HistorySidebar {
+        fn set_document(&self, document: Option<Document>) {
+            self.document.replace(document);
+
+            if self.obj().is_mapped() {
+                self.load_checkpoints();
+            }
+        }
+
+        /// Load the checkpoints of the document and select the most recent one.
+        fn load_checkpoints(&self) {
+            let checkpoints = self
+                .obj()
+                .document()
+                .map(|document| document.checkpoints())
+                .unwrap_or_default();
+            let upper = checkpoints.len().saturating_sub(1) as f64;
+            self.checkpoints.replace(checkpoints);
+
+            self.timeline_adjustment.set_upper(upper);
+            self.timeline_adjustment.set_value(upper);
+            // The value may not have changed
+            self.show_selected_checkpoint();
+        }
+
+        fn selected_checkpoint(&self) -> Option<Checkpoint> {
+            let index = self.timeline_adjustment.value().round() as usize;
+            self.checkpoints.borrow().get(index).cloned()
+        }
+
+        fn show_selected_checkpoint(&self) {
+            let Some(document) = self.obj().document() else {
+                return;
+            };
+            let Some(checkpoint) = self.selected_checkpoint() else {
+                self.checkpoint_label.set_label(&gettext("No changes yet"));
+                self.preview.buffer().set_text("");
+                self.compare_button.set_sensitive(false);
+                self.restore_button.set_sensitive(false);
+                return;
+            };
+
+            let (author, changed) = describe_checkpoint(&checkpoint);
+            self.checkpoint_label
+                .set_label(&format!("{author}\n{changed}"));
+
+            match document.text_at(&checkpoint) {
+                Ok(text) => {
+                    self.preview.buffer().set_text(&text);
+                    self.compare_button.set_sensitive(true);
+                    // Old versions of the document can't be changed
+                    self.restore_button.set_sensitive(!document.read_only());
+                }
+                Err(error) => {
+                    error!("Failed to get text of version of document: {error}");
+                    self.preview.buffer().set_text("");
+                    self.compare_button.set_sensitive(false);
+                    self.restore_button.set_sensitive(false);
+                }
+            }
+        }
+    }
+
+    impl WidgetImpl for HistorySidebar {}
+    impl BinImpl for HistorySidebar {}
+}
+
+glib::wrapper! {
+    pub struct HistorySidebar(ObjectSubclass<imp::HistorySidebar>)
+        @extends gtk::Widget, adw::Bin,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+/// The author of `checkpoint` and when it was made, for showing it to the user.
+pub(crate) fn describe_checkpoint(checkpoint: &Checkpoint) -> (String, String) {
+    let author = checkpoint
+        .author
+        .as_ref()
+        .map_or_else(|| gettext("Unknown author"), |author| author.name());
+    let changed = checkpoint.timestamp.as_ref().map_or_else(
+        || gettext("Changed at an unknown time"),
+        |timestamp| format_datetime(&gettext("Changed"), timestamp),
+    );
+
+    (author, changed)
+}
@@ -117,6 +117,22 @@ mod imp {
 
             let target = Self::transform_action_target(Some(document.id()));
             let menu = menu_set_action_target(&self.menu_model, Some(&target));
+
+            // Keeping a pad in sync is a setting of the pad itself, so it has no target
+            let actions = gio::SimpleActionGroup::new();
+            actions.add_action(&gio::PropertyAction::new(
+                "keep-in-sync",
+                document,
+                "keep-in-sync",
+            ));
+            self.obj().insert_action_group("row", Some(&actions));
+
+            let section = gio::Menu::new();
+            section.append(Some(&gettext("_Keep in Sync")), Some("row.keep-in-sync"));
+            if let Some(menu) = menu.downcast_ref::<gio::Menu>() {
+                menu.append_section(None, &section);
+            }
+
             self.menu_button.set_menu_model(Some(&menu));
         }
     }
@@ -16,6 +16,9 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
+use std::cell::RefCell;
+
+use gettextrs::{gettext, ngettext};
 use gtk::{
     glib,
     glib::clone,
@@ -24,13 +27,20 @@ use gtk::{
 
 mod document_row;
 
+use crate::application::ReflectionApplication;
 use document_row::DocumentRow;
-use reflection_doc::{document::Document, documents::Documents};
+use reflection_doc::{
+    document::Document,
+    documents::{AssignedTask, Documents},
+};
 
 mod imp {
     use super::*;
 
-    use adw::prelude::{Cast, EditableExt, ListModelExt, SorterExt, StaticType, WidgetExt};
+    use adw::prelude::{
+        ActionGroupExt, ActionRowExt, Cast, EditableExt, ListModelExt, SorterExt, StaticType,
+        ToVariant, WidgetExt,
+    };
     use adw::subclass::prelude::{
         CompositeTemplateClass, CompositeTemplateInitializingExt, NavigationPageImpl,
         WidgetClassExt, WidgetImpl, WidgetImplExt,
@@ -55,11 +65,16 @@ mod imp {
         #[template_child]
         listbox: TemplateChild<gtk::ListBox>,
         #[template_child]
+        tasks_listbox: TemplateChild<gtk::ListBox>,
+        #[template_child]
         bottom_buttons: TemplateChild<gtk::Box>,
+        #[template_child]
+        unreadable_banner: TemplateChild<adw::Banner>,
 
         #[property(get = Self::model, set = Self::set_model, type = Option<Documents>, nullable)]
         model: gtk::FilterListModel,
         sorter: gtk::CustomSorter,
+        load_tasks_handle: RefCell<Option<glib::JoinHandle<()>>>,
     }
 
     #[glib::object_subclass]
@@ -172,6 +187,26 @@ mod imp {
                 .unwrap();
             sort_list.set_model(model);
             self.sorter.changed(gtk::SorterChange::Different);
+            self.update_unreadable_banner();
+        }
+
+        /// Tell the user about pads which are stored but can't be opened anymore.
+        fn update_unreadable_banner(&self) {
+            let unreadable = self
+                .model()
+                .map(|documents| documents.unreadable())
+                .unwrap_or_default();
+
+            if !unreadable.is_empty() {
+                let title = ngettext(
+                    "The pad {} can't be opened anymore",
+                    "The pads {} can't be opened anymore",
+                    unreadable.len() as u32,
+                )
+                .replace("{}", &unreadable.join(", "));
+                self.unreadable_banner.set_title(&title);
+            }
+            self.unreadable_banner.set_revealed(!unreadable.is_empty());
         }
 
         fn update_stack(&self) {
@@ -197,6 +232,35 @@ mod imp {
                 .set_visible_child_name(visible_child);
             self.bottom_buttons.set_visible(n_items > 0);
         }
+
+        /// Collect the open tasks assigned to us in all documents.
+        fn load_tasks(&self) {
+            let Some(documents) = self.model() else {
+                return;
+            };
+            let Some(service) = ReflectionApplication::default().service() else {
+                return;
+            };
+
+            let handle = glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    let public_key = service.private_key().public_key();
+                    let tasks = documents.tasks_for(&public_key).await;
+
+                    this.tasks_listbox.remove_all();
+                    this.tasks_listbox.set_visible(!tasks.is_empty());
+                    for task in tasks {
+                        this.tasks_listbox.append(&task_row(task));
+                    }
+                }
+            ));
+
+            if let Some(handle) = self.load_tasks_handle.replace(Some(handle)) {
+                handle.abort();
+            }
+        }
     }
 
     impl WidgetImpl for LandingView {
@@ -204,9 +268,30 @@ mod imp {
             self.parent_map();
             // Resort every time before the landing page is shown
             self.sorter.changed(gtk::SorterChange::Different);
+            // Tasks may have been added or done since the landing page was shown last
+            self.load_tasks();
         }
     }
 
+    /// A row showing `task` which opens the document it's in.
+    fn task_row(task: AssignedTask) -> adw::ActionRow {
+        let row = adw::ActionRow::builder()
+            .title(&task.text)
+            .subtitle(task.document.name().unwrap_or_else(|| gettext("Empty Pad")))
+            .use_markup(false)
+            .activatable(true)
+            .build();
+        row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+
+        let document_id = task.document.id();
+        row.connect_activated(move |_| {
+            ReflectionApplication::default()
+                .activate_action("join-document", Some(&[document_id].to_variant()));
+        });
+
+        row
+    }
+
     impl NavigationPageImpl for LandingView {}
 }
 
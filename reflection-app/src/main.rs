@@ -18,12 +18,17 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
+mod action_items_panel;
 mod application;
+mod comments_sidebar;
 mod components;
 mod config;
 mod connection_popover;
+mod details_dialog;
+mod diff_dialog;
 mod document_view;
 mod error_page;
+mod history_sidebar;
 mod landing_view;
 mod open_dialog;
 mod secret;
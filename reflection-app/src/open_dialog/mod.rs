@@ -19,14 +19,17 @@
 use gettextrs::gettext;
 
 use gtk::{
+    gio::prelude::ActionGroupExt,
     glib,
     glib::{clone, variant::ToVariant},
-    prelude::{ActionableExt, ObjectExt},
+    prelude::ObjectExt,
 };
 
-use reflection_doc::document::DocumentId;
+use reflection_doc::document::{Document, INVITE_CODE_LENGTH};
+use tracing::error;
 
 use crate::ReflectionApplication;
+use crate::utils::invite_code_from_input;
 
 mod imp {
     use super::*;
@@ -74,6 +77,7 @@ mod imp {
                 #[weak(rename_to = this)]
                 self,
                 move |_| {
+                    this.join_document();
                     this.obj().close();
                 }
             ));
@@ -82,25 +86,14 @@ mod imp {
                 #[weak(rename_to = this)]
                 self,
                 move |_| {
-                    let buffer = this.open_document_entry.buffer();
-                    let input: String = buffer
-                        .text(&buffer.start_iter(), &buffer.end_iter(), false)
-                        .chars()
-                        .filter(|c| c.is_ascii_hexdigit())
-                        .collect();
-
-                    let document_id = if input.len() == 64 {
-                        DocumentId::from_hex(&input).ok()
-                    } else {
-                        None
-                    };
+                    let document_id = this
+                        .invite_code()
+                        .and_then(|invite_code| Document::parse_invite_code(&invite_code).ok())
+                        .map(|secret| secret.document_id());
                     this.open_document_button
                         .set_sensitive(document_id.is_some());
 
                     let existing = if let Some(document_id) = document_id {
-                        this.open_document_button
-                            .set_action_target_value(Some(&[document_id].to_variant()));
-
                         let app = ReflectionApplication::default();
                         app.window_for_document_id(&document_id)
                     } else {
@@ -159,7 +152,9 @@ mod imp {
                         .filter(|c| c.is_ascii_hexdigit())
                         .count();
 
-                    if trimmed_text.is_empty() || (input_len >= 64 && trimmed_text != " ") {
+                    if trimmed_text.is_empty()
+                        || (input_len >= INVITE_CODE_LENGTH && trimmed_text != " ")
+                    {
                         buffer.stop_signal_emission_by_name("insert-text");
                     } else if new_text != trimmed_text {
                         buffer.stop_signal_emission_by_name("insert-text");
@@ -169,6 +164,39 @@ mod imp {
         }
     }
 
+    impl OpenDialog {
+        fn invite_code(&self) -> Option<String> {
+            let buffer = self.open_document_entry.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            let input = invite_code_from_input(&text);
+
+            if input.len() == INVITE_CODE_LENGTH {
+                Some(input)
+            } else {
+                None
+            }
+        }
+
+        fn join_document(&self) {
+            let Some(invite_code) = self.invite_code() else {
+                return;
+            };
+            let app = ReflectionApplication::default();
+            let Some(service) = app.service() else {
+                return;
+            };
+
+            match service.join_document_with_invite_code(&invite_code) {
+                Ok(document) => {
+                    app.activate_action("join-document", Some(&[document.id()].to_variant()));
+                }
+                Err(error) => {
+                    error!("Failed to join pad: Invalid invite code: {error}");
+                }
+            }
+        }
+    }
+
     impl WidgetImpl for OpenDialog {}
     impl WindowImpl for OpenDialog {}
     impl AdwDialogImpl for OpenDialog {}
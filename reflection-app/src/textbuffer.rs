@@ -26,7 +26,10 @@ use std::{
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{glib, glib::clone};
-use reflection_doc::{author::Author, document::Document};
+use reflection_doc::{
+    author::Author,
+    document::{Document, Mark},
+};
 use sourceview::prelude::BufferExt;
 use sourceview::subclass::prelude::*;
 use sourceview::*;
@@ -48,6 +51,12 @@ mod imp {
         #[property(name = "custom-can-undo", get = Self::custom_can_undo, type = bool)]
         #[property(name = "custom-can-redo", get = Self::custom_can_redo, type = bool)]
         pub(super) remote_cursors: RefCell<HashMap<Author, (gtk::TextMark, gtk::TextMark)>>,
+        /// Tags highlighting the changes since the document was last seen by author color.
+        pub(super) changes_tags: RefCell<HashMap<String, gtk::TextTag>>,
+        pub(super) changes_update_scheduled: Cell<bool>,
+        /// Tags showing the formatting of the document.
+        pub(super) format_tags: RefCell<HashMap<Mark, gtk::TextTag>>,
+        pub(super) formatting_update_scheduled: Cell<bool>,
     }
 
     impl ReflectionTextBuffer {
@@ -99,6 +108,8 @@ mod imp {
             self.remote_cursors.take();
             self.document_handlers.get().unwrap().set_target(document);
             self.document.replace(document.cloned());
+            self.obj().schedule_changes_update();
+            self.obj().schedule_formatting_update();
         }
     }
 
@@ -185,6 +196,23 @@ mod imp {
                 ),
             );
 
+            document_handlers.connect_local(
+                "text-formatted",
+                false,
+                clone!(
+                    #[weak]
+                    buffer,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        // The text may not be inserted into the buffer yet
+                        buffer.schedule_formatting_update();
+
+                        None
+                    }
+                ),
+            );
+
             document_handlers.connect_local(
                 "remote-insert-cursor",
                 false,
@@ -248,6 +276,28 @@ mod imp {
                 ),
             );
 
+            document_handlers.connect_notify_local(
+                Some("text"),
+                clone!(
+                    #[weak]
+                    buffer,
+                    move |_, _| {
+                        buffer.schedule_changes_update();
+                    }
+                ),
+            );
+
+            document_handlers.connect_notify_local(
+                Some("has-changes-since-last-seen"),
+                clone!(
+                    #[weak]
+                    buffer,
+                    move |_, _| {
+                        buffer.schedule_changes_update();
+                    }
+                ),
+            );
+
             document_handlers.connect_notify_local(
                 Some("can-undo"),
                 clone!(
@@ -345,6 +395,32 @@ mod imp {
             }
         }
 
+        fn apply_tag(&self, tag: &gtk::TextTag, start: &gtk::TextIter, end: &gtk::TextIter) {
+            if !self.obj().inhibit_text_change()
+                && let Some(mark) = self.obj().mark_for_tag(tag)
+                && let Some(document) = self.obj().document()
+                && let Err(error) = document.mark(start.offset(), end.offset(), &mark)
+            {
+                error!("Failed to submit formatting to the document: {error}");
+                return;
+            }
+
+            self.parent_apply_tag(tag, start, end);
+        }
+
+        fn remove_tag(&self, tag: &gtk::TextTag, start: &gtk::TextIter, end: &gtk::TextIter) {
+            if !self.obj().inhibit_text_change()
+                && let Some(mark) = self.obj().mark_for_tag(tag)
+                && let Some(document) = self.obj().document()
+                && let Err(error) = document.unmark(start.offset(), end.offset(), &mark)
+            {
+                error!("Failed to submit formatting to the document: {error}");
+                return;
+            }
+
+            self.parent_remove_tag(tag, start, end);
+        }
+
         fn mark_set(&self, location: &gtk::TextIter, mark: &gtk::TextMark) {
             if let Some(name) = mark.name() {
                 match name.as_str() {
@@ -401,6 +477,167 @@ impl ReflectionTextBuffer {
         self.imp().remote_cursors.borrow().to_owned()
     }
 
+    /// Update the highlighted changes since the document was last seen once the buffer is idle.
+    fn schedule_changes_update(&self) {
+        if self.imp().changes_update_scheduled.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.imp().changes_update_scheduled.set(false);
+                obj.update_changes();
+            }
+        ));
+    }
+
+    fn update_changes(&self) {
+        let (start, end) = self.bounds();
+        for tag in self.imp().changes_tags.borrow().values() {
+            self.remove_tag(tag, &start, &end);
+        }
+
+        let Some(document) = self.document() else {
+            return;
+        };
+
+        if !document.has_changes_since_last_seen() {
+            return;
+        }
+
+        for change in document.changes_since_last_seen() {
+            let tag = self.changes_tag(&change.author.hex_color());
+            self.apply_tag(
+                &tag,
+                &self.iter_at_offset(change.start),
+                &self.iter_at_offset(change.end),
+            );
+        }
+    }
+
+    /// Tag tinting the background of changes with the given author color.
+    fn changes_tag(&self, hex_color: &str) -> gtk::TextTag {
+        let mut changes_tags = self.imp().changes_tags.borrow_mut();
+        if let Some(tag) = changes_tags.get(hex_color) {
+            return tag.clone();
+        }
+
+        let color = gtk::gdk::RGBA::parse(hex_color)
+            .unwrap_or(gtk::gdk::RGBA::BLACK)
+            .with_alpha(0.3);
+        let tag = gtk::TextTag::builder().background_rgba(&color).build();
+        self.tag_table().add(&tag);
+        changes_tags.insert(hex_color.to_owned(), tag.clone());
+
+        tag
+    }
+
+    /// Update the formatting from the document once the buffer is idle.
+    fn schedule_formatting_update(&self) {
+        if self.imp().formatting_update_scheduled.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.imp().formatting_update_scheduled.set(false);
+                obj.update_formatting();
+            }
+        ));
+    }
+
+    fn update_formatting(&self) {
+        // The formatting comes from the document, it must not be sent back to it
+        self.set_inhibit_text_change(true);
+
+        let (start, end) = self.bounds();
+        let format_tags: Vec<_> = self.imp().format_tags.borrow().values().cloned().collect();
+        for tag in format_tags {
+            self.remove_tag(&tag, &start, &end);
+        }
+
+        if let Some(document) = self.document() {
+            for range in document.marks() {
+                let tag = self.format_tag(&range.mark);
+                self.apply_tag(
+                    &tag,
+                    &self.iter_at_offset(range.start),
+                    &self.iter_at_offset(range.end),
+                );
+            }
+        }
+
+        self.set_inhibit_text_change(false);
+    }
+
+    /// Tag showing text formatted with `mark`.
+    fn format_tag(&self, mark: &Mark) -> gtk::TextTag {
+        if let Some(tag) = self.imp().format_tags.borrow().get(mark) {
+            return tag.clone();
+        }
+
+        let tag = match mark {
+            Mark::Bold => gtk::TextTag::builder().weight(700).build(),
+            Mark::Italic => gtk::TextTag::builder()
+                .style(gtk::pango::Style::Italic)
+                .build(),
+            Mark::Underline => gtk::TextTag::builder()
+                .underline(gtk::pango::Underline::Single)
+                .build(),
+            Mark::Strikethrough => gtk::TextTag::builder().strikethrough(true).build(),
+            Mark::Link(_) => gtk::TextTag::builder()
+                .underline(gtk::pango::Underline::Single)
+                .foreground_rgba(&adw::StyleManager::default().accent_color_rgba())
+                .build(),
+            Mark::Heading(level) => gtk::TextTag::builder()
+                .weight(700)
+                .scale(match level {
+                    1 => 1.6,
+                    2 => 1.4,
+                    3 => 1.2,
+                    _ => 1.0,
+                })
+                .build(),
+        };
+        self.tag_table().add(&tag);
+        self.imp()
+            .format_tags
+            .borrow_mut()
+            .insert(mark.clone(), tag.clone());
+
+        tag
+    }
+
+    /// The formatting shown by `tag`, `None` if it doesn't show any.
+    fn mark_for_tag(&self, tag: &gtk::TextTag) -> Option<Mark> {
+        self.imp()
+            .format_tags
+            .borrow()
+            .iter()
+            .find(|(_, format_tag)| *format_tag == tag)
+            .map(|(mark, _)| mark.clone())
+    }
+
+    /// Format the selected text with `mark`, or remove it when all of the text has it already.
+    pub fn toggle_mark(&self, mark: &Mark) {
+        let Some((start, end)) = self.selection_bounds() else {
+            return;
+        };
+        let tag = self.format_tag(mark);
+
+        let mut iter = start;
+        let marked = iter.has_tag(&tag) && (!iter.forward_to_tag_toggle(Some(&tag)) || iter >= end);
+        if marked {
+            self.remove_tag(&tag, &start, &end);
+        } else {
+            self.apply_tag(&tag, &start, &end);
+        }
+    }
+
     pub fn custom_undo(&self) {
         if let Some(document) = self.document() {
             let (insert_cursor, selection_bound) = document.undo();
@@ -21,14 +21,26 @@ use gtk::{prelude::*, subclass::prelude::*};
 use sourceview::subclass::prelude::ViewImpl;
 
 use crate::textbuffer::ReflectionTextBuffer;
+use reflection_doc::authors::Authors;
+use reflection_doc::document::{AuthoredRange, Document, Mark};
 
 mod imp {
     use super::*;
 
-    use std::cell::RefCell;
+    use std::cell::{Cell, OnceCell, RefCell};
 
-    #[derive(Debug, Default)]
+    #[derive(Debug, Default, glib::Properties)]
+    #[properties(wrapper_type = super::TextView)]
     pub struct TextView {
+        /// Whether the text is tinted with the color of the author who wrote it.
+        #[property(get, set = Self::set_show_authorship)]
+        show_authorship: Cell<bool>,
+        /// Authorship of the visible text, looked up again only once the text, the visible
+        /// range or the authors change.
+        authorship: RefCell<Option<(std::ops::Range<i32>, Vec<AuthoredRange>)>>,
+        buffer_handlers: OnceCell<glib::SignalGroup>,
+        document_handlers: OnceCell<glib::SignalGroup>,
+        authors_handlers: OnceCell<glib::SignalGroup>,
         spelling_adapter: RefCell<Option<libspelling::TextBufferAdapter>>,
         buffer_notify_handler:
             RefCell<Option<(glib::WeakRef<ReflectionTextBuffer>, glib::SignalHandlerId)>>,
@@ -61,9 +73,33 @@ mod imp {
                 buffer.custom_redo();
                 view.scroll_mark_onscreen(&buffer.get_insert());
             });
+
+            // Toggle the formatting of the selected text
+            let formatting = [
+                ("text.bold", Mark::Bold, "<Ctrl>b"),
+                ("text.italic", Mark::Italic, "<Ctrl>i"),
+                ("text.underline", Mark::Underline, "<Ctrl>u"),
+                ("text.strikethrough", Mark::Strikethrough, "<Ctrl><Shift>x"),
+            ];
+            for (action_name, mark, accelerator) in formatting {
+                klass.install_action(action_name, None, move |view, _, _| {
+                    let Ok(buffer): Result<ReflectionTextBuffer, _> = view.buffer().downcast()
+                    else {
+                        return;
+                    };
+
+                    if view.is_editable() {
+                        buffer.toggle_mark(&mark);
+                    }
+                });
+                let (key, modifiers) =
+                    gtk::accelerator_parse(accelerator).expect("Accelerator to be valid");
+                klass.add_binding_action(key, modifiers, action_name);
+            }
         }
     }
 
+    #[glib::derived_properties]
     impl ObjectImpl for TextView {
         fn constructed(&self) {
             self.parent_constructed();
@@ -108,12 +144,83 @@ mod imp {
             ));
             self.obj().add_controller(controller);
 
+            let buffer_handlers = glib::SignalGroup::with_type(ReflectionTextBuffer::static_type());
+            buffer_handlers.connect_local(
+                "changed",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.invalidate_authorship();
+                        None
+                    }
+                ),
+            );
+            buffer_handlers.connect_local(
+                "notify::document",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.update_document();
+                        None
+                    }
+                ),
+            );
+            self.buffer_handlers.set(buffer_handlers).unwrap();
+
+            let document_handlers = glib::SignalGroup::with_type(Document::static_type());
+            document_handlers.connect_local(
+                "action-items-changed",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.invalidate_authorship();
+                        None
+                    }
+                ),
+            );
+            self.document_handlers.set(document_handlers).unwrap();
+
+            let authors_handlers = glib::SignalGroup::with_type(Authors::static_type());
+            authors_handlers.connect_local(
+                "items-changed",
+                false,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    #[upgrade_or]
+                    None,
+                    move |_| {
+                        this.invalidate_authorship();
+                        None
+                    }
+                ),
+            );
+            self.authors_handlers.set(authors_handlers).unwrap();
+
             self.obj().connect_notify_local(
                 Some("buffer"),
                 clone!(
                     #[weak(rename_to = this)]
                     self,
                     move |view, _| {
+                        this.buffer_handlers
+                            .get()
+                            .unwrap()
+                            .set_target(view.buffer().downcast_ref::<ReflectionTextBuffer>());
+                        this.update_document();
+
                         if let Ok(buffer) = view.buffer().downcast::<sourceview::Buffer>() {
                             let checker = libspelling::Checker::default();
                             let adapter = libspelling::TextBufferAdapter::new(&buffer, &checker);
@@ -181,6 +288,12 @@ mod imp {
                 .downcast()
                 .expect("ReflectionTextView needs to have a ReflectionTextBuffer");
 
+            if self.show_authorship.get() {
+                self.snapshot_authorship(&buffer, &snapshot);
+            }
+
+            self.snapshot_comments(&buffer, &snapshot);
+
             for (author, (mark, selection_mark)) in buffer.remote_cursors().iter() {
                 let mut color =
                     gdk::RGBA::parse(author.hex_color()).expect("Author color to be in hex format");
@@ -207,55 +320,169 @@ mod imp {
                     snapshot.append_color(&color, &bounds);
                 } else {
                     color.set_alpha(0.5);
+                    self.append_range(&snapshot, &iter, &selection_iter, &color);
+                }
+            }
+        }
+    }
 
-                    let start_iter = iter.min(selection_iter);
-                    let end_iter = iter.max(selection_iter);
-                    let start_location = self.obj().iter_location(&start_iter);
-                    let end_location = self.obj().iter_location(&end_iter);
-
-                    if start_location.y() == end_location.y() {
-                        let bounds = graphene::Rect::new(
-                            start_location.x() as f32,
-                            start_location.y() as f32,
-                            (end_location.x() - start_location.x()) as f32,
-                            start_location.height() as f32,
-                        );
+    impl TextView {
+        fn set_show_authorship(&self, show_authorship: bool) {
+            if self.show_authorship.replace(show_authorship) != show_authorship {
+                self.obj().queue_draw();
+            }
+        }
 
-                        snapshot.append_color(&color, &bounds);
-                    } else {
-                        let visible_rect = self.obj().visible_rect();
-
-                        // First line that may be partially selected
-                        let bounds = graphene::Rect::new(
-                            start_location.x() as f32,
-                            start_location.y() as f32,
-                            (visible_rect.width() - self.obj().right_margin() - start_location.x())
-                                as f32,
-                            start_location.height() as f32,
-                        );
-                        snapshot.append_color(&color, &bounds);
-
-                        // Last line that might be partially selected
-                        let bounds = graphene::Rect::new(
-                            self.obj().left_margin() as f32,
-                            end_location.y() as f32,
-                            end_location.x() as f32,
-                            end_location.height() as f32,
-                        );
-                        snapshot.append_color(&color, &bounds);
-
-                        // Lines between the first and last selected line
-                        let bounds = graphene::Rect::new(
-                            self.obj().left_margin() as f32,
-                            (start_location.y() + start_location.height()) as f32,
-                            (visible_rect.width()
-                                - self.obj().right_margin()
-                                - self.obj().left_margin()) as f32,
-                            (end_location.y() - start_location.y() - end_location.height()) as f32,
-                        );
-                        snapshot.append_color(&color, &bounds);
-                    }
+        fn update_document(&self) {
+            let document = self
+                .obj()
+                .buffer()
+                .downcast::<ReflectionTextBuffer>()
+                .ok()
+                .and_then(|buffer| buffer.document());
+
+            self.authors_handlers.get().unwrap().set_target(
+                document
+                    .as_ref()
+                    .map(|document| document.authors())
+                    .as_ref(),
+            );
+            self.document_handlers
+                .get()
+                .unwrap()
+                .set_target(document.as_ref());
+            self.invalidate_authorship();
+        }
+
+        fn invalidate_authorship(&self) {
+            if self.authorship.take().is_some() {
+                self.obj().queue_draw();
+            }
+        }
+
+        /// Tint the visible text with the color of the author who wrote it.
+        fn snapshot_authorship(&self, buffer: &ReflectionTextBuffer, snapshot: &gtk::Snapshot) {
+            let Some(document) = buffer.document() else {
+                return;
+            };
+
+            let visible_rect = self.obj().visible_rect();
+            let start = self
+                .obj()
+                .iter_at_location(visible_rect.x(), visible_rect.y())
+                .unwrap_or_else(|| buffer.start_iter());
+            let end = self
+                .obj()
+                .iter_at_location(
+                    visible_rect.x() + visible_rect.width(),
+                    visible_rect.y() + visible_rect.height(),
+                )
+                .unwrap_or_else(|| buffer.end_iter());
+
+            // Only the visible text is looked up, the document may be long
+            let visible_range = start.offset()..end.offset() + 1;
+            let mut authorship = self.authorship.borrow_mut();
+            if authorship
+                .as_ref()
+                .is_none_or(|(range, _)| *range != visible_range)
+            {
+                let ranges = document.authorship(visible_range.clone());
+                authorship.replace((visible_range, ranges));
+            }
+            let Some((_, ranges)) = authorship.as_ref() else {
+                return;
+            };
+
+            for range in ranges {
+                let color = gdk::RGBA::parse(range.author.hex_color())
+                    .expect("Author color to be in hex format")
+                    .with_alpha(0.3);
+
+                self.append_range(
+                    snapshot,
+                    &buffer.iter_at_offset(range.start),
+                    &buffer.iter_at_offset(range.end),
+                    &color,
+                );
+            }
+        }
+
+        /// Highlight the text which has comments that aren't resolved yet.
+        fn snapshot_comments(&self, buffer: &ReflectionTextBuffer, snapshot: &gtk::Snapshot) {
+            let Some(document) = buffer.document() else {
+                return;
+            };
+
+            // Yellow 3 of the GNOME palette
+            let color = gdk::RGBA::parse("#f6d32d")
+                .expect("Comment color to be in hex format")
+                .with_alpha(0.4);
+            for comment in document.comments() {
+                if comment.resolved {
+                    continue;
                 }
+
+                self.append_range(
+                    snapshot,
+                    &buffer.iter_at_offset(comment.start),
+                    &buffer.iter_at_offset(comment.end),
+                    &color,
+                );
+            }
+        }
+
+        /// Fill the background of the text between `iter` and `bound` with `color`.
+        fn append_range(
+            &self,
+            snapshot: &gtk::Snapshot,
+            iter: &gtk::TextIter,
+            bound: &gtk::TextIter,
+            color: &gdk::RGBA,
+        ) {
+            let start_iter = iter.min(bound);
+            let end_iter = iter.max(bound);
+            let start_location = self.obj().iter_location(start_iter);
+            let end_location = self.obj().iter_location(end_iter);
+
+            if start_location.y() == end_location.y() {
+                let bounds = graphene::Rect::new(
+                    start_location.x() as f32,
+                    start_location.y() as f32,
+                    (end_location.x() - start_location.x()) as f32,
+                    start_location.height() as f32,
+                );
+
+                snapshot.append_color(color, &bounds);
+            } else {
+                let visible_rect = self.obj().visible_rect();
+
+                // First line that may be partially selected
+                let bounds = graphene::Rect::new(
+                    start_location.x() as f32,
+                    start_location.y() as f32,
+                    (visible_rect.width() - self.obj().right_margin() - start_location.x()) as f32,
+                    start_location.height() as f32,
+                );
+                snapshot.append_color(color, &bounds);
+
+                // Last line that might be partially selected
+                let bounds = graphene::Rect::new(
+                    self.obj().left_margin() as f32,
+                    end_location.y() as f32,
+                    end_location.x() as f32,
+                    end_location.height() as f32,
+                );
+                snapshot.append_color(color, &bounds);
+
+                // Lines between the first and last selected line
+                let bounds = graphene::Rect::new(
+                    self.obj().left_margin() as f32,
+                    (start_location.y() + start_location.height()) as f32,
+                    (visible_rect.width() - self.obj().right_margin() - self.obj().left_margin())
+                        as f32,
+                    (end_location.y() - start_location.y() - end_location.height()) as f32,
+                );
+                snapshot.append_color(color, &bounds);
             }
         }
     }
@@ -192,3 +192,9 @@ pub fn menu_set_action_target(
 
     menu.into()
 }
+
+/// The invite code in text the user typed or pasted, invite codes are shown in groups
+/// separated by spaces.
+pub fn invite_code_from_input(input: &str) -> String {
+    input.chars().filter(|c| c.is_ascii_hexdigit()).collect()
+}
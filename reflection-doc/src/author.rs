@@ -82,6 +82,9 @@ mod imp {
         public_key: OnceLock<PublicKey>,
         #[property(get, set, construct_only)]
         pub last_seen: Mutex<Option<glib::DateTime>>,
+        /// When the author was removed from the document.
+        #[property(get, set, construct_only)]
+        pub removed_at: Mutex<Option<glib::DateTime>>,
         #[property(get, default = true)]
         pub is_online: Cell<bool>,
         #[property(get)]
@@ -151,10 +154,15 @@ impl Author {
             .build()
     }
 
-    pub(crate) fn with_state(public_key: &PublicKey, last_seen: Option<&glib::DateTime>) -> Self {
+    pub(crate) fn with_state(
+        public_key: &PublicKey,
+        last_seen: Option<&glib::DateTime>,
+        removed_at: Option<&glib::DateTime>,
+    ) -> Self {
         glib::Object::builder()
             .property("public-key", public_key)
             .property("last-seen", last_seen)
+            .property("removed-at", removed_at)
             .build()
     }
 
@@ -162,7 +170,7 @@ impl Author {
         public_key: &PublicKey,
         last_seen: Option<&glib::DateTime>,
     ) -> Self {
-        let obj = Self::with_state(public_key, last_seen);
+        let obj = Self::with_state(public_key, last_seen, None);
 
         obj.imp().is_this_device.set(true);
         obj
@@ -178,6 +186,11 @@ impl Author {
         self.notify_is_online();
     }
 
+    pub(crate) fn set_removed(&self, removed_at: &glib::DateTime) {
+        *self.imp().removed_at.lock().unwrap() = Some(removed_at.clone());
+        self.notify_removed_at();
+    }
+
     pub(crate) fn is_new_cursor_position(&self, timestamp: std::time::SystemTime) -> bool {
         let mut last_cursor_update = self.imp().last_cursor_update.lock().unwrap();
 
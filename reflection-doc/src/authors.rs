@@ -108,8 +108,14 @@ impl Authors {
         author
     }
 
-    pub(crate) fn author(&self, author_key: &PublicKey) -> Option<Author> {
+    pub fn author(&self, author_key: &PublicKey) -> Option<Author> {
         let list = self.imp().list.read().unwrap();
         list.get(author_key).cloned()
     }
+
+    /// The first author matching `predicate`.
+    pub(crate) fn find(&self, predicate: impl Fn(&Author) -> bool) -> Option<Author> {
+        let list = self.imp().list.read().unwrap();
+        list.values().find(|author| predicate(author)).cloned()
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::Hash;
 use std::sync::Arc;
@@ -9,16 +10,19 @@ use glib::{Properties, clone};
 pub use hex::FromHexError;
 use loro::{ExportMode, LoroDoc, LoroText, event::Diff};
 use p2panda_core::cbor::{decode_cbor, encode_cbor};
+use reflection_node::chrono::{DateTime, Utc};
+use reflection_node::node::BundleError;
+pub use reflection_node::node::Role;
 use reflection_node::p2panda_core;
 use reflection_node::topic::{
     SubscribableTopic, Subscription as TopicSubscription,
-    SubscriptionError as TopicSubscriptionError,
+    SubscriptionError as TopicSubscriptionError, TOPIC_SECRET_LENGTH, TopicSecret,
 };
 use tracing::error;
 
 use crate::author::Author;
 use crate::authors::Authors;
-use crate::identity::PublicKey;
+use crate::identity::{PrivateKey, PublicKey};
 use crate::service::Service;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, glib::Boxed)]
@@ -63,31 +67,587 @@ impl glib::variant::ToVariant for DocumentId {
     }
 }
 
-impl Default for DocumentId {
-    fn default() -> Self {
-        Self::new()
+impl DocumentId {
+    pub fn from_hex(hex: &str) -> Result<DocumentId, FromHexError> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(hex, &mut bytes as &mut [u8])?;
+
+        Ok(DocumentId(bytes))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
     }
 }
 
-impl DocumentId {
-    pub fn new() -> Self {
+/// Number of hex characters of an invite code, see [`Document::invite_code`].
+pub const INVITE_CODE_LENGTH: usize = TOPIC_SECRET_LENGTH * 2;
+
+/// Secret shared between all authors of a document.
+///
+/// The [`DocumentId`] is derived from this secret and everything that is sent to the network is
+/// encrypted with a key derived from it, therefore it must only be shared with people who should
+/// be able to read the document. It also contains the owner of the document.
+///
+/// Documents created before documents were encrypted only have a legacy secret, which is their
+/// id, and stay unencrypted.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, glib::Boxed)]
+#[boxed_type(name = "ReflectionDocumentSecret", nullable)]
+pub struct DocumentSecret(TopicSecret);
+
+impl From<DocumentSecret> for TopicSecret {
+    fn from(secret: DocumentSecret) -> Self {
+        secret.0
+    }
+}
+
+impl From<TopicSecret> for DocumentSecret {
+    fn from(secret: TopicSecret) -> Self {
+        Self(secret)
+    }
+}
+
+// Never print the secret by accident
+impl fmt::Debug for DocumentSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DocumentSecret").finish_non_exhaustive()
+    }
+}
+
+impl DocumentSecret {
+    /// A new random secret for a document owned by `owner`.
+    pub fn new(owner: &PublicKey) -> Self {
         let mut arr = [0u8; 32];
         rand::fill(&mut arr[..]);
-        DocumentId(arr)
+        DocumentSecret(TopicSecret::new(arr, owner.0))
     }
 
-    pub fn from_hex(hex: &str) -> Result<DocumentId, FromHexError> {
-        let mut bytes = [0u8; 32];
-        hex::decode_to_slice(hex, &mut bytes as &mut [u8])?;
+    /// The id of the document this secret belongs to.
+    pub fn document_id(&self) -> DocumentId {
+        DocumentId(self.0.id())
+    }
 
-        Ok(DocumentId(bytes))
+    /// The author who created the document and manages who can edit it, `None` for legacy
+    /// documents.
+    pub fn owner(&self) -> Option<PublicKey> {
+        self.0.owner().map(PublicKey)
+    }
+
+    /// Whether the document was created before documents were encrypted.
+    pub fn is_legacy(&self) -> bool {
+        self.0.is_legacy()
+    }
+
+    pub fn from_hex(hex: &str) -> Result<DocumentSecret, FromHexError> {
+        let bytes = hex::decode(hex)?;
+
+        // The owner needs to be a valid public key
+        TopicSecret::try_from(bytes.as_slice())
+            .map(DocumentSecret)
+            .map_err(|_| FromHexError::InvalidStringLength)
     }
 
     pub fn to_hex(&self) -> String {
-        hex::encode(self.0)
+        hex::encode(self.0.to_bytes())
+    }
+}
+
+/// Whether we have all changes of the peers we are connected to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, glib::Enum, Default)]
+#[repr(u32)]
+#[enum_type(name = "ReflectionSyncState")]
+pub enum SyncState {
+    /// We didn't sync with any peer yet.
+    #[default]
+    Idle,
+    /// We are receiving the changes of at least one peer.
+    Syncing,
+    /// We received all changes of the peers we synced with.
+    UpToDate,
+}
+
+/// A range of the text and the author who inserted it.
+#[derive(Debug, Clone)]
+pub struct AuthoredRange {
+    pub start: i32,
+    pub end: i32,
+    pub author: Author,
+}
+
+/// Formatting of a range of the text.
+///
+/// Marks are stored as Loro text marks, so concurrent formatting merges like the text does.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Mark {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    /// A link to the URL.
+    Link(String),
+    /// A heading of the level, from 1 to 6.
+    Heading(u8),
+}
+
+impl Mark {
+    /// Key of the mark in the Loro text, only one mark per key applies to each character.
+    fn key(&self) -> &'static str {
+        match self {
+            Mark::Bold => "bold",
+            Mark::Italic => "italic",
+            Mark::Underline => "underline",
+            Mark::Strikethrough => "strikethrough",
+            Mark::Link(_) => "link",
+            Mark::Heading(_) => "heading",
+        }
+    }
+
+    fn value(&self) -> loro::LoroValue {
+        match self {
+            Mark::Link(url) => url.as_str().into(),
+            Mark::Heading(level) => i64::from(*level).into(),
+            _ => true.into(),
+        }
+    }
+
+    fn from_attribute(key: &str, value: &loro::LoroValue) -> Option<Self> {
+        let mark = match (key, value) {
+            ("bold", loro::LoroValue::Bool(true)) => Mark::Bold,
+            ("italic", loro::LoroValue::Bool(true)) => Mark::Italic,
+            ("underline", loro::LoroValue::Bool(true)) => Mark::Underline,
+            ("strikethrough", loro::LoroValue::Bool(true)) => Mark::Strikethrough,
+            ("link", loro::LoroValue::String(url)) => Mark::Link(url.to_string()),
+            ("heading", loro::LoroValue::I64(level @ 1..=6)) => Mark::Heading(*level as u8),
+            _ => return None,
+        };
+
+        Some(mark)
+    }
+}
+
+/// A range of the text and its formatting, see [`Document::marks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkedRange {
+    pub start: i32,
+    pub end: i32,
+    pub mark: Mark,
+}
+
+/// A version of the document in its history.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// When the change leading to this version was made, `None` if it wasn't recorded.
+    pub timestamp: Option<glib::DateTime>,
+    /// Author of the change leading to this version.
+    pub author: Option<Author>,
+    frontiers: loro::Frontiers,
+}
+
+/// How a [`DiffSpan`] changed between two versions of the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Unchanged,
+    Inserted,
+    Deleted,
+}
+
+/// A part of the text in the comparison of two versions, see [`Document::diff`].
+#[derive(Debug, Clone)]
+pub struct DiffSpan {
+    pub kind: DiffKind,
+    pub text: String,
+    /// Who inserted or deleted the text, `None` for unchanged text or unknown authors.
+    pub author: Option<Author>,
+}
+
+/// A named version of the document, see [`Document::create_tag`].
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub checkpoint: Checkpoint,
+}
+
+/// A task agreed on in the document, see [`Document::action_items`].
+#[derive(Debug, Clone)]
+pub struct ActionItem {
+    pub text: String,
+    pub done: bool,
+    /// Who should take care of it.
+    pub assignee: Option<PublicKey>,
+    /// When it should be done, `None` if there is no deadline.
+    pub due: Option<glib::DateTime>,
+    /// Id of the map container of the item, it stays the same when the item is moved.
+    id: loro::ContainerID,
+}
+
+impl ActionItem {
+    fn from_map(map: &loro::LoroMap) -> Self {
+        let value = |key: &str| match map.get(key) {
+            Some(loro::ValueOrContainer::Value(value)) => Some(value),
+            _ => None,
+        };
+
+        Self {
+            text: match value("text") {
+                Some(loro::LoroValue::String(text)) => text.to_string(),
+                _ => String::new(),
+            },
+            done: matches!(value("done"), Some(loro::LoroValue::Bool(true))),
+            assignee: match value("assignee") {
+                Some(loro::LoroValue::Binary(public_key)) => {
+                    p2panda_core::PublicKey::try_from(&public_key[..])
+                        .ok()
+                        .map(PublicKey)
+                }
+                _ => None,
+            },
+            due: match value("due") {
+                Some(loro::LoroValue::I64(due)) => glib::DateTime::from_unix_utc(due).ok(),
+                _ => None,
+            },
+            id: map.id(),
+        }
     }
 }
 
+/// The action items of `doc`, in the order of the list.
+fn action_items_in(doc: &LoroDoc) -> Vec<ActionItem> {
+    let list = doc.get_movable_list(&*imp::ACTION_ITEMS_CONTAINER_ID);
+
+    (0..list.len())
+        .filter_map(|index| match list.get(index) {
+            Some(loro::ValueOrContainer::Container(loro::Container::Map(item))) => {
+                Some(ActionItem::from_map(&item))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The open tasks of the author with `public_key` in `doc`, see [`Document::tasks_for`].
+fn tasks_in(doc: &LoroDoc, public_key: &PublicKey) -> Vec<String> {
+    let name = Author::new(public_key).name();
+    let text = doc.get_text(&*imp::TEXT_CONTAINER_ID).to_string();
+    let mut tasks = unchecked_items_mentioning(&text, &name);
+
+    tasks.extend(
+        action_items_in(doc)
+            .into_iter()
+            .filter(|item| !item.done && item.assignee.as_ref() == Some(public_key))
+            .map(|item| item.text),
+    );
+
+    tasks
+}
+
+/// The unchecked Markdown checkboxes in `text` which mention `@name`, without the checkbox.
+///
+/// E.g. `- [ ] @Blue Fox send budget` becomes `@Blue Fox send budget`. The mention has to end
+/// at a word boundary, so `@Blue Foxglove` doesn't mention `Blue Fox`.
+fn unchecked_items_mentioning(text: &str, name: &str) -> Vec<String> {
+    let mention = format!("@{name}");
+
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            let item = ["- [ ]", "* [ ]", "+ [ ]"]
+                .iter()
+                .find_map(|checkbox| line.strip_prefix(checkbox))?
+                .trim();
+
+            item.match_indices(&mention)
+                .any(|(index, _)| {
+                    !item[index + mention.len()..].starts_with(|c: char| c.is_alphanumeric())
+                })
+                .then(|| item.to_owned())
+        })
+        .collect()
+}
+
+/// A discussion about a range of the text, see [`Document::comments`].
+///
+/// The range is anchored with Loro cursors, so it moves along with the text it's about when the
+/// text around it is edited.
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub start: i32,
+    pub end: i32,
+    /// `None` if the author isn't known.
+    pub author: Option<Author>,
+    pub timestamp: Option<glib::DateTime>,
+    pub text: String,
+    pub replies: Vec<Reply>,
+    /// Whether the discussion is over, resolved comments aren't highlighted in the text.
+    pub resolved: bool,
+    /// Key of the comment in the map of comments.
+    id: String,
+}
+
+/// An answer in the discussion of a [`Comment`].
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub author: Option<Author>,
+    pub timestamp: Option<glib::DateTime>,
+    pub text: String,
+}
+
+/// The Loro peer id of `public_key`, unless another author claimed it already.
+///
+/// It's made of the first 8 bytes of the public key (32 bytes), which is used to keep authors
+/// apart inside the text crdt. This isn't collision-resistant, since Loro limits peer ids to 64
+/// bit, therefore authors claim their peer id in the document and pick a random one when it's
+/// taken, see [`PeerClaim`].
+pub(crate) fn peer_id(public_key: &PublicKey) -> loro::PeerID {
+    let mut buf = [0u8; 8];
+    buf[..8].copy_from_slice(&public_key.0.as_bytes()[..8]);
+    u64::from_be_bytes(buf)
+}
+
+/// Claim of an author on a Loro peer id, stored in the document so everybody maps the peer id
+/// to the same author.
+///
+/// The claim is signed by the author, together with the document id, so others can't claim peer
+/// ids in the name of somebody else.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct PeerClaim {
+    public_key: p2panda_core::PublicKey,
+    signature: p2panda_core::identity::Signature,
+}
+
+impl PeerClaim {
+    fn new(
+        private_key: &p2panda_core::PrivateKey,
+        document: DocumentId,
+        peer: loro::PeerID,
+    ) -> Self {
+        Self {
+            public_key: private_key.public_key(),
+            signature: private_key.sign(&Self::signed_bytes(document, peer)),
+        }
+    }
+
+    /// The author of the claim, `None` if the signature isn't valid.
+    fn verify(&self, document: DocumentId, peer: loro::PeerID) -> Option<PublicKey> {
+        self.public_key
+            .verify(&Self::signed_bytes(document, peer), &self.signature)
+            .then_some(PublicKey(self.public_key))
+    }
+
+    fn signed_bytes(document: DocumentId, peer: loro::PeerID) -> Vec<u8> {
+        [&document.0[..], &peer.to_be_bytes()[..]].concat()
+    }
+}
+
+/// Claim the Loro peer id `peer` for the author of `private_key` in `doc`.
+///
+/// The claim is made with the current peer id of `doc`, it needs to be committed.
+pub(crate) fn claim_peer(
+    doc: &LoroDoc,
+    private_key: &PrivateKey,
+    document: DocumentId,
+    peer: loro::PeerID,
+) -> Result<()> {
+    let claim = encode_cbor(&PeerClaim::new(&private_key.0, document, peer))?;
+    doc.get_map(&*imp::PEERS_CONTAINER_ID)
+        .insert(&format!("{peer:016x}"), claim)?;
+
+    Ok(())
+}
+
+/// The author who claimed the Loro peer id `peer` in `doc`, `None` if nobody did or the claim
+/// isn't valid.
+///
+/// The peer id derived from a public key can only be claimed with that key. Any other peer id
+/// has to be claimed by the first operation made with it, otherwise changes made with the peer
+/// id before could be claimed by somebody else.
+pub(crate) fn peer_claim(
+    doc: &LoroDoc,
+    document: DocumentId,
+    peer: loro::PeerID,
+) -> Option<PublicKey> {
+    let key = format!("{peer:016x}");
+    let Some(loro::ValueOrContainer::Value(loro::LoroValue::Binary(claim))) =
+        doc.get_map(&*imp::PEERS_CONTAINER_ID).get(&key)
+    else {
+        return None;
+    };
+
+    let claim: PeerClaim = decode_cbor(&claim[..]).ok()?;
+    let public_key = claim.verify(document, peer)?;
+    if peer_id(&public_key) == peer {
+        return Some(public_key);
+    }
+
+    let mut first = loro::VersionVector::default();
+    first.extend_to_include_last_id(loro::ID::new(peer, 0));
+    let updates = doc.export_json_updates_without_peer_compression(&Default::default(), &first);
+    let first_op = updates
+        .changes
+        .into_iter()
+        .next()
+        .and_then(|change| change.ops.into_iter().next())?;
+    let loro::json::JsonOpContent::Map(loro::json::MapOp::Insert {
+        key: first_key,
+        value: loro::LoroValue::Binary(first_claim),
+    }) = first_op.content
+    else {
+        return None;
+    };
+
+    (first_op.container == *imp::PEERS_CONTAINER_ID
+        && *first_key == *key
+        && first_claim[..] == claim[..])
+        .then_some(public_key)
+}
+
+/// A copy of `doc` checked out at the version `frontiers`.
+fn fork_at(doc: &LoroDoc, frontiers: &loro::Frontiers) -> Result<LoroDoc> {
+    let version_doc = doc.fork();
+    version_doc.checkout(frontiers)?;
+    Ok(version_doc)
+}
+
+/// The id of the operation which inserted the character at `pos` of `text`.
+fn id_at(text: &LoroText, pos: usize) -> Option<loro::ID> {
+    text.get_cursor(pos, loro::cursor::Side::Middle)?.id
+}
+
+/// The Loro peer id of the author who deleted each of the characters `deleted` between the
+/// versions `from` and `to`.
+///
+/// Loro's diff doesn't say who deleted text, but the delete operations in between refer to the
+/// ids of the characters they removed, so a single pass over them is enough.
+fn deleted_by(
+    doc: &LoroDoc,
+    mut deleted: HashSet<loro::ID>,
+    from: &loro::Frontiers,
+    to: &loro::Frontiers,
+) -> HashMap<loro::ID, loro::PeerID> {
+    let (Some(from), Some(to)) = (doc.frontiers_to_vv(from), doc.frontiers_to_vv(to)) else {
+        return HashMap::new();
+    };
+
+    let mut deleted_by = HashMap::new();
+    for change in doc
+        .export_json_updates_without_peer_compression(&from, &to)
+        .changes
+    {
+        for op in change.ops {
+            let loro::json::JsonOpContent::Text(loro::json::TextOp::Delete {
+                len, start_id, ..
+            }) = op.content
+            else {
+                continue;
+            };
+            if op.container != *imp::TEXT_CONTAINER_ID {
+                continue;
+            }
+
+            // Deleting backwards counts down from `start_id`
+            let counters = if len >= 0 {
+                start_id.counter..start_id.counter + len
+            } else {
+                start_id.counter + len + 1..start_id.counter + 1
+            };
+            for counter in counters {
+                let id = loro::ID::new(start_id.peer, counter);
+                if deleted.remove(&id) {
+                    deleted_by.insert(id, change.id.peer);
+                }
+            }
+        }
+    }
+
+    deleted_by
+}
+
+/// What changed in the text of `doc` between the versions `from` and `to`, with the Loro peer
+/// id of who inserted or deleted each span, see [`Document::diff`].
+fn diff_spans(
+    doc: &LoroDoc,
+    from: &loro::Frontiers,
+    to: &loro::Frontiers,
+) -> Result<Vec<(DiffKind, String, Option<loro::PeerID>)>> {
+    let (old_doc, new_doc) = (fork_at(doc, from)?, fork_at(doc, to)?);
+    let old_text = old_doc.get_text(&*imp::TEXT_CONTAINER_ID);
+    let new_text = new_doc.get_text(&*imp::TEXT_CONTAINER_ID);
+    let old_chars: Vec<char> = old_text.to_string().chars().collect();
+    let diff = doc.diff(from, to)?;
+    let deltas = diff
+        .iter()
+        .find_map(|(container, diff)| match diff {
+            Diff::Text(deltas) if container == &*imp::TEXT_CONTAINER_ID => Some(deltas.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    // Only the ids of inserted and deleted characters are looked up, the text may be long
+    let mut deleted = HashSet::new();
+    let mut old_index = 0;
+    for delta in &deltas {
+        match delta {
+            loro::TextDelta::Retain { retain, .. } => old_index += retain,
+            loro::TextDelta::Insert { .. } => {}
+            loro::TextDelta::Delete { delete } => {
+                deleted.extend(
+                    (old_index..old_index + delete).filter_map(|pos| id_at(&old_text, pos)),
+                );
+                old_index += delete;
+            }
+        }
+    }
+    let deleted_by = deleted_by(doc, deleted, from, to);
+
+    fn push(
+        spans: &mut Vec<(DiffKind, String, Option<loro::PeerID>)>,
+        kind: DiffKind,
+        c: char,
+        peer: Option<loro::PeerID>,
+    ) {
+        match spans.last_mut() {
+            Some((last_kind, text, last_peer)) if *last_kind == kind && *last_peer == peer => {
+                text.push(c);
+            }
+            _ => spans.push((kind, c.to_string(), peer)),
+        }
+    }
+
+    let mut spans: Vec<(DiffKind, String, Option<loro::PeerID>)> = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    for delta in &deltas {
+        match delta {
+            loro::TextDelta::Retain { retain, .. } => {
+                for &c in old_chars.iter().skip(old_index).take(*retain) {
+                    push(&mut spans, DiffKind::Unchanged, c, None);
+                }
+                old_index += retain;
+                new_index += retain;
+            }
+            loro::TextDelta::Insert { insert, .. } => {
+                for c in insert.chars() {
+                    let peer = id_at(&new_text, new_index).map(|id| id.peer);
+                    push(&mut spans, DiffKind::Inserted, c, peer);
+                    new_index += 1;
+                }
+            }
+            loro::TextDelta::Delete { delete } => {
+                for (pos, &c) in old_chars.iter().enumerate().skip(old_index).take(*delete) {
+                    let peer = id_at(&old_text, pos).and_then(|id| deleted_by.get(&id).copied());
+                    push(&mut spans, DiffKind::Deleted, c, peer);
+                }
+                old_index += delete;
+            }
+        }
+    }
+
+    // The deltas end with the last change, the rest of the text is unchanged
+    for &c in old_chars.iter().skip(old_index) {
+        push(&mut spans, DiffKind::Unchanged, c, None);
+    }
+
+    Ok(spans)
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 enum EphemerialData {
     Cursor {
@@ -100,16 +660,49 @@ enum EphemerialData {
 mod imp {
     use super::*;
     use std::cell::{Cell, OnceCell};
+    use std::collections::{HashMap, HashSet};
     use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
     use std::time::Duration;
 
     /// Identifier of container where we handle the text CRDT in a Loro document.
     ///
     /// Loro documents can contain multiple different CRDT types in one document.
-    static TEXT_CONTAINER_ID: LazyLock<loro::ContainerID> =
+    pub(super) static TEXT_CONTAINER_ID: LazyLock<loro::ContainerID> =
         LazyLock::new(|| loro::ContainerID::new_root("document", loro::ContainerType::Text));
+    /// Identifier of the container with the action items of the document, each of them is a
+    /// map so its fields can be changed concurrently.
+    pub(super) static ACTION_ITEMS_CONTAINER_ID: LazyLock<loro::ContainerID> =
+        LazyLock::new(|| {
+            loro::ContainerID::new_root("action-items", loro::ContainerType::MovableList)
+        });
+    /// Identifier of the container with the comments on the text, each of them is a map with
+    /// the cursors anchoring it in the text and a list of replies.
+    static COMMENTS_CONTAINER_ID: LazyLock<loro::ContainerID> =
+        LazyLock::new(|| loro::ContainerID::new_root("comments", loro::ContainerType::Map));
+    /// Identifier of the container mapping Loro peer ids to the [`PeerClaim`] of their author.
+    pub(super) static PEERS_CONTAINER_ID: LazyLock<loro::ContainerID> =
+        LazyLock::new(|| loro::ContainerID::new_root("peers", loro::ContainerType::Map));
+    /// Origin of commits which store our peer claim, they can't be undone.
+    const PEER_CLAIM_ORIGIN: &str = "peer-claim";
+    /// Identifier of the container mapping the names of tags to the versions they point to.
+    pub(super) static TAGS_CONTAINER_ID: LazyLock<loro::ContainerID> =
+        LazyLock::new(|| loro::ContainerID::new_root("tags", loro::ContainerType::Map));
+    /// Identifier of the container with the title, icon, color, description and creation date
+    /// of the document.
+    static METADATA_CONTAINER_ID: LazyLock<loro::ContainerID> =
+        LazyLock::new(|| loro::ContainerID::new_root("metadata", loro::ContainerType::Map));
+    /// Origin of commits which change the metadata, they aren't undone with the text.
+    const METADATA_ORIGIN: &str = "metadata";
+    /// Origin of commits which change the action items, they aren't undone with the text.
+    const ACTION_ITEMS_ORIGIN: &str = "action-items";
+    /// Origin of commits which change the comments, they aren't undone with the text.
+    const COMMENTS_ORIGIN: &str = "comments";
+    /// Origin of commits which create tags, they aren't undone with the text.
+    pub(super) const TAGS_ORIGIN: &str = "tags";
     const DOCUMENT_NAME_LENGTH: usize = 124;
     const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(5);
+    /// Edits of an author within this many seconds are merged into one checkpoint.
+    const CHECKPOINT_INTERVAL: i64 = 60;
 
     #[derive(Properties, Default)]
     #[properties(wrapper_type = super::Document)]
@@ -121,6 +714,12 @@ mod imp {
         #[property(get, construct_only, set)]
         pub(super) last_accessed: Mutex<Option<glib::DateTime>>,
         #[property(name = "text", get = Self::text, type = String)]
+        #[property(name = "read-only", get = Self::read_only, type = bool)]
+        #[property(name = "title", get = Self::title, type = Option<String>)]
+        #[property(name = "icon", get = Self::icon, type = Option<String>)]
+        #[property(name = "color", get = Self::color, type = Option<String>)]
+        #[property(name = "description", get = Self::description, type = Option<String>)]
+        #[property(name = "created", get = Self::created, type = Option<glib::DateTime>)]
         pub(super) crdt_doc: OnceCell<LoroDoc>,
         pub(super) undo_manager: Mutex<Option<loro::UndoManager>>,
         #[property(get)]
@@ -129,14 +728,35 @@ mod imp {
         pub(super) can_redo: Cell<bool>,
         #[property(get, construct_only)]
         id: OnceCell<DocumentId>,
+        #[property(get, construct_only)]
+        secret: OnceCell<DocumentSecret>,
         #[property(name = "subscribed", get = Self::subscribed, type = bool)]
         pub(super) subscription: RwLock<Option<Arc<TopicSubscription<DocumentHandle>>>>,
+        /// Keeps the document in sync while it isn't subscribed.
+        #[property(name = "keep-in-sync", get = Self::keep_in_sync, set = Self::set_keep_in_sync, type = bool)]
+        pub(super) background_subscription: Mutex<Option<TopicSubscription<BackgroundHandle>>>,
+        /// Number of changes others made since the document was last subscribed.
+        #[property(get, construct_only)]
+        #[property(name = "has-unseen-changes", get = Self::has_unseen_changes, type = bool)]
+        pub(super) unseen_changes: Cell<u32>,
+        /// State of the document when it was last unsubscribed, text others inserted since then
+        /// is highlighted until it's dismissed.
+        #[property(name = "has-changes-since-last-seen", get = Self::has_changes_since_last_seen, type = bool)]
+        pub(super) last_seen_frontiers: Mutex<Option<loro::Frontiers>>,
+        /// State of the document when it was last unsubscribed, as stored with its topic.
+        pub(super) bookmark: Mutex<Option<loro::Frontiers>>,
         #[property(get = Self::service, set = Self::set_service, construct_only, type = Service)]
         service: glib::WeakRef<Service>,
         #[property(get)]
         authors: Authors,
         pub(super) tasks: Mutex<Vec<glib::JoinHandle<()>>>,
         pub(super) snapshot_scheduled: Cell<bool>,
+        #[property(get, builder(SyncState::default()))]
+        sync_state: Cell<SyncState>,
+        /// Number of peers we are currently syncing with.
+        #[property(get)]
+        syncing_peers: Cell<u32>,
+        sync_sessions: Mutex<HashSet<p2panda_core::PublicKey>>,
 
         insert_cursor: RwLock<Option<loro::cursor::Cursor>>,
         selection_bound: RwLock<Option<loro::cursor::Cursor>>,
@@ -201,6 +821,18 @@ mod imp {
             self.service.set(Some(service));
         }
 
+        /// Whether the document is kept in sync in the background, even when that's disabled
+        /// for all documents.
+        fn keep_in_sync(&self) -> bool {
+            self.service().keeps_in_sync(&self.obj().id())
+        }
+
+        fn set_keep_in_sync(&self, keep_in_sync: bool) {
+            // The service applies the setting once it's stored
+            self.service()
+                .set_keeps_in_sync(&self.obj().id(), keep_in_sync);
+        }
+
         pub fn text(&self) -> String {
             self.crdt_doc
                 .get()
@@ -209,6 +841,72 @@ mod imp {
                 .to_string()
         }
 
+        fn metadata(&self, key: &str) -> Option<loro::LoroValue> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+
+            match doc.get_map(&*METADATA_CONTAINER_ID).get(key) {
+                Some(loro::ValueOrContainer::Value(value)) => Some(value),
+                _ => None,
+            }
+        }
+
+        fn metadata_string(&self, key: &str) -> Option<String> {
+            match self.metadata(key) {
+                Some(loro::LoroValue::String(value)) => Some(value.to_string()),
+                _ => None,
+            }
+        }
+
+        /// Change the metadata `key` for everyone, `None` removes it.
+        pub(super) fn set_metadata(
+            &self,
+            key: &str,
+            value: Option<impl Into<loro::LoroValue>>,
+        ) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let metadata = doc.get_map(&*METADATA_CONTAINER_ID);
+
+            match value {
+                Some(value) => metadata.insert(key, value)?,
+                None => metadata.delete(key)?,
+            }
+            doc.set_next_commit_origin(METADATA_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        fn title(&self) -> Option<String> {
+            self.metadata_string("title")
+        }
+
+        fn icon(&self) -> Option<String> {
+            self.metadata_string("icon")
+        }
+
+        fn color(&self) -> Option<String> {
+            self.metadata_string("color")
+        }
+
+        fn description(&self) -> Option<String> {
+            self.metadata_string("description")
+        }
+
+        fn created(&self) -> Option<glib::DateTime> {
+            match self.metadata("created") {
+                Some(loro::LoroValue::I64(created)) => glib::DateTime::from_unix_utc(created).ok(),
+                _ => None,
+            }
+        }
+
+        /// Whether this is an old version of a document, see [`super::Document::checkout`].
+        fn read_only(&self) -> bool {
+            self.crdt_doc
+                .get()
+                .expect("crdt_doc to be set")
+                .is_detached()
+        }
+
         pub(super) fn update_name(&self) {
             let crdt_text = self
                 .crdt_doc
@@ -216,7 +914,8 @@ mod imp {
                 .expect("crdt_doc to be set")
                 .get_text(&*TEXT_CONTAINER_ID);
 
-            let name = extract_name(crdt_text);
+            // The first line of the text is only used when nobody gave the document a title
+            let name = self.title().or_else(|| extract_name(crdt_text));
 
             if name == self.obj().name() {
                 return;
@@ -255,6 +954,331 @@ mod imp {
             Ok(())
         }
 
+        pub fn mark_text(&self, range: std::ops::Range<usize>, mark: &Mark) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let text = doc.get_text(&*TEXT_CONTAINER_ID);
+
+            text.mark(range, mark.key(), mark.value())?;
+            doc.commit();
+
+            Ok(())
+        }
+
+        pub fn unmark_text(&self, range: std::ops::Range<usize>, mark: &Mark) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let text = doc.get_text(&*TEXT_CONTAINER_ID);
+
+            text.unmark(range, mark.key())?;
+            doc.commit();
+
+            Ok(())
+        }
+
+        /// All formatted ranges of the text.
+        pub(super) fn text_marks(&self) -> Vec<MarkedRange> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let text = doc.get_text(&*TEXT_CONTAINER_ID);
+
+            let mut marks = Vec::new();
+            let mut pos = 0;
+            for delta in text.to_delta() {
+                let loro::TextDelta::Insert { insert, attributes } = delta else {
+                    continue;
+                };
+                let len = insert.chars().count() as i32;
+
+                for (key, value) in attributes.iter().flatten() {
+                    if let Some(mark) = Mark::from_attribute(key, value) {
+                        marks.push(MarkedRange {
+                            start: pos,
+                            end: pos + len,
+                            mark,
+                        });
+                    }
+                }
+                pos += len;
+            }
+
+            marks
+        }
+
+        pub(super) fn action_items(&self) -> Vec<ActionItem> {
+            action_items_in(self.crdt_doc.get().expect("crdt_doc to be set"))
+        }
+
+        /// The position and map of the action item with the id `id`, `None` if it was removed.
+        fn action_item_map(&self, id: &loro::ContainerID) -> Option<(usize, loro::LoroMap)> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let list = doc.get_movable_list(&*ACTION_ITEMS_CONTAINER_ID);
+
+            (0..list.len()).find_map(|index| match list.get(index) {
+                Some(loro::ValueOrContainer::Container(loro::Container::Map(item)))
+                    if item.id() == *id =>
+                {
+                    Some((index, item))
+                }
+                _ => None,
+            })
+        }
+
+        pub(super) fn add_action_item(&self, text: &str) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let list = doc.get_movable_list(&*ACTION_ITEMS_CONTAINER_ID);
+
+            let item = list.insert_container(list.len(), loro::LoroMap::new())?;
+            item.insert("text", text)?;
+            item.insert("done", false)?;
+            doc.set_next_commit_origin(ACTION_ITEMS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        /// Change the fields of the action item `item` with `update`.
+        pub(super) fn update_action_item(
+            &self,
+            item: &ActionItem,
+            update: impl FnOnce(&loro::LoroMap) -> loro::LoroResult<()>,
+        ) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let Some((_, map)) = self.action_item_map(&item.id) else {
+                anyhow::bail!("Action item was removed");
+            };
+
+            update(&map)?;
+            doc.set_next_commit_origin(ACTION_ITEMS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        pub(super) fn move_action_item(&self, item: &ActionItem, position: usize) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let list = doc.get_movable_list(&*ACTION_ITEMS_CONTAINER_ID);
+            let Some((index, _)) = self.action_item_map(&item.id) else {
+                anyhow::bail!("Action item was removed");
+            };
+
+            list.mov(index, position.min(list.len() - 1))?;
+            doc.set_next_commit_origin(ACTION_ITEMS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        pub(super) fn remove_action_item(&self, item: &ActionItem) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let list = doc.get_movable_list(&*ACTION_ITEMS_CONTAINER_ID);
+            let Some((index, _)) = self.action_item_map(&item.id) else {
+                anyhow::bail!("Action item was removed");
+            };
+
+            list.delete(index, 1)?;
+            doc.set_next_commit_origin(ACTION_ITEMS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        /// The comments of the document, ordered by their position in the text.
+        pub(super) fn comments(&self) -> Vec<Comment> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+
+            let mut comments = Vec::new();
+            doc.get_map(&*COMMENTS_CONTAINER_ID)
+                .for_each(|id, comment| {
+                    let loro::ValueOrContainer::Container(loro::Container::Map(comment)) = comment
+                    else {
+                        return;
+                    };
+                    comments.extend(self.comment_from_map(id, &comment));
+                });
+            comments.sort_by_key(|comment| (comment.start, comment.end));
+
+            comments
+        }
+
+        fn comment_from_map(&self, id: &str, map: &loro::LoroMap) -> Option<Comment> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let position = |key: &str| {
+                let Some(loro::ValueOrContainer::Value(loro::LoroValue::Binary(cursor))) =
+                    map.get(key)
+                else {
+                    return None;
+                };
+                let cursor = loro::cursor::Cursor::decode(&cursor).ok()?;
+
+                match doc.get_cursor_pos(&cursor) {
+                    Ok(loro::cursor::PosQueryResult { current, .. }) => Some(current.pos as i32),
+                    Err(error) => {
+                        error!("Failed to get position of comment: {error}");
+                        None
+                    }
+                }
+            };
+
+            let replies = match map.get("replies") {
+                Some(loro::ValueOrContainer::Container(loro::Container::List(replies))) => {
+                    self.replies_from_list(&replies)
+                }
+                _ => Vec::new(),
+            };
+
+            let (author, timestamp, text) = self.message_from_map(map);
+            let start = position("start")?;
+
+            Some(Comment {
+                start,
+                // The end moves before the start when all of the text is deleted
+                end: position("end")?.max(start),
+                author,
+                timestamp,
+                text,
+                replies,
+                resolved: matches!(
+                    map.get("resolved"),
+                    Some(loro::ValueOrContainer::Value(loro::LoroValue::Bool(true)))
+                ),
+                id: id.to_owned(),
+            })
+        }
+
+        fn replies_from_list(&self, list: &loro::LoroList) -> Vec<Reply> {
+            (0..list.len())
+                .filter_map(|index| match list.get(index) {
+                    Some(loro::ValueOrContainer::Container(loro::Container::Map(reply))) => {
+                        let (author, timestamp, text) = self.message_from_map(&reply);
+                        Some(Reply {
+                            author,
+                            timestamp,
+                            text,
+                        })
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+
+        /// The author, time and text of a comment or reply.
+        fn message_from_map(
+            &self,
+            map: &loro::LoroMap,
+        ) -> (Option<Author>, Option<glib::DateTime>, String) {
+            let value = |key: &str| match map.get(key) {
+                Some(loro::ValueOrContainer::Value(value)) => Some(value),
+                _ => None,
+            };
+
+            // The map was created by the author of the message, unlike a field of the map the
+            // peer id of its creator can't be changed by others
+            let author = match map.id() {
+                loro::ContainerID::Normal { peer, .. } => self.author_for_peer(peer),
+                loro::ContainerID::Root { .. } => None,
+            };
+            let timestamp = match value("timestamp") {
+                Some(loro::LoroValue::I64(timestamp)) => {
+                    glib::DateTime::from_unix_utc(timestamp).ok()
+                }
+                _ => None,
+            };
+            let text = match value("text") {
+                Some(loro::LoroValue::String(text)) => text.to_string(),
+                _ => String::new(),
+            };
+
+            (author, timestamp, text)
+        }
+
+        /// Store the current time and `text` in the comment or reply `map` we just created.
+        fn insert_message(&self, map: &loro::LoroMap, text: &str) -> Result<()> {
+            map.insert("timestamp", glib::DateTime::now_utc()?.to_unix())?;
+            map.insert("text", text)?;
+
+            Ok(())
+        }
+
+        pub(super) fn add_comment(&self, range: std::ops::Range<usize>, text: &str) -> Result<()> {
+            if range.is_empty() {
+                anyhow::bail!("Comments need a range of the text");
+            }
+
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let text_container = doc.get_text(&*TEXT_CONTAINER_ID);
+            let (Some(start), Some(end)) = (
+                text_container.get_cursor(range.start, loro::cursor::Side::Middle),
+                text_container.get_cursor(range.end, loro::cursor::Side::Middle),
+            ) else {
+                anyhow::bail!("Range of the comment is outside of the text");
+            };
+
+            // Random keys, so comments added concurrently don't replace each other
+            let id = format!("{:016x}", rand::random::<u64>());
+            let comment = doc
+                .get_map(&*COMMENTS_CONTAINER_ID)
+                .insert_container(&id, loro::LoroMap::new())?;
+            comment.insert("start", start.encode())?;
+            comment.insert("end", end.encode())?;
+            comment.insert("resolved", false)?;
+            comment.insert_container("replies", loro::LoroList::new())?;
+            self.insert_message(&comment, text)?;
+            doc.set_next_commit_origin(COMMENTS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        /// The map of the comment `comment`, `None` if it was removed.
+        fn comment_map(&self, comment: &Comment) -> Option<loro::LoroMap> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+
+            match doc.get_map(&*COMMENTS_CONTAINER_ID).get(&comment.id) {
+                Some(loro::ValueOrContainer::Container(loro::Container::Map(map))) => Some(map),
+                _ => None,
+            }
+        }
+
+        pub(super) fn reply_to_comment(&self, comment: &Comment, text: &str) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let Some(map) = self.comment_map(comment) else {
+                anyhow::bail!("Comment was removed");
+            };
+            let Some(loro::ValueOrContainer::Container(loro::Container::List(replies))) =
+                map.get("replies")
+            else {
+                anyhow::bail!("Comment has no replies");
+            };
+
+            let reply = replies.insert_container(replies.len(), loro::LoroMap::new())?;
+            self.insert_message(&reply, text)?;
+            doc.set_next_commit_origin(COMMENTS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        pub(super) fn set_comment_resolved(&self, comment: &Comment, resolved: bool) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let Some(map) = self.comment_map(comment) else {
+                anyhow::bail!("Comment was removed");
+            };
+
+            map.insert("resolved", resolved)?;
+            doc.set_next_commit_origin(COMMENTS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
+        pub(super) fn remove_comment(&self, comment: &Comment) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+
+            doc.get_map(&*COMMENTS_CONTAINER_ID).delete(&comment.id)?;
+            doc.set_next_commit_origin(COMMENTS_ORIGIN);
+            doc.commit();
+
+            Ok(())
+        }
+
         pub fn delete_text(&self, index: usize, len: usize) -> Result<()> {
             let doc = self.crdt_doc.get().expect("crdt_doc to be set");
             let text = doc.get_text(&*TEXT_CONTAINER_ID);
@@ -265,6 +1289,69 @@ mod imp {
             Ok(())
         }
 
+        /// The text at the version `frontiers`.
+        pub(super) fn text_at(&self, frontiers: &loro::Frontiers) -> Result<String> {
+            let version_doc = self.crdt_doc.get().expect("crdt_doc to be set").fork();
+            version_doc.checkout(frontiers)?;
+
+            Ok(version_doc.get_text(&*TEXT_CONTAINER_ID).to_string())
+        }
+
+        /// The checkpoint of the version `frontiers`, its author and timestamp are the ones of
+        /// the most recent change of the version.
+        pub(super) fn checkpoint_at(&self, frontiers: loro::Frontiers) -> Checkpoint {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let change = frontiers
+                .iter()
+                .filter_map(|id| doc.get_change(id))
+                .max_by_key(|change| (change.timestamp, change.lamport));
+
+            Checkpoint {
+                timestamp: change
+                    .as_ref()
+                    .filter(|change| change.timestamp > 0)
+                    .and_then(|change| glib::DateTime::from_unix_utc(change.timestamp).ok()),
+                author: change.and_then(|change| self.author_for_peer(change.id.peer)),
+                frontiers,
+            }
+        }
+
+        /// Replace the whole text with `text`, only the differences are changed.
+        pub(super) fn replace_text(&self, text: &str) -> Result<()> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+
+            doc.get_text(&*TEXT_CONTAINER_ID)
+                .update(text, Default::default())?;
+            doc.commit();
+
+            Ok(())
+        }
+
+        /// All changes which are part of the version `to` but not of the version `from`, in the
+        /// order they were made.
+        pub(super) fn changes_between(
+            &self,
+            from: &loro::VersionVector,
+            to: &loro::VersionVector,
+        ) -> Vec<loro::ChangeMeta> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+
+            let mut changes = Vec::new();
+            for (peer, end) in to.iter() {
+                let mut counter = from.get(peer).copied().unwrap_or(0);
+                while counter < *end {
+                    let Some(change) = doc.get_change(loro::ID::new(*peer, counter)) else {
+                        break;
+                    };
+                    counter = change.id.counter + change.len as i32;
+                    changes.push(change);
+                }
+            }
+            changes.sort_by_key(|change| (change.timestamp, change.lamport));
+
+            changes
+        }
+
         pub fn set_insert_cursor(&self, position: usize, send: bool) {
             let doc = self.crdt_doc.get().expect("crdt_doc to be set");
             let text = doc.get_text(&*TEXT_CONTAINER_ID);
@@ -364,12 +1451,258 @@ mod imp {
             if let Err(err) = doc.import_with(&bytes, "delta") {
                 error!("received invalid message: {}", err);
             }
+
+            // Somebody else may have claimed our peer id in the meantime
+            if self.subscribed() {
+                self.claim_peer_id();
+            }
         }
 
         fn subscribed(&self) -> bool {
             self.subscription().is_some()
         }
 
+        fn has_unseen_changes(&self) -> bool {
+            self.unseen_changes.get() > 0
+        }
+
+        fn has_changes_since_last_seen(&self) -> bool {
+            self.last_seen_frontiers.lock().unwrap().is_some()
+        }
+
+        pub(super) fn set_last_seen_frontiers(&self, frontiers: Option<loro::Frontiers>) {
+            let had_changes_since_last_seen = self.has_changes_since_last_seen();
+            *self.last_seen_frontiers.lock().unwrap() = frontiers;
+
+            if had_changes_since_last_seen != self.has_changes_since_last_seen() {
+                self.obj().notify_has_changes_since_last_seen();
+            }
+        }
+
+        /// Ranges of the text others inserted since `frontiers`, with the Loro peer id of the
+        /// author of each range.
+        ///
+        /// Only the text Loro's diff between `frontiers` and the current state reports as
+        /// inserted is looked at, the rest of the text is skipped.
+        pub(super) fn inserted_since(
+            &self,
+            frontiers: &loro::Frontiers,
+        ) -> Vec<(std::ops::Range<usize>, loro::PeerID)> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let diff = match doc.diff(frontiers, &doc.state_frontiers()) {
+                Ok(diff) => diff,
+                Err(error) => {
+                    // The state isn't part of the history we know, e.g. after a shallow snapshot
+                    error!("Failed to diff the document with its last seen state: {error}");
+                    return Vec::new();
+                }
+            };
+            let this_peer = doc.peer_id();
+
+            let mut ranges = Vec::new();
+            for (container, diff) in diff.iter() {
+                if container != &*TEXT_CONTAINER_ID {
+                    continue;
+                }
+                let Diff::Text(deltas) = diff else {
+                    continue;
+                };
+
+                let mut index = 0;
+                for delta in deltas {
+                    match delta {
+                        loro::TextDelta::Retain { retain, .. } => index += retain,
+                        loro::TextDelta::Insert { insert, .. } => {
+                            let len = insert.chars().count();
+                            ranges.extend(
+                                self.inserted_by(index..index + len, |id| id.peer != this_peer),
+                            );
+                            index += len;
+                        }
+                        loro::TextDelta::Delete { .. } => {}
+                    }
+                }
+            }
+
+            ranges
+        }
+
+        /// Ranges of the text within `range` with the Loro peer id of the author who inserted
+        /// each of them, characters for which `include` returns `false` are left out.
+        pub(super) fn inserted_by(
+            &self,
+            range: std::ops::Range<usize>,
+            include: impl Fn(loro::ID) -> bool,
+        ) -> Vec<(std::ops::Range<usize>, loro::PeerID)> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let text = doc.get_text(&*TEXT_CONTAINER_ID);
+
+            let mut ranges: Vec<(std::ops::Range<usize>, loro::PeerID)> = Vec::new();
+            for pos in range.start..range.end.min(text.len_unicode()) {
+                // The cursor points to the id of the operation which inserted the character
+                let Some(id) = text
+                    .get_cursor(pos, loro::cursor::Side::Middle)
+                    .and_then(|cursor| cursor.id)
+                else {
+                    continue;
+                };
+
+                if !include(id) {
+                    continue;
+                }
+
+                match ranges.last_mut() {
+                    Some((range, peer)) if range.end == pos && *peer == id.peer => {
+                        range.end = pos + 1;
+                    }
+                    _ => ranges.push((pos..pos + 1, id.peer)),
+                }
+            }
+
+            ranges
+        }
+
+        /// The author of the document with the given Loro peer id.
+        pub(super) fn author_for_peer(&self, peer: loro::PeerID) -> Option<Author> {
+            if let Some(public_key) = self.peer_claim(peer) {
+                return self.authors.author(&public_key);
+            }
+
+            // Changes made before authors claimed their peer ids
+            self.authors
+                .find(|author| peer_id(&author.public_key()) == peer)
+        }
+
+        /// The author who claimed the Loro peer id `peer`, see [`super::peer_claim()`].
+        fn peer_claim(&self, peer: loro::PeerID) -> Option<PublicKey> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            super::peer_claim(doc, self.obj().id(), peer)
+        }
+
+        /// All valid peer claims of the document.
+        fn peer_claims(&self) -> HashMap<loro::PeerID, PublicKey> {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let loro::LoroValue::Map(claims) = doc.get_map(&*PEERS_CONTAINER_ID).get_value() else {
+                return HashMap::new();
+            };
+
+            claims
+                .keys()
+                .filter_map(|peer| {
+                    let peer = loro::PeerID::from_str_radix(peer, 16).ok()?;
+                    Some((peer, self.peer_claim(peer)?))
+                })
+                .collect()
+        }
+
+        /// Make sure nobody else uses our Loro peer id and claim it in the document.
+        ///
+        /// When another author claimed the peer id derived from our public key first, e.g. when
+        /// both claimed it concurrently, we switch to a random peer id which isn't taken yet.
+        pub(super) fn claim_peer_id(&self) {
+            let doc = self.crdt_doc.get().expect("crdt_doc to be set");
+            let private_key = self.obj().service().private_key();
+            let public_key = private_key.public_key();
+            if self.peer_claim(doc.peer_id()).as_ref() == Some(&public_key) {
+                return;
+            }
+
+            let claims = self.peer_claims();
+
+            // We claimed a peer id before, it may be a random one after a collision
+            if let Some((peer, _)) = claims.iter().find(|(_, author)| **author == public_key) {
+                if doc.peer_id() != *peer
+                    && let Err(error) = doc.set_peer_id(*peer)
+                {
+                    error!("Failed to switch to claimed peer id: {error}");
+                }
+                return;
+            }
+
+            // Random peer ids must not be used by changes made before peer ids were claimed
+            let current_peer = doc.peer_id();
+            let taken = |peer: &loro::PeerID| {
+                claims.contains_key(peer)
+                    || (*peer != current_peer && doc.oplog_vv().get(peer).is_some())
+            };
+            let mut peer = current_peer;
+            while taken(&peer) {
+                peer = rand::random();
+            }
+
+            if let Err(error) = doc.set_peer_id(peer) {
+                error!("Failed to switch to a new peer id: {error}");
+                return;
+            }
+
+            // Random peer ids are only valid when the claim is their first operation
+            if let Err(error) = claim_peer(doc, &private_key, self.obj().id(), peer) {
+                error!("Failed to claim peer id: {error}");
+                return;
+            }
+            doc.set_next_commit_origin(PEER_CLAIM_ORIGIN);
+            doc.commit();
+        }
+
+        pub(super) fn set_unseen_changes(&self, unseen_changes: u32) {
+            let had_unseen_changes = self.has_unseen_changes();
+            if self.unseen_changes.replace(unseen_changes) == unseen_changes {
+                return;
+            }
+
+            let obj = self.obj();
+            obj.notify_unseen_changes();
+            if had_unseen_changes != self.has_unseen_changes() {
+                obj.notify_has_unseen_changes();
+            }
+        }
+
+        pub(super) fn sync_started(&self, peer: p2panda_core::PublicKey) {
+            let syncing_peers = {
+                let mut sync_sessions = self.sync_sessions.lock().unwrap();
+                sync_sessions.insert(peer);
+                sync_sessions.len()
+            };
+
+            self.set_sync_state(SyncState::Syncing, syncing_peers);
+        }
+
+        /// The sync session with `peer` ended, `completed` is false if it was interrupted.
+        pub(super) fn sync_finished(&self, peer: p2panda_core::PublicKey, completed: bool) {
+            let syncing_peers = {
+                let mut sync_sessions = self.sync_sessions.lock().unwrap();
+                if !sync_sessions.remove(&peer) {
+                    return;
+                }
+                sync_sessions.len()
+            };
+
+            let sync_state = if syncing_peers > 0 {
+                SyncState::Syncing
+            } else if completed {
+                SyncState::UpToDate
+            } else {
+                SyncState::Idle
+            };
+            self.set_sync_state(sync_state, syncing_peers);
+        }
+
+        pub(super) fn reset_sync_state(&self) {
+            self.sync_sessions.lock().unwrap().clear();
+            self.set_sync_state(SyncState::Idle, 0);
+        }
+
+        fn set_sync_state(&self, sync_state: SyncState, syncing_peers: usize) {
+            let obj = self.obj();
+            let syncing_peers = syncing_peers as u32;
+            if self.syncing_peers.replace(syncing_peers) != syncing_peers {
+                obj.notify_syncing_peers();
+            }
+            if self.sync_state.replace(sync_state) != sync_state {
+                obj.notify_sync_state();
+            }
+        }
+
         fn emit_text_inserted(&self, pos: i32, text: String) {
             if pos <= DOCUMENT_NAME_LENGTH as i32 {
                 self.update_name();
@@ -388,6 +1721,11 @@ mod imp {
                 .emit_by_name::<()>("range-deleted", &[&start, &end]);
         }
 
+        fn emit_text_formatted(&self, start: i32, end: i32) {
+            self.obj()
+                .emit_by_name::<()>("text-formatted", &[&start, &end]);
+        }
+
         fn mark_for_snapshot(&self) {
             if !self.snapshot_scheduled.get() && self.subscribed() {
                 let obj = self.obj();
@@ -419,19 +1757,28 @@ mod imp {
             let doc = LoroDoc::new();
             // The peer id represents the identity of the author applying local changes (that's
             // essentially us), it needs be strictly unique.
-            doc.set_peer_id({
-                // Take first 8 bytes of public key (32 bytes) to determine a unique "peer id"
-                // which is used to keep authors apart inside the text crdt.
-                //
-                // TODO(adz): This is strictly speaking not collision-resistant but we're limited
-                // here by the 8 bytes / 64 bit from the u64 `PeerId` type from Loro. In practice
-                // this should not really be a problem, but it would be nice if the Loro API would
-                // change some day.
-                let mut buf = [0u8; 8];
-                buf[..8].copy_from_slice(&public_key.0.as_bytes()[..8]);
-                u64::from_be_bytes(buf)
-            })
-            .expect("set peer id for new document");
+            doc.set_peer_id(peer_id(&public_key))
+                .expect("set peer id for new document");
+            // Formatting typed at the end of a formatted range continues it, except for links
+            let mut text_style = loro::StyleConfigMap::new();
+            for mark in ["bold", "italic", "underline", "strikethrough", "heading"] {
+                text_style.insert(
+                    mark.into(),
+                    loro::StyleConfig {
+                        expand: loro::ExpandType::After,
+                    },
+                );
+            }
+            text_style.insert(
+                "link".into(),
+                loro::StyleConfig {
+                    expand: loro::ExpandType::None,
+                },
+            );
+            doc.config_text_style(text_style);
+            // Timestamps of changes are needed to browse the history of the document
+            doc.set_record_timestamp(true);
+            doc.set_change_merge_interval(CHECKPOINT_INTERVAL);
 
             doc.subscribe(
                 &TEXT_CONTAINER_ID,
@@ -459,12 +1806,24 @@ mod imp {
                             let mut index = 0;
                             for delta in commit {
                                 match delta {
-                                    loro::TextDelta::Retain { retain, .. } => {
+                                    loro::TextDelta::Retain { retain, attributes } => {
+                                        if attributes.is_some() {
+                                            obj.imp().emit_text_formatted(
+                                                index as i32,
+                                                (index + retain) as i32,
+                                            );
+                                        }
                                         index += retain;
                                     }
-                                    loro::TextDelta::Insert { insert, .. } => {
-                                        let len = insert.len();
+                                    loro::TextDelta::Insert { insert, attributes } => {
+                                        let len = insert.chars().count();
                                         obj.imp().emit_text_inserted(index as i32, insert);
+                                        if attributes.is_some() {
+                                            obj.imp().emit_text_formatted(
+                                                index as i32,
+                                                (index + len) as i32,
+                                            );
+                                        }
                                         index += len;
                                     }
                                     loro::TextDelta::Delete { delete } => {
@@ -477,7 +1836,48 @@ mod imp {
                             }
                         }
 
-                        obj.notify_text();
+                        obj.notify_text();
+                    }
+                )),
+            )
+            .detach();
+
+            doc.subscribe(
+                &ACTION_ITEMS_CONTAINER_ID,
+                Arc::new(clone!(
+                    #[weak]
+                    obj,
+                    move |_| {
+                        obj.emit_by_name::<()>("action-items-changed", &[]);
+                    }
+                )),
+            )
+            .detach();
+
+            doc.subscribe(
+                &METADATA_CONTAINER_ID,
+                Arc::new(clone!(
+                    #[weak]
+                    obj,
+                    move |_| {
+                        obj.imp().update_name();
+                        obj.notify_title();
+                        obj.notify_icon();
+                        obj.notify_color();
+                        obj.notify_description();
+                        obj.notify_created();
+                    }
+                )),
+            )
+            .detach();
+
+            doc.subscribe(
+                &COMMENTS_CONTAINER_ID,
+                Arc::new(clone!(
+                    #[weak]
+                    obj,
+                    move |_| {
+                        obj.emit_by_name::<()>("comments-changed", &[]);
                     }
                 )),
             )
@@ -517,6 +1917,11 @@ mod imp {
             let mut undo_manager = loro::UndoManager::new(&doc);
             // FIXME: Would be nice to also use `loro::UndoManager::group_start()/group_end()`
             undo_manager.set_merge_interval(1000);
+            undo_manager.add_exclude_origin_prefix(PEER_CLAIM_ORIGIN);
+            undo_manager.add_exclude_origin_prefix(METADATA_ORIGIN);
+            undo_manager.add_exclude_origin_prefix(ACTION_ITEMS_ORIGIN);
+            undo_manager.add_exclude_origin_prefix(COMMENTS_ORIGIN);
+            undo_manager.add_exclude_origin_prefix(TAGS_ORIGIN);
 
             undo_manager.set_on_push(Some(Box::new(clone!(
                 #[weak]
@@ -662,6 +2067,11 @@ mod imp {
                     Signal::builder("range-deleted")
                         .param_types([glib::types::Type::I32, glib::types::Type::I32])
                         .build(),
+                    Signal::builder("text-formatted")
+                        .param_types([glib::types::Type::I32, glib::types::Type::I32])
+                        .build(),
+                    Signal::builder("action-items-changed").build(),
+                    Signal::builder("comments-changed").build(),
                     Signal::builder("remote-insert-cursor")
                         .param_types([
                             Author::static_type(),
@@ -699,28 +2109,70 @@ glib::wrapper! {
 impl Document {
     pub(crate) fn new(
         service: &Service,
-        id: &DocumentId,
+        secret: &DocumentSecret,
         main_context: Option<&glib::MainContext>,
     ) -> Self {
         glib::Object::builder()
             .property("service", service)
-            .property("id", id)
+            .property("id", secret.document_id())
+            .property("secret", secret)
             .property("main-context", main_context)
             .build()
     }
 
+    /// Creates a new document owned by this device.
+    pub(crate) fn create(service: &Service, main_context: Option<&glib::MainContext>) -> Self {
+        let owner = service.private_key().public_key();
+        let document = Self::new(service, &DocumentSecret::new(&owner), main_context);
+
+        let created = glib::DateTime::now_utc().map(|created| created.to_unix());
+        if let Err(error) = document.imp().set_metadata("created", created.ok()) {
+            error!("Failed to store creation date of document: {error}");
+        }
+
+        document
+    }
+
     pub(crate) fn with_state(
         service: &Service,
-        id: Option<&DocumentId>,
+        secret: &DocumentSecret,
         name: Option<&str>,
         last_accessed: Option<&glib::DateTime>,
+        unseen_changes: u32,
+        bookmark: Option<loro::Frontiers>,
     ) -> Self {
-        glib::Object::builder()
+        let obj: Self = glib::Object::builder()
             .property("service", service)
-            .property("id", id)
+            .property("id", secret.document_id())
+            .property("secret", secret)
             .property("name", name)
             .property("last-accessed", last_accessed)
-            .build()
+            .property("unseen-changes", unseen_changes)
+            .build();
+        *obj.imp().bookmark.lock().unwrap() = bookmark;
+
+        obj
+    }
+
+    /// Code that is shared with others to invite them to collaborate on this document.
+    ///
+    /// It's the hex encoded document secret, the document id is derived from it when joining.
+    /// `None` for legacy documents, their secret is just their id, which anyone may know, so
+    /// nobody can join them anymore.
+    pub fn invite_code(&self) -> Option<String> {
+        let secret = self.secret();
+        (!secret.is_legacy()).then(|| secret.to_hex())
+    }
+
+    /// Parses an invite code created by [`Document::invite_code`].
+    ///
+    /// Ids of legacy documents aren't accepted, see [`Document::invite_code`].
+    pub fn parse_invite_code(invite_code: &str) -> Result<DocumentSecret, FromHexError> {
+        if invite_code.len() != INVITE_CODE_LENGTH {
+            return Err(FromHexError::InvalidStringLength);
+        }
+
+        DocumentSecret::from_hex(invite_code)
     }
 
     pub fn insert_text(&self, pos: i32, text: &str) -> Result<()> {
@@ -732,6 +2184,26 @@ impl Document {
             .delete_text(start_pos as usize, (end_pos - start_pos) as usize)
     }
 
+    /// Format the text between `start_pos` and `end_pos` with `mark`.
+    ///
+    /// A link or heading replaces other links or headings in the range.
+    pub fn mark(&self, start_pos: i32, end_pos: i32, mark: &Mark) -> Result<()> {
+        self.imp()
+            .mark_text(start_pos as usize..end_pos as usize, mark)
+    }
+
+    /// Remove the formatting of the kind of `mark` between `start_pos` and `end_pos`, e.g. any
+    /// link for [`Mark::Link`].
+    pub fn unmark(&self, start_pos: i32, end_pos: i32, mark: &Mark) -> Result<()> {
+        self.imp()
+            .unmark_text(start_pos as usize..end_pos as usize, mark)
+    }
+
+    /// All formatted ranges of the text, overlapping when text has more than one mark.
+    pub fn marks(&self) -> Vec<MarkedRange> {
+        self.imp().text_marks()
+    }
+
     pub fn undo(&self) -> (i32, Option<i32>) {
         let mut guard = self.imp().undo_manager.lock().unwrap();
         let undo_manager = guard.as_mut().expect("UndoManager exists always");
@@ -792,18 +2264,22 @@ impl Document {
     }
 
     pub async fn subscribe(&self) {
-        if self.subscribed() {
+        if self.subscribed() || self.read_only() {
             return;
         }
 
+        // The subscription takes over syncing the document
+        self.stop_background_sync().await;
+
         let handle = DocumentHandle(self.downgrade());
-        match self.service().node().subscribe(self.id(), handle).await {
+        match self.service().node().subscribe(self.secret(), handle).await {
             Ok(subscription) => {
                 self.imp()
                     .subscription
                     .write()
                     .unwrap()
                     .replace(Arc::new(subscription));
+                self.imp().claim_peer_id();
             }
             Err(error) => {
                 error!("Failed to subscribe to document: {}", error);
@@ -815,22 +2291,41 @@ impl Document {
         self.store_snapshot().await;
         self.imp().store_name();
 
+        // Only highlight changes when others changed something since we last saw the document
+        let last_seen_frontiers = self
+            .imp()
+            .bookmark
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|_| self.unseen_changes() > 0);
+        self.imp().set_last_seen_frontiers(last_seen_frontiers);
+        self.imp().set_unseen_changes(0);
         self.notify_last_accessed();
         self.notify_subscribed();
     }
 
     pub async fn unsubscribe(&self) {
+        // Old versions of a document are never synced
+        if self.read_only() {
+            return;
+        }
+
         let subscription = self.imp().subscription.write().unwrap().take();
 
         if let Some(subscription) = subscription {
-            let snapshot_bytes = self
-                .imp()
-                .crdt_doc
-                .get()
-                .expect("crdt_doc to be set")
+            let doc = self.imp().crdt_doc.get().expect("crdt_doc to be set");
+            let snapshot_bytes = doc
                 .export(ExportMode::Snapshot)
                 .expect("encoded crdt snapshot");
 
+            // Remember what we have seen, to highlight what changed when we come back
+            let frontiers = doc.oplog_frontiers();
+            if let Err(error) = subscription.set_bookmark(Some(frontiers.encode())).await {
+                error!("Failed to store the last seen state of the document: {error}");
+            }
+            *self.imp().bookmark.lock().unwrap() = Some(frontiers);
+
             if let Err(error) = subscription.send_snapshot(snapshot_bytes).await {
                 error!(
                     "Failed to send snapshot of document to the network: {}",
@@ -860,8 +2355,426 @@ impl Document {
 
         *self.imp().last_accessed.lock().unwrap() = glib::DateTime::now_utc().ok();
 
+        self.imp().reset_sync_state();
         self.notify_last_accessed();
         self.notify_subscribed();
+
+        self.update_background_sync().await;
+    }
+
+    /// Text others inserted since the document was last unsubscribed, with its authors.
+    ///
+    /// Empty if nobody else changed the document in the meantime or once the changes were
+    /// dismissed with [`Document::dismiss_changes_since_last_seen`].
+    pub fn changes_since_last_seen(&self) -> Vec<AuthoredRange> {
+        let Some(frontiers) = self.imp().last_seen_frontiers.lock().unwrap().clone() else {
+            return Vec::new();
+        };
+
+        self.imp()
+            .inserted_since(&frontiers)
+            .into_iter()
+            .filter_map(|(range, peer)| {
+                Some(AuthoredRange {
+                    start: range.start as i32,
+                    end: range.end as i32,
+                    author: self.imp().author_for_peer(peer)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Who wrote the text between `range.start` and `range.end`, as consecutive ranges.
+    ///
+    /// Text of authors we don't know (yet) is left out.
+    pub fn authorship(&self, range: std::ops::Range<i32>) -> Vec<AuthoredRange> {
+        let range = range.start.max(0) as usize..range.end.max(0) as usize;
+
+        // Few authors wrote many ranges, look each of them up only once
+        let mut authors: HashMap<loro::PeerID, Option<Author>> = HashMap::new();
+        self.imp()
+            .inserted_by(range, |_| true)
+            .into_iter()
+            .filter_map(|(range, peer)| {
+                let author = authors
+                    .entry(peer)
+                    .or_insert_with(|| self.imp().author_for_peer(peer))
+                    .clone()?;
+
+                Some(AuthoredRange {
+                    start: range.start as i32,
+                    end: range.end as i32,
+                    author,
+                })
+            })
+            .collect()
+    }
+
+    /// Stop highlighting the changes since the document was last unsubscribed.
+    pub fn dismiss_changes_since_last_seen(&self) {
+        self.imp().set_last_seen_frontiers(None);
+    }
+
+    /// All versions of the document, from oldest to most recent.
+    ///
+    /// Each version contains all changes up to and including its own one, also concurrent
+    /// changes which were made before it.
+    pub fn checkpoints(&self) -> Vec<Checkpoint> {
+        let doc = self.imp().crdt_doc.get().expect("crdt_doc to be set");
+
+        let mut version = loro::VersionVector::default();
+        self.imp()
+            .changes_between(&Default::default(), &doc.oplog_vv())
+            .into_iter()
+            .map(|change| {
+                version.extend_to_include_last_id(loro::ID::new(
+                    change.id.peer,
+                    change.id.counter + change.len as i32 - 1,
+                ));
+
+                Checkpoint {
+                    timestamp: (change.timestamp > 0)
+                        .then(|| glib::DateTime::from_unix_utc(change.timestamp).ok())
+                        .flatten(),
+                    author: self.imp().author_for_peer(change.id.peer),
+                    frontiers: doc.vv_to_frontiers(&version),
+                }
+            })
+            .collect()
+    }
+
+    /// The text at `checkpoint`, without checking out a copy of the whole document.
+    pub fn text_at(&self, checkpoint: &Checkpoint) -> Result<String> {
+        self.imp().text_at(&checkpoint.frontiers)
+    }
+
+    /// A read-only copy of the document at `checkpoint`.
+    ///
+    /// The copy isn't synced with anybody, it can't be subscribed to.
+    pub fn checkout(&self, checkpoint: &Checkpoint) -> Result<Document> {
+        let document = Document::new(&self.service(), &self.secret(), Some(&self.main_context()));
+        let doc = self.imp().crdt_doc.get().expect("crdt_doc to be set");
+        let version_doc = document.imp().crdt_doc.get().expect("crdt_doc to be set");
+
+        version_doc.import(&doc.export(ExportMode::Snapshot)?)?;
+        // Loro doesn't allow to edit a checked out version
+        version_doc.checkout(&checkpoint.frontiers)?;
+
+        Ok(document)
+    }
+
+    /// Replace the text with the text at `checkpoint`.
+    ///
+    /// The restored text is a new change on top of the current version, so it's synced with
+    /// others and can be undone.
+    pub fn restore(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let text = self.imp().text_at(&checkpoint.frontiers)?;
+        self.imp().replace_text(&text)
+    }
+
+    /// What changed in the text between the versions `from` and `to`, character by character.
+    ///
+    /// The spans contain the text of both versions in order, text only in `from` is deleted
+    /// and text only in `to` is inserted. The versions are compared on a separate thread, since
+    /// both need to be checked out.
+    pub async fn diff(&self, from: &Checkpoint, to: &Checkpoint) -> Result<Vec<DiffSpan>> {
+        let doc = self
+            .imp()
+            .crdt_doc
+            .get()
+            .expect("crdt_doc to be set")
+            .clone();
+        let (from, to) = (from.frontiers.clone(), to.frontiers.clone());
+        let spans = gio::spawn_blocking(move || diff_spans(&doc, &from, &to))
+            .await
+            .map_err(|_| anyhow::anyhow!("Comparing versions of the document panicked"))??;
+
+        Ok(spans
+            .into_iter()
+            .map(|(kind, text, peer)| DiffSpan {
+                kind,
+                text,
+                author: peer.and_then(|peer| self.imp().author_for_peer(peer)),
+            })
+            .collect())
+    }
+
+    /// Load the stored content of the document without subscribing to it.
+    ///
+    /// Subscribed documents are loaded already.
+    pub async fn load(&self) {
+        if self.subscribed() || self.read_only() {
+            return;
+        }
+
+        match self.service().node().topic_content(self.secret()).await {
+            Ok(content) => {
+                for (author, data) in content {
+                    self.imp().on_remote_message(data);
+                    self.authors().add(PublicKey(author));
+                }
+            }
+            Err(error) => {
+                error!("Failed to load document: {error}");
+            }
+        }
+    }
+
+    /// The named versions of the document, ordered by name.
+    pub fn tags(&self) -> Vec<Tag> {
+        let doc = self.imp().crdt_doc.get().expect("crdt_doc to be set");
+        let loro::LoroValue::Map(tags) = doc.get_map(&*imp::TAGS_CONTAINER_ID).get_value() else {
+            return Vec::new();
+        };
+
+        let mut tags: Vec<Tag> = tags
+            .iter()
+            .filter_map(|(name, frontiers)| {
+                let loro::LoroValue::Binary(frontiers) = frontiers else {
+                    return None;
+                };
+
+                Some(Tag {
+                    name: name.to_owned(),
+                    checkpoint: self
+                        .imp()
+                        .checkpoint_at(loro::Frontiers::decode(frontiers).ok()?),
+                })
+            })
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        tags
+    }
+
+    /// Give the current version of the document a name, e.g. "Agreed minutes 2026-10-14".
+    ///
+    /// Tags are part of the document, so they are synced with everyone else. An existing tag
+    /// with the same name is moved to the current version.
+    pub fn create_tag(&self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Tags need a name");
+        }
+
+        let doc = self.imp().crdt_doc.get().expect("crdt_doc to be set");
+        let frontiers = doc.oplog_frontiers();
+        doc.get_map(&*imp::TAGS_CONTAINER_ID)
+            .insert(name, frontiers.encode())?;
+        doc.set_next_commit_origin(imp::TAGS_ORIGIN);
+        doc.commit();
+
+        Ok(())
+    }
+
+    /// The text of the version with the tag `name`, `None` if there is no such tag.
+    pub fn text_at_tag(&self, name: &str) -> Option<String> {
+        let tag = self.tags().into_iter().find(|tag| tag.name == name)?;
+
+        match self.imp().text_at(&tag.checkpoint.frontiers) {
+            Ok(text) => Some(text),
+            Err(error) => {
+                error!("Failed to get the text of tag {name}: {error}");
+                None
+            }
+        }
+    }
+
+    /// The action items of the document, in the order they were arranged in.
+    pub fn action_items(&self) -> Vec<ActionItem> {
+        self.imp().action_items()
+    }
+
+    /// Add an action item which isn't done yet to the end of the list.
+    pub fn add_action_item(&self, text: &str) -> Result<()> {
+        self.imp().add_action_item(text)
+    }
+
+    pub fn set_action_item_text(&self, item: &ActionItem, text: &str) -> Result<()> {
+        self.imp()
+            .update_action_item(item, |map| map.insert("text", text))
+    }
+
+    pub fn set_action_item_done(&self, item: &ActionItem, done: bool) -> Result<()> {
+        self.imp()
+            .update_action_item(item, |map| map.insert("done", done))
+    }
+
+    pub fn set_action_item_assignee(
+        &self,
+        item: &ActionItem,
+        assignee: Option<&PublicKey>,
+    ) -> Result<()> {
+        self.imp().update_action_item(item, |map| match assignee {
+            Some(assignee) => map.insert("assignee", assignee.as_bytes().to_vec()),
+            None => map.delete("assignee"),
+        })
+    }
+
+    pub fn set_action_item_due(
+        &self,
+        item: &ActionItem,
+        due: Option<&glib::DateTime>,
+    ) -> Result<()> {
+        self.imp().update_action_item(item, |map| match due {
+            Some(due) => map.insert("due", due.to_unix()),
+            None => map.delete("due"),
+        })
+    }
+
+    /// Move the action item `item` to `position` in the list.
+    pub fn move_action_item(&self, item: &ActionItem, position: u32) -> Result<()> {
+        self.imp().move_action_item(item, position as usize)
+    }
+
+    pub fn remove_action_item(&self, item: &ActionItem) -> Result<()> {
+        self.imp().remove_action_item(item)
+    }
+
+    /// The open tasks of the author with `public_key`.
+    ///
+    /// These are the unchecked Markdown checkboxes in the text which mention the name of the
+    /// author, e.g. `- [ ] @Blue Fox send budget`, followed by the action items assigned to the
+    /// author which aren't done yet.
+    ///
+    /// Documents which aren't subscribed are read from the latest snapshot of each author,
+    /// without loading them, so changes made after the snapshots may be missing.
+    pub async fn tasks_for(&self, public_key: &PublicKey) -> Vec<String> {
+        let doc = self.imp().crdt_doc.get().expect("crdt_doc to be set");
+        if self.subscribed() || self.read_only() {
+            return tasks_in(doc, public_key);
+        }
+
+        let snapshots = match self.service().node().latest_snapshots(self.secret()).await {
+            Ok(snapshots) => snapshots,
+            Err(error) => {
+                error!("Failed to read snapshots of document: {error}");
+                return Vec::new();
+            }
+        };
+
+        // The document itself stays unloaded, the snapshots are dropped again right away.
+        // Importing them takes a while for long documents, so it's done on a separate thread.
+        let public_key = public_key.clone();
+        gio::spawn_blocking(move || {
+            let stored_doc = LoroDoc::new();
+            for (_, snapshot) in snapshots {
+                if let Err(error) = stored_doc.import(&snapshot) {
+                    error!("Failed to read snapshot of document: {error}");
+                }
+            }
+
+            tasks_in(&stored_doc, &public_key)
+        })
+        .await
+        .unwrap_or_else(|_| {
+            error!("Reading the tasks of the document panicked");
+            Vec::new()
+        })
+    }
+
+    /// Give the document a title which is used as its name, instead of the first line of the
+    /// text.
+    ///
+    /// An empty or `None` title removes it. Like the rest of the metadata, the title is part of
+    /// the document, so it's changed for everyone.
+    pub fn set_title(&self, title: Option<&str>) -> Result<()> {
+        self.imp().set_metadata(
+            "title",
+            title.map(str::trim).filter(|title| !title.is_empty()),
+        )
+    }
+
+    /// Set an emoji representing the document.
+    pub fn set_icon(&self, icon: Option<&str>) -> Result<()> {
+        self.imp()
+            .set_metadata("icon", icon.map(str::trim).filter(|icon| !icon.is_empty()))
+    }
+
+    /// Set the color of the document, in hex format e.g. `#3584e4`.
+    pub fn set_color(&self, color: Option<&str>) -> Result<()> {
+        self.imp().set_metadata("color", color)
+    }
+
+    pub fn set_description(&self, description: Option<&str>) -> Result<()> {
+        self.imp().set_metadata(
+            "description",
+            description
+                .map(str::trim)
+                .filter(|description| !description.is_empty()),
+        )
+    }
+
+    /// The comments on the text, ordered by their position.
+    pub fn comments(&self) -> Vec<Comment> {
+        self.imp().comments()
+    }
+
+    /// Start a discussion about the text between `start_pos` and `end_pos`.
+    ///
+    /// Comments are part of the document, so everyone else can reply to them.
+    pub fn add_comment(&self, start_pos: i32, end_pos: i32, text: &str) -> Result<()> {
+        self.imp()
+            .add_comment(start_pos as usize..end_pos as usize, text)
+    }
+
+    pub fn reply_to_comment(&self, comment: &Comment, text: &str) -> Result<()> {
+        self.imp().reply_to_comment(comment, text)
+    }
+
+    pub fn set_comment_resolved(&self, comment: &Comment, resolved: bool) -> Result<()> {
+        self.imp().set_comment_resolved(comment, resolved)
+    }
+
+    pub fn remove_comment(&self, comment: &Comment) -> Result<()> {
+        self.imp().remove_comment(comment)
+    }
+
+    /// Start or stop syncing this document in the background, depending on the settings.
+    pub(crate) async fn update_background_sync(&self) {
+        if !self.subscribed() && self.service().syncs_in_background(self) {
+            self.sync_in_background().await;
+        } else {
+            self.stop_background_sync().await;
+        }
+    }
+
+    /// Keep this document in sync while it isn't subscribed.
+    ///
+    /// Changes are only stored, the document learns about them once it's subscribed again.
+    pub(crate) async fn sync_in_background(&self) {
+        if self.subscribed() || self.imp().background_subscription.lock().unwrap().is_some() {
+            return;
+        }
+
+        let handle = BackgroundHandle(self.downgrade());
+        match self
+            .service()
+            .node()
+            .subscribe_in_background(self.secret(), handle)
+            .await
+        {
+            Ok(subscription) => {
+                self.imp()
+                    .background_subscription
+                    .lock()
+                    .unwrap()
+                    .replace(subscription);
+            }
+            Err(error) => {
+                error!("Failed to sync document in the background: {error}");
+            }
+        }
+    }
+
+    pub(crate) async fn stop_background_sync(&self) {
+        let subscription = self.imp().background_subscription.lock().unwrap().take();
+
+        if let Some(subscription) = subscription
+            && let Err(error) = subscription.unsubscribe().await
+        {
+            error!("Failed to stop syncing document in the background: {error}");
+        }
     }
 
     /// Persist the snapshot.
@@ -884,7 +2797,62 @@ impl Document {
         }
     }
 
+    /// Give `author` the given role in this document, or remove them if `role` is `None`.
+    ///
+    /// Only the owner of the document can change the roles of other authors.
+    pub async fn set_author_role(&self, author: &PublicKey, role: Option<Role>) -> Result<()> {
+        let Some(subscription) = self.imp().subscription() else {
+            anyhow::bail!("Document is not subscribed");
+        };
+
+        subscription.set_member_role(author.0, role).await?;
+
+        Ok(())
+    }
+
+    /// Remove `author` from this document.
+    ///
+    /// Only the owner of the document can remove authors. The document gets re-keyed and a fresh
+    /// snapshot is stored, so that the removed author can't read anything written from now on.
+    pub async fn remove_author(&self, author: &PublicKey) -> Result<()> {
+        let Some(subscription) = self.imp().subscription() else {
+            anyhow::bail!("Document is not subscribed");
+        };
+
+        subscription.revoke(author.0).await?;
+        self.store_snapshot().await;
+
+        Ok(())
+    }
+
+    /// Change the role of authors who haven't been given a role explicitly.
+    ///
+    /// Setting it to [`Role::Read`] turns this into a read-only document for everyone else.
+    pub async fn set_default_role(&self, role: Option<Role>) -> Result<()> {
+        let Some(subscription) = self.imp().subscription() else {
+            anyhow::bail!("Document is not subscribed");
+        };
+
+        subscription.set_default_role(role).await?;
+
+        Ok(())
+    }
+
+    /// Export this document into a bundle, which can be opened with
+    /// [`Service::import_document`].
+    ///
+    /// The bundle doesn't contain the secret of the document, so it can only be opened together
+    /// with the invite code.
+    pub async fn export(&self) -> Result<Vec<u8>, BundleError> {
+        // Make sure the bundle contains the most recent changes
+        self.store_snapshot().await;
+
+        self.service().node().export_topic(self.id()).await
+    }
+
     pub async fn delete(&self) {
+        self.stop_background_sync().await;
+
         if let Err(error) = self.service().node().delete_topic(self.id()).await {
             error!("Failed to delete document from document store: {}", error);
             return;
@@ -920,15 +2888,55 @@ impl SubscribableTopic for DocumentHandle {
         }
     }
 
+    fn author_removed(&self, author: p2panda_core::PublicKey, removed_at: DateTime<Utc>) {
+        if let Some(document) = self.0.upgrade() {
+            document.main_context().invoke(move || {
+                let author = document.authors().add(PublicKey(author));
+                author.set_online(false);
+                if let Ok(removed_at) = glib::DateTime::from_unix_utc(removed_at.timestamp()) {
+                    author.set_removed(&removed_at);
+                }
+            });
+        }
+    }
+
     fn author_left(&self, author: p2panda_core::PublicKey) {
         if let Some(document) = self.0.upgrade() {
             document.main_context().invoke(move || {
+                // A sync session can't complete once the author went away
+                document.imp().sync_finished(author, false);
                 let author = document.authors().add(PublicKey(author));
                 author.set_online(false);
             });
         }
     }
 
+    fn sync_started(&self, peer: p2panda_core::PublicKey) {
+        if let Some(document) = self.0.upgrade() {
+            document.main_context().invoke(move || {
+                document.imp().sync_started(peer);
+            });
+        }
+    }
+
+    fn sync_progress(&self, _peer: p2panda_core::PublicKey, _operations: u64) {}
+
+    fn sync_completed(&self, peer: p2panda_core::PublicKey) {
+        if let Some(document) = self.0.upgrade() {
+            document.main_context().invoke(move || {
+                document.imp().sync_finished(peer, true);
+            });
+        }
+    }
+
+    fn sync_failed(&self, peer: p2panda_core::PublicKey) {
+        if let Some(document) = self.0.upgrade() {
+            document.main_context().invoke(move || {
+                document.imp().sync_finished(peer, false);
+            });
+        }
+    }
+
     fn ephemeral_bytes_received(&self, author: p2panda_core::PublicKey, data: Vec<u8>) {
         if let Some(document) = self.0.upgrade() {
             document.main_context().invoke(move || {
@@ -949,3 +2957,43 @@ impl SubscribableTopic for DocumentHandle {
         }
     }
 }
+
+/// Handle of a document which is only synced in the background.
+struct BackgroundHandle(glib::WeakRef<Document>);
+
+impl SubscribableTopic for BackgroundHandle {
+    fn bytes_received(&self, author: p2panda_core::PublicKey, _data: Vec<u8>) {
+        if let Some(document) = self.0.upgrade() {
+            document.main_context().invoke(move || {
+                let author = document.authors().add(PublicKey(author));
+                if !author.is_this_device() {
+                    let imp = document.imp();
+                    imp.set_unseen_changes(imp.unseen_changes.get().saturating_add(1));
+                }
+            });
+        }
+    }
+
+    fn author_removed(&self, author: p2panda_core::PublicKey, removed_at: DateTime<Utc>) {
+        if let Some(document) = self.0.upgrade() {
+            document.main_context().invoke(move || {
+                let author = document.authors().add(PublicKey(author));
+                if let Ok(removed_at) = glib::DateTime::from_unix_utc(removed_at.timestamp()) {
+                    author.set_removed(&removed_at);
+                }
+            });
+        }
+    }
+
+    fn author_joined(&self, _author: p2panda_core::PublicKey) {}
+    fn author_left(&self, _author: p2panda_core::PublicKey) {}
+    fn ephemeral_bytes_received(&self, _author: p2panda_core::PublicKey, _data: Vec<u8>) {}
+    fn sync_started(&self, _peer: p2panda_core::PublicKey) {}
+    fn sync_progress(&self, _peer: p2panda_core::PublicKey, _operations: u64) {}
+    fn sync_completed(&self, _peer: p2panda_core::PublicKey) {}
+    fn sync_failed(&self, _peer: p2panda_core::PublicKey) {}
+
+    fn error(&self, error: TopicSubscriptionError) {
+        error!("Network error received for document synced in the background: {error}");
+    }
+}
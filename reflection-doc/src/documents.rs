@@ -4,21 +4,30 @@ use gio::prelude::*;
 use gio::subclass::prelude::ListModelImpl;
 use glib::subclass::prelude::*;
 use indexmap::IndexMap;
+use tracing::warn;
 
 use crate::identity::PublicKey;
 use crate::service::StartupError;
 use crate::{
     author::Author,
-    document::{Document, DocumentId},
+    document::{Document, DocumentId, DocumentSecret},
     service::Service,
 };
 
+/// An open task of an author and the document it's in.
+#[derive(Debug, Clone)]
+pub struct AssignedTask {
+    pub document: Document,
+    pub text: String,
+}
+
 mod imp {
     use super::*;
 
     #[derive(Default)]
     pub struct Documents {
         pub(super) list: RwLock<IndexMap<DocumentId, Document>>,
+        pub(super) unreadable: RwLock<Vec<String>>,
     }
 
     #[glib::object_subclass]
@@ -75,8 +84,20 @@ impl Documents {
         let mut list = self.imp().list.write().unwrap();
         assert!(list.is_empty());
 
-        let documents_len = documents.len();
+        let mut unreadable = self.imp().unreadable.write().unwrap();
         for document in documents {
+            // Documents created before payloads were encrypted have a legacy secret, only
+            // documents whose secret is incomplete or broken can't be read
+            let secret = document.secret.map(DocumentSecret::from);
+            let Some(secret) = secret.filter(|secret| secret.document_id() == document.id) else {
+                warn!(
+                    "Skipping document {} since its secret is invalid",
+                    document.id
+                );
+                unreadable.push(document.name.unwrap_or_else(|| document.id.to_hex()));
+                continue;
+            };
+
             let last_accessed = document.last_accessed.and_then(|last_accessed| {
                 glib::DateTime::from_unix_utc(last_accessed.timestamp()).ok()
             });
@@ -95,16 +116,29 @@ impl Documents {
                         let last_seen = author.last_seen.and_then(|last_seen| {
                             glib::DateTime::from_unix_utc(last_seen.timestamp()).ok()
                         });
-                        Author::with_state(&author_public_key, last_seen.as_ref())
+                        let removed_at = author.removed_at.and_then(|removed_at| {
+                            glib::DateTime::from_unix_utc(removed_at.timestamp()).ok()
+                        });
+                        Author::with_state(
+                            &author_public_key,
+                            last_seen.as_ref(),
+                            removed_at.as_ref(),
+                        )
                     }
                 })
                 .collect();
 
+            let bookmark = document
+                .bookmark
+                .and_then(|bookmark| loro::Frontiers::decode(&bookmark).ok());
+
             let obj = Document::with_state(
                 service,
-                Some(&document.id),
+                &secret,
                 document.name.as_deref(),
                 last_accessed.as_ref(),
+                document.unseen_changes.try_into().unwrap_or(u32::MAX),
+                bookmark,
             );
 
             obj.authors().load(authors);
@@ -112,12 +146,21 @@ impl Documents {
             list.insert(document.id, obj);
         }
 
+        let documents_len = list.len();
         drop(list);
+        drop(unreadable);
         self.items_changed(0, 0, documents_len as u32);
 
         Ok(())
     }
 
+    /// Names of stored documents which can't be opened, since their secret is missing parts.
+    ///
+    /// Documents without a name are listed with their id.
+    pub fn unreadable(&self) -> Vec<String> {
+        self.imp().unreadable.read().unwrap().clone()
+    }
+
     pub(crate) fn add(&self, document: Document) {
         let mut list = self.imp().list.write().unwrap();
         let document_id = document.id();
@@ -146,4 +189,28 @@ impl Documents {
 
         list.get(document_id).cloned()
     }
+
+    /// The open tasks of the author with `public_key` in all documents, see
+    /// [`Document::tasks_for()`].
+    ///
+    /// Documents which aren't open are read from their stored snapshots, without loading them.
+    pub async fn tasks_for(&self, public_key: &PublicKey) -> Vec<AssignedTask> {
+        let documents: Vec<Document> = self.imp().list.read().unwrap().values().cloned().collect();
+
+        let mut tasks = Vec::new();
+        for document in documents {
+            tasks.extend(
+                document
+                    .tasks_for(public_key)
+                    .await
+                    .into_iter()
+                    .map(|text| AssignedTask {
+                        document: document.clone(),
+                        text,
+                    }),
+            );
+        }
+
+        tasks
+    }
 }
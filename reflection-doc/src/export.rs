@@ -0,0 +1,288 @@
+use gio::prelude::*;
+
+use crate::author::Author;
+use crate::document::{Document, Mark};
+
+/// File formats the text of a document can be exported to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Markdown with YAML front matter, the formatting is converted to Markdown syntax.
+    Markdown,
+    /// A standalone HTML page.
+    Html,
+    /// The text without formatting.
+    PlainText,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::PlainText,
+    ];
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::PlainText => "txt",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "text/markdown",
+            ExportFormat::Html => "text/html",
+            ExportFormat::PlainText => "text/plain",
+        }
+    }
+
+    /// The format of files with the suffix `suffix`, e.g. `md`.
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.suffix().eq_ignore_ascii_case(suffix))
+    }
+}
+
+/// A line of the text, split into spans with the same formatting.
+struct Line {
+    /// The level of the heading, if the line is one.
+    heading: Option<u8>,
+    /// The marks of each span are in [`nesting_order`].
+    spans: Vec<(String, Vec<Mark>)>,
+}
+
+impl Document {
+    /// The text of the document in `format`, preceded by its title, authors and the date it was
+    /// last accessed.
+    ///
+    /// Documents which aren't subscribed need to be loaded first.
+    pub fn export_as(&self, format: ExportFormat) -> String {
+        let title = self.name().unwrap_or_default();
+        let authors: Vec<String> = self
+            .authors()
+            .iter::<Author>()
+            .filter_map(Result::ok)
+            .filter(|author| author.removed_at().is_none())
+            .map(|author| author.name())
+            .collect();
+        // Only documents which were closed again know when they were accessed last, for open
+        // documents and read-only versions it's today
+        let date = self
+            .last_accessed()
+            .or_else(|| glib::DateTime::now_utc().ok())
+            .and_then(|date| date.format("%F").ok())
+            .map(|date| date.to_string())
+            .unwrap_or_default();
+        let description = self.description();
+        let lines = self.lines();
+
+        match format {
+            ExportFormat::Markdown => {
+                let mut markdown = String::from("---\n");
+                markdown.push_str(&format!("title: {}\n", yaml_string(&title)));
+                if let Some(description) = &description {
+                    markdown.push_str(&format!("description: {}\n", yaml_string(description)));
+                }
+                markdown.push_str("authors:\n");
+                for author in &authors {
+                    markdown.push_str(&format!("  - {}\n", yaml_string(author)));
+                }
+                markdown.push_str(&format!("date: {date}\n---\n\n"));
+
+                let lines: Vec<String> = lines
+                    .iter()
+                    .map(|line| {
+                        let heading = line
+                            .heading
+                            .map(|level| format!("{} ", "#".repeat(level as usize)))
+                            .unwrap_or_default();
+                        let spans: String = line
+                            .spans
+                            .iter()
+                            .map(|(text, marks)| markdown_span(text, marks))
+                            .collect();
+                        format!("{heading}{spans}")
+                    })
+                    .collect();
+                markdown.push_str(&lines.join("\n"));
+
+                markdown
+            }
+            ExportFormat::Html => {
+                let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n");
+                html.push_str("<meta charset=\"utf-8\">\n");
+                html.push_str(&format!("<title>{}</title>\n", escape_html(&title)));
+                html.push_str(&format!(
+                    "<meta name=\"author\" content=\"{}\">\n",
+                    escape_html(&authors.join(", "))
+                ));
+                if let Some(description) = &description {
+                    html.push_str(&format!(
+                        "<meta name=\"description\" content=\"{}\">\n",
+                        escape_html(description)
+                    ));
+                }
+                html.push_str(&format!("<meta name=\"date\" content=\"{date}\">\n"));
+                html.push_str("</head>\n<body>\n");
+                html.push_str(&format!(
+                    "<p class=\"byline\">{} · <time>{date}</time></p>\n",
+                    escape_html(&authors.join(", "))
+                ));
+
+                for line in lines {
+                    if line.spans.is_empty() {
+                        continue;
+                    }
+
+                    let tag = line
+                        .heading
+                        .map_or_else(|| "p".to_owned(), |level| format!("h{level}"));
+                    html.push_str(&format!("<{tag}>"));
+                    for (text, marks) in &line.spans {
+                        html.push_str(&html_span(text, marks));
+                    }
+                    html.push_str(&format!("</{tag}>\n"));
+                }
+
+                html.push_str("</body>\n</html>\n");
+                html
+            }
+            ExportFormat::PlainText => {
+                let mut plain_text = format!("{title}\n");
+                if let Some(description) = &description {
+                    plain_text.push_str(&format!("{description}\n"));
+                }
+                plain_text.push_str(&format!("Authors: {}\n", authors.join(", ")));
+                plain_text.push_str(&format!("Date: {date}\n\n"));
+                plain_text.push_str(&self.text());
+                plain_text
+            }
+        }
+    }
+
+    /// The lines of the text with their formatting.
+    fn lines(&self) -> Vec<Line> {
+        let marks = self.marks();
+        let marks_at = |pos: i32| -> Vec<Mark> {
+            marks
+                .iter()
+                .filter(|range| range.start <= pos && pos < range.end)
+                .map(|range| range.mark.clone())
+                .collect()
+        };
+
+        let mut lines = vec![Line {
+            heading: None,
+            spans: Vec::new(),
+        }];
+        for (pos, char) in self.text().chars().enumerate() {
+            let line = lines.last_mut().unwrap();
+            if char == '\n' {
+                lines.push(Line {
+                    heading: None,
+                    spans: Vec::new(),
+                });
+                continue;
+            }
+
+            // Headings apply to the whole line, the other marks to spans of it
+            let mut char_marks = marks_at(pos as i32);
+            char_marks.retain(|mark| {
+                let Mark::Heading(level) = mark else {
+                    return true;
+                };
+                line.heading.get_or_insert(*level);
+                false
+            });
+            char_marks.sort_by_key(nesting_order);
+
+            match line.spans.last_mut() {
+                Some((text, span_marks)) if *span_marks == char_marks => text.push(char),
+                _ => line.spans.push((char.to_string(), char_marks)),
+            }
+        }
+
+        lines
+    }
+}
+
+/// The text of a span in Markdown syntax.
+fn markdown_span(text: &str, marks: &[Mark]) -> String {
+    // Emphasis can't start or end with whitespace in Markdown
+    let content = text.trim();
+    if content.is_empty() || marks.is_empty() {
+        return text.to_owned();
+    }
+
+    let mut span = content.to_owned();
+    for mark in marks {
+        span = match mark {
+            Mark::Bold => format!("**{span}**"),
+            Mark::Italic => format!("*{span}*"),
+            Mark::Strikethrough => format!("~~{span}~~"),
+            // Markdown has no syntax for underlined text, but allows HTML
+            Mark::Underline => format!("<u>{span}</u>"),
+            Mark::Link(url) if is_safe_link(url) => format!("[{span}]({url})"),
+            Mark::Link(_) | Mark::Heading(_) => span,
+        };
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{leading}{span}{trailing}")
+}
+
+/// The text of a span as HTML.
+fn html_span(text: &str, marks: &[Mark]) -> String {
+    let mut span = escape_html(text);
+    for mark in marks {
+        span = match mark {
+            Mark::Bold => format!("<strong>{span}</strong>"),
+            Mark::Italic => format!("<em>{span}</em>"),
+            Mark::Strikethrough => format!("<s>{span}</s>"),
+            Mark::Underline => format!("<u>{span}</u>"),
+            Mark::Link(url) if is_safe_link(url) => {
+                format!("<a href=\"{}\">{span}</a>", escape_html(url))
+            }
+            Mark::Link(_) | Mark::Heading(_) => span,
+        };
+    }
+
+    span
+}
+
+/// Whether `url` can be kept as link in exported files, links with other schemes like
+/// `javascript:` are exported as plain text.
+fn is_safe_link(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        ["http", "https", "mailto"]
+            .iter()
+            .any(|safe_scheme| scheme.eq_ignore_ascii_case(safe_scheme))
+    })
+}
+
+/// Order in which marks are nested, from the innermost to the outermost one.
+fn nesting_order(mark: &Mark) -> u8 {
+    match mark {
+        Mark::Bold => 0,
+        Mark::Italic => 1,
+        Mark::Strikethrough => 2,
+        Mark::Underline => 3,
+        Mark::Link(_) => 4,
+        Mark::Heading(_) => 5,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `text` as double quoted YAML string, so names like "Q3 Retro: Ops & Infra" stay intact.
+fn yaml_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -2,6 +2,7 @@ pub mod author;
 pub mod authors;
 pub mod document;
 pub mod documents;
+pub mod export;
 pub mod service;
 
 pub mod identity {
@@ -80,9 +81,16 @@ pub mod identity {
 
 #[cfg(test)]
 mod tests {
-    use crate::document::DocumentId;
-    use crate::identity::PrivateKey;
+    use std::time::{Duration, Instant};
+
+    use crate::author::Author;
+    use crate::document::{
+        DiffKind, Document, DocumentSecret, Mark, MarkedRange, claim_peer, peer_claim, peer_id,
+    };
+    use crate::export::ExportFormat;
+    use crate::identity::{PrivateKey, PublicKey};
     use crate::service::Service;
+    use reflection_node::p2panda_core;
 
     #[test_log::test(glib::async_test)]
     async fn create_document() {
@@ -91,10 +99,13 @@ mod tests {
         let context = glib::MainContext::ref_thread_default();
 
         let private_key = PrivateKey::new();
-        let service = Service::new(&private_key, None);
+        let service = Service::new(&private_key, None, None);
         service.startup().await.unwrap();
 
-        let document = service.join_document_with_main_context(&DocumentId::new(), &context);
+        let document = service.join_document_with_main_context(
+            &DocumentSecret::new(&private_key.public_key()),
+            &context,
+        );
         document.subscribe().await;
 
         assert!(document.insert_text(0, test_string).is_ok());
@@ -110,18 +121,21 @@ mod tests {
         let context = glib::MainContext::ref_thread_default();
 
         let private_key = PrivateKey::new();
-        let service = Service::new(&private_key, None);
+        let service = Service::new(&private_key, None, None);
         service.startup().await.unwrap();
 
-        let document = service.join_document_with_main_context(&DocumentId::new(), &context);
+        let document = service.join_document_with_main_context(
+            &DocumentSecret::new(&private_key.public_key()),
+            &context,
+        );
         document.subscribe().await;
-        let id = document.id();
+        let secret = document.secret();
 
         let private_key2 = PrivateKey::new();
-        let service2 = Service::new(&private_key2, None);
+        let service2 = Service::new(&private_key2, None, None);
         service2.startup().await.unwrap();
 
-        let document2 = service2.join_document_with_main_context(&id, &context);
+        let document2 = service2.join_document_with_main_context(&secret, &context);
         document2.subscribe().await;
 
         assert_eq!(document.id(), document2.id());
@@ -151,18 +165,21 @@ mod tests {
         let context = glib::MainContext::ref_thread_default();
 
         let private_key = PrivateKey::new();
-        let service = Service::new(&private_key, None);
+        let service = Service::new(&private_key, None, None);
         service.startup().await.unwrap();
 
-        let document = service.join_document_with_main_context(&DocumentId::new(), &context);
+        let document = service.join_document_with_main_context(
+            &DocumentSecret::new(&private_key.public_key()),
+            &context,
+        );
         document.subscribe().await;
-        let id = document.id();
+        let secret = document.secret();
 
         let private_key2 = PrivateKey::new();
-        let service2 = Service::new(&private_key2, None);
+        let service2 = Service::new(&private_key2, None, None);
         service2.startup().await.unwrap();
 
-        let document2 = service2.join_document_with_main_context(&id, &context);
+        let document2 = service2.join_document_with_main_context(&secret, &context);
         document2.subscribe().await;
 
         assert_eq!(document.id(), document2.id());
@@ -202,19 +219,22 @@ mod tests {
         let context = glib::MainContext::ref_thread_default();
 
         let private_key = PrivateKey::new();
-        let service = Service::new(&private_key, None);
+        let service = Service::new(&private_key, None, None);
         service.startup().await.unwrap();
 
-        let document = service.join_document_with_main_context(&DocumentId::new(), &context);
-        let id = document.id();
+        let document = service.join_document_with_main_context(
+            &DocumentSecret::new(&private_key.public_key()),
+            &context,
+        );
+        let secret = document.secret();
 
         document.subscribe().await;
 
         let private_key2 = PrivateKey::new();
-        let service2 = Service::new(&private_key2, None);
+        let service2 = Service::new(&private_key2, None, None);
         service2.startup().await.unwrap();
 
-        let document2 = service2.join_document_with_main_context(&id, &context);
+        let document2 = service2.join_document_with_main_context(&secret, &context);
         document2.subscribe().await;
 
         assert_eq!(document.id(), document2.id());
@@ -238,4 +258,549 @@ mod tests {
 
         assert_eq!(document2.text(), expected_string);
     }
+
+    /// How long tests wait for changes to get synced between services.
+    const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Starts a service for `private_key` and subscribes to the document with `secret`.
+    async fn subscribed_document(
+        private_key: &PrivateKey,
+        secret: &DocumentSecret,
+    ) -> (Service, Document) {
+        let context = glib::MainContext::ref_thread_default();
+
+        let service = Service::new(private_key, None, None);
+        service.startup().await.unwrap();
+
+        let document = service.join_document_with_main_context(secret, &context);
+        document.subscribe().await;
+
+        (service, document)
+    }
+
+    /// Polls `condition` until it holds, panics if it doesn't within `timeout`.
+    async fn wait_until(condition: impl Fn() -> bool, timeout: Duration) {
+        let started = Instant::now();
+        while !condition() {
+            assert!(
+                started.elapsed() < timeout,
+                "Condition didn't hold within {timeout:?}"
+            );
+            glib::timeout_future(Duration::from_millis(50)).await;
+        }
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn history() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+
+        assert!(document.insert_text(0, "Hello").is_ok());
+        wait_until(|| document2.text() == "Hello", SYNC_TIMEOUT).await;
+
+        assert!(document2.delete_range(0, 5).is_ok());
+        assert!(document2.insert_text(0, "Bye").is_ok());
+
+        let checkpoints = document2.checkpoints();
+        assert_eq!(checkpoints.len(), 2);
+        let author = checkpoints[0].author.clone().unwrap();
+        assert_eq!(author.public_key(), private_key.public_key());
+        assert_eq!(document2.text_at(&checkpoints[0]).unwrap(), "Hello");
+        assert_eq!(document2.text_at(&checkpoints[1]).unwrap(), "Bye");
+
+        // Old versions can be read but not edited
+        let version = document2.checkout(&checkpoints[0]).unwrap();
+        assert!(version.read_only());
+        assert_eq!(version.text(), "Hello");
+        assert!(version.insert_text(0, "Hi").is_err());
+
+        assert!(document2.restore(&checkpoints[0]).is_ok());
+        assert_eq!(document2.text(), "Hello");
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn diff() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+
+        assert!(document.insert_text(0, "Hello World").is_ok());
+        wait_until(|| document2.text() == "Hello World", SYNC_TIMEOUT).await;
+
+        assert!(document2.delete_range(6, 11).is_ok());
+        assert!(document2.insert_text(6, "Moon").is_ok());
+
+        let checkpoints = document2.checkpoints();
+        assert_eq!(checkpoints.len(), 2);
+
+        let spans = document2
+            .diff(&checkpoints[0], checkpoints.last().unwrap())
+            .await
+            .unwrap();
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|span| {
+                let author = span.author.as_ref().map(|author| author.public_key());
+                (span.kind, span.text.as_str(), author)
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (DiffKind::Unchanged, "Hello ", None),
+                (DiffKind::Deleted, "World", Some(private_key2.public_key())),
+                (DiffKind::Inserted, "Moon", Some(private_key2.public_key())),
+            ]
+        );
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn authorship() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+
+        assert!(document.insert_text(0, "Hello").is_ok());
+        wait_until(|| document2.text() == "Hello", SYNC_TIMEOUT).await;
+
+        assert!(document2.insert_text(5, " World").is_ok());
+
+        let authorship: Vec<_> = document2
+            .authorship(0..11)
+            .iter()
+            .map(|range| (range.start, range.end, range.author.public_key()))
+            .collect();
+        assert_eq!(
+            authorship,
+            [
+                (0, 5, private_key.public_key()),
+                (5, 11, private_key2.public_key()),
+            ]
+        );
+
+        let authorship: Vec<_> = document2
+            .authorship(3..7)
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect();
+        assert_eq!(authorship, [(3, 5), (5, 7)]);
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test]
+    fn peer_claims() {
+        let private_key = PrivateKey::new();
+        let private_key2 = PrivateKey::new();
+        let document_id = DocumentSecret::new(&private_key.public_key()).document_id();
+        let derived_peer = peer_id(&private_key.public_key());
+
+        let doc = loro::LoroDoc::new();
+        doc.set_peer_id(derived_peer).unwrap();
+        doc.get_text("document").insert(0, "Hello").unwrap();
+        doc.commit();
+
+        // Only the key a peer id is derived from can claim it, even after it was used
+        claim_peer(&doc, &private_key2, document_id, derived_peer).unwrap();
+        doc.commit();
+        assert_eq!(peer_claim(&doc, document_id, derived_peer), None);
+        claim_peer(&doc, &private_key, document_id, derived_peer).unwrap();
+        doc.commit();
+        assert_eq!(
+            peer_claim(&doc, document_id, derived_peer),
+            Some(private_key.public_key())
+        );
+
+        // Another key with the same first 8 bytes derives the same peer id, the claim decides
+        // who the changes made with it belong to
+        let mut bytes = private_key.public_key().0.as_bytes().to_vec();
+        let original = bytes[31];
+        let colliding = (0..=u8::MAX)
+            .filter(|byte| *byte != original)
+            .find_map(|byte| {
+                bytes[31] = byte;
+                p2panda_core::PublicKey::try_from(&bytes[..]).ok()
+            })
+            .map(PublicKey)
+            .unwrap();
+        assert_eq!(peer_id(&colliding), derived_peer);
+        assert_ne!(peer_claim(&doc, document_id, derived_peer), Some(colliding));
+
+        // Random peer ids are claimed with their first operation
+        let random_peer = rand::random();
+        doc.set_peer_id(random_peer).unwrap();
+        claim_peer(&doc, &private_key2, document_id, random_peer).unwrap();
+        doc.commit();
+        assert_eq!(
+            peer_claim(&doc, document_id, random_peer),
+            Some(private_key2.public_key())
+        );
+
+        // Claims replacing the first one or made after the peer id was used aren't valid
+        claim_peer(&doc, &private_key, document_id, random_peer).unwrap();
+        doc.commit();
+        assert_eq!(peer_claim(&doc, document_id, random_peer), None);
+
+        let used_peer = rand::random();
+        doc.set_peer_id(used_peer).unwrap();
+        doc.get_text("document").insert(0, "Hi ").unwrap();
+        doc.commit();
+        claim_peer(&doc, &private_key2, document_id, used_peer).unwrap();
+        doc.commit();
+        assert_eq!(peer_claim(&doc, document_id, used_peer), None);
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn marks() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+
+        let link = Mark::Link("https://example.org".into());
+        assert!(document.insert_text(0, "Hello World").is_ok());
+        assert!(document.mark(0, 5, &Mark::Bold).is_ok());
+        assert!(document.mark(6, 11, &link).is_ok());
+        wait_until(|| document2.marks().len() == 2, SYNC_TIMEOUT).await;
+
+        assert_eq!(
+            document2.marks(),
+            [
+                MarkedRange {
+                    start: 0,
+                    end: 5,
+                    mark: Mark::Bold,
+                },
+                MarkedRange {
+                    start: 6,
+                    end: 11,
+                    mark: link.clone(),
+                },
+            ]
+        );
+
+        // Bold text typed at the end of bold text stays bold, links don't grow
+        assert!(document2.insert_text(5, "!").is_ok());
+        assert!(document2.insert_text(12, "!").is_ok());
+        assert!(document2.unmark(0, 2, &Mark::Bold).is_ok());
+        assert_eq!(
+            document2.marks(),
+            [
+                MarkedRange {
+                    start: 2,
+                    end: 6,
+                    mark: Mark::Bold,
+                },
+                MarkedRange {
+                    start: 7,
+                    end: 12,
+                    mark: link.clone(),
+                },
+            ]
+        );
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn action_items() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+
+        assert!(document.add_action_item("Send budget").is_ok());
+        assert!(document.add_action_item("Book room").is_ok());
+        let items = document.action_items();
+        assert!(
+            document
+                .set_action_item_assignee(&items[0], Some(&private_key2.public_key()))
+                .is_ok()
+        );
+        wait_until(
+            || {
+                let items = document2.action_items();
+                items.len() == 2 && items[0].assignee.is_some()
+            },
+            SYNC_TIMEOUT,
+        )
+        .await;
+
+        let items = document2.action_items();
+        assert_eq!(items[0].text, "Send budget");
+        assert_eq!(items[0].assignee, Some(private_key2.public_key()));
+        assert!(!items[0].done);
+
+        assert!(document2.set_action_item_done(&items[0], true).is_ok());
+        assert!(document2.move_action_item(&items[0], 1).is_ok());
+        wait_until(|| document.action_items()[1].done, SYNC_TIMEOUT).await;
+
+        let items = document.action_items();
+        assert_eq!(items[0].text, "Book room");
+        assert_eq!(items[1].text, "Send budget");
+
+        assert!(document.remove_action_item(&items[0]).is_ok());
+        assert_eq!(document.action_items().len(), 1);
+
+        // Changes of the action items aren't undone with the text
+        document.undo();
+        assert_eq!(document.action_items().len(), 1);
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn comments() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+
+        assert!(document.insert_text(0, "The budget is fine").is_ok());
+        assert!(document.add_comment(4, 4, "Empty").is_err());
+        assert!(document.add_comment(4, 10, "Which budget?").is_ok());
+
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+        wait_until(|| !document2.comments().is_empty(), SYNC_TIMEOUT).await;
+
+        let comments = document2.comments();
+        assert_eq!(comments[0].text, "Which budget?");
+        assert_eq!((comments[0].start, comments[0].end), (4, 10));
+        assert!(
+            comments[0]
+                .author
+                .as_ref()
+                .is_some_and(|author| author.public_key() == private_key.public_key())
+        );
+        assert!(document2.reply_to_comment(&comments[0], "Q3").is_ok());
+
+        // The comment moves along with the text it's about
+        assert!(document.insert_text(0, "Yes. ").is_ok());
+        wait_until(|| !document.comments()[0].replies.is_empty(), SYNC_TIMEOUT).await;
+
+        let comments = document.comments();
+        assert_eq!((comments[0].start, comments[0].end), (9, 15));
+        assert_eq!(comments[0].replies[0].text, "Q3");
+        assert!(!comments[0].resolved);
+
+        assert!(document.set_comment_resolved(&comments[0], true).is_ok());
+
+        // Changes of the comments aren't undone with the text
+        document.undo();
+        assert!(document.comments()[0].resolved);
+        assert!(document.comments()[0].resolved);
+        assert!(document.remove_comment(&comments[0]).is_ok());
+        assert!(document.comments().is_empty());
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn tasks() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+
+        let private_key2 = PrivateKey::new();
+        let name2 = Author::new(&private_key2.public_key()).name();
+        let text = format!(
+            "Minutes\n\
+             - [ ] @{name2} send budget\n\
+             - [x] @{name2} book room\n  \
+             * [ ] ask @{name2}\n\
+             - [ ] @{name2}glove water plants\n\
+             - [ ] tidy up\n"
+        );
+        assert!(document.insert_text(0, &text).is_ok());
+        assert!(document.add_action_item("Write report").is_ok());
+        let items = document.action_items();
+        assert!(
+            document
+                .set_action_item_assignee(&items[0], Some(&private_key2.public_key()))
+                .is_ok()
+        );
+
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+        wait_until(
+            || document2.text() == text && !document2.action_items().is_empty(),
+            SYNC_TIMEOUT,
+        )
+        .await;
+
+        let tasks = service2
+            .documents()
+            .tasks_for(&private_key2.public_key())
+            .await;
+        let tasks: Vec<&str> = tasks.iter().map(|task| task.text.as_str()).collect();
+        assert_eq!(
+            tasks,
+            [
+                format!("@{name2} send budget"),
+                format!("ask @{name2}"),
+                "Write report".to_owned()
+            ]
+        );
+        assert!(
+            service
+                .documents()
+                .tasks_for(&private_key.public_key())
+                .await
+                .is_empty()
+        );
+
+        // Documents which aren't open are read from their snapshots
+        document2.unsubscribe().await;
+        let stored_tasks = service2
+            .documents()
+            .tasks_for(&private_key2.public_key())
+            .await;
+        let stored_tasks: Vec<&str> = stored_tasks.iter().map(|task| task.text.as_str()).collect();
+        assert_eq!(stored_tasks, tasks);
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn metadata() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+
+        assert!(service.create_document().created().is_some());
+
+        assert!(document.insert_text(0, "Retro\nWhat went well").is_ok());
+        assert_eq!(document.name().as_deref(), Some("Retro"));
+
+        assert!(document.set_title(Some("Q3 Retro: Ops & Infra")).is_ok());
+        assert!(document.set_icon(Some("🚀")).is_ok());
+        assert!(document.set_description(Some(" ")).is_ok());
+        assert_eq!(document.name().as_deref(), Some("Q3 Retro: Ops & Infra"));
+        assert_eq!(document.description(), None);
+
+        // Changes of the metadata aren't undone with the text
+        document.undo();
+        assert_eq!(document.title().as_deref(), Some("Q3 Retro: Ops & Infra"));
+
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+        wait_until(|| document2.icon().is_some(), SYNC_TIMEOUT).await;
+
+        assert_eq!(document2.name().as_deref(), Some("Q3 Retro: Ops & Infra"));
+        assert_eq!(document2.icon().as_deref(), Some("🚀"));
+
+        assert!(document2.set_title(None).is_ok());
+        assert_eq!(document2.title(), None);
+        assert_eq!(document2.name().as_deref(), Some("Retro"));
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn export() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+
+        assert!(
+            document
+                .insert_text(0, "Minutes\nThe budget is <fine>\n")
+                .is_ok()
+        );
+        assert!(document.set_title(Some("Q3 Retro: Ops & Infra")).is_ok());
+        assert!(document.mark(0, 7, &Mark::Heading(1)).is_ok());
+        assert!(document.mark(12, 18, &Mark::Bold).is_ok());
+        // Links which could run code are exported as plain text
+        assert!(
+            document
+                .mark(0, 7, &Mark::Link("javascript:alert(1)".into()))
+                .is_ok()
+        );
+        let name = Author::new(&private_key.public_key()).name();
+
+        let markdown = document.export_as(ExportFormat::Markdown);
+        assert!(markdown.starts_with("---\ntitle: \"Q3 Retro: Ops & Infra\"\n"));
+        assert!(markdown.contains(&format!("authors:\n  - \"{name}\"\n")));
+        assert!(markdown.ends_with("---\n\n# Minutes\nThe **budget** is <fine>\n"));
+
+        let html = document.export_as(ExportFormat::Html);
+        assert!(html.contains("<title>Q3 Retro: Ops &amp; Infra</title>"));
+        assert!(
+            html.contains("<h1>Minutes</h1>\n<p>The <strong>budget</strong> is &lt;fine&gt;</p>")
+        );
+
+        let plain_text = document.export_as(ExportFormat::PlainText);
+        assert!(plain_text.starts_with("Q3 Retro: Ops & Infra\n"));
+        assert!(plain_text.ends_with("\n\nMinutes\nThe budget is <fine>\n"));
+
+        assert_eq!(ExportFormat::from_suffix("HTML"), Some(ExportFormat::Html));
+        assert_eq!(ExportFormat::from_suffix("doc"), None);
+
+        service.shutdown().await;
+    }
+
+    #[test_log::test(glib::async_test)]
+    async fn tags() {
+        let private_key = PrivateKey::new();
+        let secret = DocumentSecret::new(&private_key.public_key());
+        let (service, document) = subscribed_document(&private_key, &secret).await;
+
+        assert!(document.insert_text(0, "Hello").is_ok());
+        assert!(document.create_tag("Agreed minutes").is_ok());
+        assert!(document.create_tag(" ").is_err());
+        assert!(document.insert_text(5, " World").is_ok());
+
+        assert_eq!(
+            document.text_at_tag("Agreed minutes").as_deref(),
+            Some("Hello")
+        );
+        assert_eq!(document.text_at_tag("Draft"), None);
+
+        // Tags aren't undone with the text
+        document.undo();
+        assert!(document.text_at_tag("Agreed minutes").is_some());
+
+        let private_key2 = PrivateKey::new();
+        let (service2, document2) = subscribed_document(&private_key2, &secret).await;
+        wait_until(|| !document2.tags().is_empty(), SYNC_TIMEOUT).await;
+
+        let tags = document2.tags();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "Agreed minutes");
+        let version = document2.checkout(&tags[0].checkpoint).unwrap();
+        assert_eq!(version.text(), "Hello");
+
+        service.shutdown().await;
+        service2.shutdown().await;
+    }
+
+    #[test_log::test]
+    fn invite_codes() {
+        let secret = DocumentSecret::new(&PrivateKey::new().public_key());
+        assert!(Document::parse_invite_code(&secret.to_hex()).unwrap() == secret);
+
+        // Ids of legacy documents can't be used to join them anymore
+        let legacy_id = secret.document_id().to_hex();
+        assert!(Document::parse_invite_code(&legacy_id).is_err());
+    }
 }
@@ -1,25 +1,33 @@
-use gio::prelude::{FileExt, ListModelExtManual, NetworkMonitorExt};
+use futures_util::Stream;
+use gio::prelude::{
+    FileExt, ListModelExtManual, NetworkMonitorExt, SettingsExt, SettingsExtManual,
+};
 use glib::object::ObjectExt;
 use glib::subclass::prelude::*;
 use glib::{Properties, clone};
 use reflection_node::p2panda_core::Hash;
 use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 use thiserror::Error;
 use tracing::error;
 
 use crate::identity::PrivateKey;
 use crate::{
-    document::{Document, DocumentId},
+    document::{Document, DocumentId, DocumentSecret, FromHexError},
     documents::Documents,
 };
 use reflection_node::{
     node,
-    node::{Node, NodeError},
+    node::{ConfigError, Node, NodeConfig, NodeError},
     topic::TopicError,
 };
 
+pub use reflection_node::node::{BundleError, PeerConnectionType, PeerInfo};
+
 #[derive(Error, Debug)]
 pub enum StartupError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
     #[error(transparent)]
     Node(#[from] NodeError),
     #[error(transparent)]
@@ -34,6 +42,9 @@ pub enum ConnectionMode {
     Bluetooth,
     #[default]
     Network,
+    LocalNetwork,
+    /// Sync via the sync directory, e.g. on a USB drive.
+    Directory,
 }
 
 impl From<ConnectionMode> for node::ConnectionMode {
@@ -42,6 +53,8 @@ impl From<ConnectionMode> for node::ConnectionMode {
             ConnectionMode::None => node::ConnectionMode::None,
             ConnectionMode::Bluetooth => node::ConnectionMode::Bluetooth,
             ConnectionMode::Network => node::ConnectionMode::Network,
+            ConnectionMode::LocalNetwork => node::ConnectionMode::LocalNetwork,
+            ConnectionMode::Directory => node::ConnectionMode::Directory,
         }
     }
 }
@@ -57,10 +70,21 @@ mod imp {
         pub private_key: OnceLock<PrivateKey>,
         #[property(get, set, construct_only, nullable, type = Option<gio::File>)]
         pub data_dir: OnceLock<Option<gio::File>>,
+        /// Settings the network configuration of the node is read from.
+        #[property(get, set, construct_only, nullable, type = Option<gio::Settings>)]
+        pub settings: OnceLock<Option<gio::Settings>>,
         #[property(get)]
         documents: Documents,
         #[property(get = Self::connection_mode, set = Self::set_connection_mode, builder(ConnectionMode::default()))]
         pub connection_mode: Mutex<ConnectionMode>,
+        /// Directory used in [`ConnectionMode::Directory`].
+        #[property(get = Self::sync_directory, set = Self::set_sync_directory, nullable, type = Option<gio::File>)]
+        pub sync_directory: Mutex<Option<gio::File>>,
+        /// Whether all documents which aren't subscribed are kept in sync in the background.
+        #[property(get, set = Self::set_background_sync)]
+        pub background_sync: Mutex<bool>,
+        /// Closed documents aren't synced in the background anymore while shutting down.
+        pub shutting_down: Mutex<bool>,
     }
 
     impl Service {
@@ -79,6 +103,65 @@ mod imp {
             *self.connection_mode.lock().unwrap()
         }
 
+        fn set_sync_directory(&self, directory: Option<gio::File>) {
+            if let Some(settings) = self.settings.get().and_then(Option::as_ref) {
+                let uri = directory.as_ref().map(|directory| directory.uri());
+                if let Err(error) =
+                    settings.set_string("sync-directory", uri.as_deref().unwrap_or(""))
+                {
+                    error!("Failed to store sync directory: {error}");
+                }
+            }
+
+            let path = directory.as_ref().and_then(|directory| directory.path());
+            *self.sync_directory.lock().unwrap() = directory;
+
+            if let Some(node) = self.node.get() {
+                node.set_sync_directory(path);
+
+                if self.connection_mode() == ConnectionMode::Directory {
+                    glib::spawn_future(clone!(
+                        #[weak(rename_to = this)]
+                        self,
+                        async move {
+                            this.update_node_connection_mode().await;
+                        }
+                    ));
+                }
+            }
+        }
+
+        fn sync_directory(&self) -> Option<gio::File> {
+            self.sync_directory.lock().unwrap().clone()
+        }
+
+        fn set_background_sync(&self, background_sync: bool) {
+            *self.background_sync.lock().unwrap() = background_sync;
+            self.update_background_sync();
+        }
+
+        /// Start or stop syncing each document in the background, after the settings changed.
+        pub(super) fn update_background_sync(&self) {
+            if self.node.get().is_none() {
+                return;
+            }
+
+            glib::spawn_future(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    for document in this.documents.iter::<Document>() {
+                        let Ok(document) = document else {
+                            break;
+                        };
+
+                        document.notify_keep_in_sync();
+                        document.update_background_sync().await;
+                    }
+                }
+            ));
+        }
+
         pub(super) async fn update_node_connection_mode(&self) {
             let Some(node) = self.node.get() else {
                 return;
@@ -88,6 +171,8 @@ mod imp {
                 monitor.is_network_available()
             };
             let connection_mode = (*self.connection_mode.lock().unwrap()).into();
+            // The local network mode is meant for networks without internet access, which
+            // the network monitor reports as unavailable, so don't turn it off
             let wants_network = connection_mode == node::ConnectionMode::Network;
             let real_connection_mode = if !network_available && wants_network {
                 node::ConnectionMode::None
@@ -135,35 +220,80 @@ glib::wrapper! {
 }
 
 impl Service {
-    pub fn new(private_key: &PrivateKey, data_dir: Option<&gio::File>) -> Self {
+    /// Create a new service, without `settings` the node only connects to peers in the local
+    /// network.
+    pub fn new(
+        private_key: &PrivateKey,
+        data_dir: Option<&gio::File>,
+        settings: Option<&gio::Settings>,
+    ) -> Self {
         glib::Object::builder()
             .property("private-key", private_key)
             .property("data-dir", data_dir)
+            .property("settings", settings)
             .build()
     }
 
-    pub fn join_document(&self, document_id: &DocumentId) -> Document {
+    /// Create a new document owned by this device.
+    pub fn create_document(&self) -> Document {
+        let document = Document::create(self, None);
+        self.documents().add(document.clone());
+
+        document
+    }
+
+    pub fn join_document(&self, secret: &DocumentSecret) -> Document {
         let list = self.documents();
-        if let Some(document) = list.document(document_id) {
+        if let Some(document) = list.document(&secret.document_id()) {
             document
         } else {
-            let document = Document::new(self, document_id, None);
+            let document = Document::new(self, secret, None);
             list.add(document.clone());
 
             document
         }
     }
 
+    /// Join a document someone else shared with us via [`Document::invite_code`].
+    pub fn join_document_with_invite_code(
+        &self,
+        invite_code: &str,
+    ) -> Result<Document, FromHexError> {
+        let secret = Document::parse_invite_code(invite_code)?;
+
+        Ok(self.join_document(&secret))
+    }
+
+    /// Import a document exported with [`Document::export`], `secret` is the one of its invite
+    /// code.
+    ///
+    /// Documents which are open already are reloaded to pick up the imported changes.
+    pub async fn import_document(
+        &self,
+        bundle: Vec<u8>,
+        secret: &DocumentSecret,
+    ) -> Result<Document, BundleError> {
+        self.node().import_topic(bundle, (*secret).into()).await?;
+        let document = self.join_document(secret);
+
+        if document.subscribed() {
+            document.unsubscribe().await;
+            document.subscribe().await;
+        }
+
+        Ok(document)
+    }
+
     pub fn join_document_with_main_context(
         &self,
-        document_id: &DocumentId,
+        secret: &DocumentSecret,
         main_context: &glib::MainContext,
     ) -> Document {
         let list = self.documents();
-        if let Some(document) = list.document(document_id) {
+        if let Some(document) = list.document(&secret.document_id()) {
             document
         } else {
-            let document = Document::new(self, document_id, Some(main_context));
+            let document = Document::new(self, secret, Some(main_context));
             list.add(document.clone());
 
             document
@@ -174,7 +304,8 @@ impl Service {
         let private_key = self.private_key().0;
         let network_id = Hash::new(b"reflection");
         let path = self.data_dir().and_then(|data_dir| data_dir.path());
-        let node = Node::new(private_key, network_id, path.as_deref()).await?;
+        let config = self.node_config()?;
+        let node = Node::new(private_key, network_id, path.as_deref(), config).await?;
 
         self.imp()
             .node
@@ -184,12 +315,111 @@ impl Service {
         self.imp().update_node_connection_mode().await;
         self.documents().load(self).await?;
 
+        // Binding the setting applies it to the documents right away
+        if let Some(settings) = self.settings() {
+            settings
+                .bind("background-sync", self, "background-sync")
+                .build();
+            settings.connect_changed(
+                Some("keep-in-sync"),
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |_, _| {
+                        this.imp().update_background_sync();
+                    }
+                ),
+            );
+        }
+
         Ok(())
     }
 
+    fn node_config(&self) -> Result<NodeConfig, ConfigError> {
+        let Some(settings) = self.settings() else {
+            return Ok(NodeConfig::default());
+        };
+
+        let mut builder = NodeConfig::builder()
+            .mdns(settings.boolean("mdns"))
+            .offline_timeout(Duration::from_secs(settings.uint("offline-timeout").into()));
+
+        let sync_directory = settings.string("sync-directory");
+        if !sync_directory.is_empty() {
+            let sync_directory = gio::File::for_uri(&sync_directory);
+            if let Some(path) = sync_directory.path() {
+                builder = builder.sync_directory(path);
+            }
+            *self.imp().sync_directory.lock().unwrap() = Some(sync_directory);
+        }
+
+        for relay_url in settings.strv("relay-urls") {
+            builder = builder.relay_url(relay_url.as_str());
+        }
+
+        for (node_id, relay_url) in settings.get::<Vec<(String, String)>>("bootstrap-nodes") {
+            builder = builder.bootstrap_node(node_id, (!relay_url.is_empty()).then_some(relay_url));
+        }
+
+        builder.build()
+    }
+
+    /// Whether the document with `id` is kept in sync in the background, even when
+    /// [`Service::background_sync()`] is disabled.
+    pub(crate) fn keeps_in_sync(&self, id: &DocumentId) -> bool {
+        let id = id.to_hex();
+        self.settings().is_some_and(|settings| {
+            settings
+                .strv("keep-in-sync")
+                .iter()
+                .any(|kept| kept.as_str() == id)
+        })
+    }
+
+    pub(crate) fn set_keeps_in_sync(&self, id: &DocumentId, keep_in_sync: bool) {
+        let Some(settings) = self.settings() else {
+            return;
+        };
+
+        let id = id.to_hex();
+        let mut kept: Vec<String> = settings
+            .strv("keep-in-sync")
+            .iter()
+            .map(|kept| kept.to_string())
+            .filter(|kept| *kept != id)
+            .collect();
+        if keep_in_sync {
+            kept.push(id);
+        }
+
+        if let Err(error) = settings.set_strv("keep-in-sync", kept) {
+            error!("Failed to store documents kept in sync: {error}");
+        }
+    }
+
+    /// Whether `document` is synced in the background while it isn't subscribed.
+    pub(crate) fn syncs_in_background(&self, document: &Document) -> bool {
+        !*self.imp().shutting_down.lock().unwrap()
+            && (self.background_sync() || document.keep_in_sync())
+    }
+
+    /// Stream of the peers known via the current connection mode.
+    ///
+    /// The current peers are sent right away, afterwards whenever they changed. Some changes,
+    /// like new round-trip times, are only noticed by polling, so they show up with a delay.
+    /// Dropping the stream stops watching the peers.
+    pub fn peers_changed(&self) -> impl Stream<Item = Vec<PeerInfo>> + Unpin + use<> {
+        self.node().peers_changed()
+    }
+
     pub async fn shutdown(&self) {
+        // Closing documents would otherwise start syncing them in the background
+        *self.imp().shutting_down.lock().unwrap() = true;
+
         for document in self.documents().iter::<Document>() {
-            document.unwrap().unsubscribe().await;
+            let document = document.unwrap();
+            document.unsubscribe().await;
+            document.stop_background_sync().await;
         }
 
         if let Err(error) = self.node().shutdown().await {
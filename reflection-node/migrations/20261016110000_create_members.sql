@@ -0,0 +1,10 @@
+ALTER TABLE topics ADD COLUMN owner TEXT;
+ALTER TABLE topics ADD COLUMN default_role TEXT;
+
+CREATE TABLE IF NOT EXISTS members (
+    public_key          TEXT NOT NULL,
+    topic_id          	TEXT NOT NULL,
+    role		TEXT,
+    UNIQUE(public_key, topic_id),
+    FOREIGN KEY(topic_id) REFERENCES topics(id) ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+ALTER TABLE members ADD COLUMN removed_at INTEGER;
+
+CREATE TABLE IF NOT EXISTS topic_keys (
+    topic_id          	TEXT NOT NULL,
+    key			BLOB NOT NULL,
+    created_at		INTEGER NOT NULL,
+    UNIQUE(topic_id, key),
+    FOREIGN KEY(topic_id) REFERENCES topics(id) ON DELETE CASCADE
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS seen_logs (
+    public_key          TEXT NOT NULL,
+    topic_id          	TEXT NOT NULL,
+    snapshot_seq_num	INTEGER,
+    delta_seq_num	INTEGER,
+    UNIQUE(public_key, topic_id),
+    FOREIGN KEY(topic_id) REFERENCES topics(id) ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+ALTER TABLE topics ADD COLUMN bookmark BLOB;
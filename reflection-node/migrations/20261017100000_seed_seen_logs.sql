@@ -0,0 +1,12 @@
+-- Only deltas count as changes, snapshots merely repeat them
+ALTER TABLE seen_logs DROP COLUMN snapshot_seq_num;
+
+-- Topics which were never seen since changes are tracked, their current logs are marked as
+-- seen once the node starts
+CREATE TABLE IF NOT EXISTS unseeded_topics (
+    topic_id          	TEXT NOT NULL PRIMARY KEY,
+    FOREIGN KEY(topic_id) REFERENCES topics(id) ON DELETE CASCADE
+);
+
+INSERT INTO unseeded_topics ( topic_id )
+SELECT id FROM topics WHERE id NOT IN ( SELECT topic_id FROM seen_logs );
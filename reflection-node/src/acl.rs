@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use p2panda_core::PublicKey;
+use p2panda_core::cbor::{DecodeError, EncodeError, decode_cbor, encode_cbor};
+use serde::{Deserialize, Serialize};
+
+use crate::operation::LogType;
+
+/// Role of a member in a topic.
+///
+/// The owner of a topic implicitly has the [`Role::Write`] role and is the only one who can
+/// change the roles of other members. Authors who don't have a role can't write either.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Role {
+    /// The member can read the topic, but operations they author are dropped.
+    Read,
+    /// The member can read and write the topic.
+    Write,
+}
+
+impl Role {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Role::Read => "read",
+            Role::Write => "write",
+        }
+    }
+
+    pub(crate) fn parse(role: &str) -> Option<Self> {
+        match role {
+            "read" => Some(Role::Read),
+            "write" => Some(Role::Write),
+            _ => None,
+        }
+    }
+}
+
+/// Access-control list of a topic.
+#[derive(Clone, Debug)]
+pub struct Acl {
+    pub owner: PublicKey,
+    /// Role of authors who aren't explicitly listed as members.
+    pub default_role: Option<Role>,
+    /// Explicit roles of members, `None` if the member was removed from the topic.
+    pub members: HashMap<PublicKey, Option<Role>>,
+    /// When members were removed from the topic.
+    pub removed_at: HashMap<PublicKey, DateTime<Utc>>,
+}
+
+impl Acl {
+    /// The role of `author` in the topic, `None` if the author has no access.
+    pub fn role(&self, author: &PublicKey) -> Option<Role> {
+        if author == &self.owner {
+            return Some(Role::Write);
+        }
+
+        self.members
+            .get(author)
+            .copied()
+            .unwrap_or(self.default_role)
+    }
+
+    /// Whether operations of the given log type authored by `author` are accepted.
+    ///
+    /// Only the owner can write to the access-control list itself.
+    pub fn can_write(&self, author: &PublicKey, log_type: LogType) -> bool {
+        match log_type {
+            LogType::Acl => author == &self.owner,
+            LogType::Delta | LogType::Snapshot => self.role(author) == Some(Role::Write),
+        }
+    }
+
+    /// Whether an operation of the given log type authored by `author` at `timestamp` is
+    /// accepted.
+    ///
+    /// Members who were removed from the topic keep the content they wrote before they were
+    /// removed, only what they wrote afterwards is rejected. Timestamps are in seconds, so
+    /// operations from the second they were removed in are still accepted.
+    pub fn can_write_at(&self, author: &PublicKey, log_type: LogType, timestamp: u64) -> bool {
+        if self.can_write(author, log_type) {
+            return true;
+        }
+
+        log_type != LogType::Acl
+            && self.role(author).is_none()
+            && self
+                .removed_at
+                .get(author)
+                .is_some_and(|removed_at| timestamp as i64 <= removed_at.timestamp())
+    }
+
+    /// Whether any operations of the given log type authored by `author` may be accepted,
+    /// either because they can write or because they could until they were removed.
+    pub fn may_have_written(&self, author: &PublicKey, log_type: LogType) -> bool {
+        self.can_write(author, log_type)
+            || (log_type != LogType::Acl
+                && self.role(author).is_none()
+                && self.removed_at.contains_key(author))
+    }
+}
+
+/// Messages stored in the `LogType::Acl` log of a topic.
+///
+/// The owner of a topic is part of its secret, see [`TopicSecret`](crate::topic::TopicSecret).
+/// Messages are only accepted when they are authored by the owner.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AclMessage {
+    /// Changes the role of authors who aren't explicitly listed as members.
+    SetDefaultRole { role: Option<Role> },
+    /// Changes the role of a member, `None` removes the member from the topic.
+    SetRole {
+        member: PublicKey,
+        role: Option<Role>,
+    },
+    /// Shares a new topic key with the remaining members, each entry contains the key encrypted
+    /// for that member.
+    ShareKey { keys: Vec<(PublicKey, Vec<u8>)> },
+}
+
+impl AclMessage {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_cbor(self)
+    }
+}
+
+impl TryFrom<&[u8]> for AclMessage {
+    type Error = DecodeError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        decode_cbor(value)
+    }
+}
+
+impl std::fmt::Display for AclMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AclMessage::SetDefaultRole { .. } => write!(f, "Set default role message"),
+            AclMessage::SetRole { member, .. } => write!(f, "Set role for {member} message"),
+            AclMessage::ShareKey { .. } => write!(f, "Share key message"),
+        }
+    }
+}
@@ -1,21 +1,20 @@
 use std::collections::HashMap;
 use std::ops::DerefMut;
 use std::sync::Arc;
-use std::time::{Duration, Instant, SystemTime};
+use std::time::{Instant, SystemTime};
 
 use crate::ephemerial_operation::EphemerialOperation;
 use crate::node_inner::MessageType;
 use crate::node_inner::NodeInner;
 use crate::topic::SubscribableTopic;
+use crate::transport::EphemeralHandle;
 use chrono::Utc;
 use p2panda_core::cbor::{DecodeError, decode_cbor, encode_cbor};
 use p2panda_core::{PrivateKey, PublicKey};
-use p2panda_net::gossip::GossipHandle;
+use p2panda_net::TopicId;
 use tokio::sync::{Mutex, RwLock};
 use tracing::error;
 
-const OFFLINE_TIMEOUT: Duration = Duration::from_secs(60);
-
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum AuthorMessage {
     Hello,
@@ -47,20 +46,22 @@ pub struct AuthorTracker<T> {
     last_ping: Mutex<HashMap<PublicKey, Instant>>,
     subscribable_topic: Arc<T>,
     node: Arc<NodeInner>,
-    tx: RwLock<Option<GossipHandle>>,
+    id: TopicId,
+    tx: RwLock<Option<EphemeralHandle>>,
 }
 
 impl<T: SubscribableTopic> AuthorTracker<T> {
-    pub fn new(node: Arc<NodeInner>, subscribable_topic: Arc<T>) -> Arc<Self> {
+    pub fn new(node: Arc<NodeInner>, id: TopicId, subscribable_topic: Arc<T>) -> Arc<Self> {
         Arc::new(Self {
             last_ping: Mutex::new(HashMap::new()),
             subscribable_topic,
             node,
+            id,
             tx: RwLock::new(None),
         })
     }
 
-    pub async fn set_topic_tx(&self, tx: Option<GossipHandle>) {
+    pub async fn set_topic_tx(&self, tx: Option<EphemeralHandle>) {
         let mut tx_guard = self.tx.write().await;
         // Send good bye message to the network
         if let Some(tx) = tx_guard.as_ref() {
@@ -71,6 +72,7 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
         let old_authors = std::mem::take(self.last_ping.lock().await.deref_mut());
         for author in old_authors.into_keys() {
             self.subscribable_topic.author_left(author);
+            self.node.peer_left_topic(author, self.id);
             self.set_last_seen(author).await;
         }
 
@@ -86,6 +88,16 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
     }
 
     pub async fn received(&self, message: AuthorMessage, author: PublicKey) {
+        // Authors who were removed from the topic are never reported as online
+        match self.node.topic_store.is_removed(&self.id, &author).await {
+            Ok(false) => {}
+            Ok(true) => return,
+            Err(error) => {
+                error!("Failed to check if author {author} was removed: {error}");
+                return;
+            }
+        }
+
         match message {
             AuthorMessage::Hello => {
                 self.join(author).await;
@@ -108,10 +120,11 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
     async fn join(&self, author: PublicKey) {
         self.last_ping.lock().await.insert(author, Instant::now());
         self.subscribable_topic.author_joined(author);
+        self.node.peer_joined_topic(author, self.id);
         self.set_last_seen(author).await;
 
         // Send a ping to the network to ensure that the new author knows we exist
-        // Normally we send a ping every `OFFLINE_TIMEOUT / 2`
+        // Normally we send a ping every half of the configured offline timeout
         self.send(AuthorMessage::Ping).await;
     }
 
@@ -121,6 +134,7 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
         // If this is a new author emit author join
         if old.is_none() {
             self.subscribable_topic.author_joined(author);
+            self.node.peer_joined_topic(author, self.id);
         }
         self.set_last_seen(author).await;
     }
@@ -128,6 +142,7 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
     async fn left(&self, author: PublicKey) {
         self.last_ping.lock().await.remove(&author);
         self.subscribable_topic.author_left(author);
+        self.node.peer_left_topic(author, self.id);
         self.set_last_seen(author).await;
     }
 
@@ -135,7 +150,8 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
         // Send a hello to the network so other authors know we joined the topic
         self.send(AuthorMessage::Hello).await;
 
-        let mut interval = tokio::time::interval(OFFLINE_TIMEOUT / 2);
+        let offline_timeout = self.node.config.offline_timeout();
+        let mut interval = tokio::time::interval(offline_timeout / 2);
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         // Skip over the first tick which completes immediately
         interval.tick().await;
@@ -147,7 +163,7 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
             self.send(AuthorMessage::Ping).await;
             let mut expired = Vec::new();
             self.last_ping.lock().await.retain(|author, instant| {
-                if instant.elapsed() > OFFLINE_TIMEOUT {
+                if instant.elapsed() > offline_timeout {
                     expired.push(*author);
                     false
                 } else {
@@ -157,6 +173,7 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
 
             for author in expired {
                 self.subscribable_topic.author_left(author);
+                self.node.peer_left_topic(author, self.id);
                 self.set_last_seen(author).await;
             }
         }
@@ -174,7 +191,7 @@ impl<T: SubscribableTopic> AuthorTracker<T> {
     }
 }
 
-async fn send_message(private_key: &PrivateKey, tx: &GossipHandle, message: AuthorMessage) {
+async fn send_message(private_key: &PrivateKey, tx: &EphemeralHandle, message: AuthorMessage) {
     // FIXME: We need to add the current time to the message,
     // because iroh doesn't broadcast twice the same message message.
     let author_message = match encode_cbor(&(&message, SystemTime::now())) {
@@ -0,0 +1,146 @@
+use p2panda_core::cbor::{DecodeError, EncodeError, decode_cbor, encode_cbor};
+use p2panda_core::identity::{PrivateKey, PublicKey, Signature};
+use p2panda_core::{Body, Header, Operation};
+use p2panda_net::TopicId;
+use thiserror::Error;
+use tokio::task::JoinError;
+use tracing::warn;
+
+use crate::operation::ReflectionExtensions;
+
+/// Version of the bundle format, bumped on incompatible changes.
+const BUNDLE_VERSION: u8 = 3;
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    TopicStore(#[from] sqlx::Error),
+    #[error(transparent)]
+    Runtime(#[from] JoinError),
+    #[error("Unsupported bundle version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Bundle with an invalid signature")]
+    InvalidSignature,
+    #[error("Bundle wasn't exported by an author of the topic")]
+    UnknownExporter,
+    #[error("Topic isn't known or was created before topics had a secret")]
+    UnknownTopic,
+    #[error("Bundle belongs to a different topic")]
+    WrongTopic,
+}
+
+/// All operations of a topic, signed by the node which exported them.
+///
+/// Bundles don't contain the secret or the keys of the topic, so they can only be read together
+/// with its invite code. The operations keep their original signatures and are validated on
+/// import like operations received from the network.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct SignedBundle {
+    #[serde(with = "serde_bytes")]
+    content: Vec<u8>,
+    exported_by: PublicKey,
+    signature: Signature,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct TopicBundle {
+    version: u8,
+    pub(crate) id: TopicId,
+    operations: Vec<BundleOperation>,
+}
+
+/// An operation with its raw header, as it was signed by the author.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct BundleOperation {
+    #[serde(with = "serde_bytes")]
+    header: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    body: Option<Vec<u8>>,
+}
+
+impl From<&Operation<ReflectionExtensions>> for BundleOperation {
+    fn from(operation: &Operation<ReflectionExtensions>) -> Self {
+        Self {
+            header: operation.header.to_bytes(),
+            body: operation.body.as_ref().map(|body| body.to_bytes()),
+        }
+    }
+}
+
+impl BundleOperation {
+    /// Decode the operation, `None` if the header can't be decoded.
+    pub(crate) fn into_operation(self) -> Option<Operation<ReflectionExtensions>> {
+        let header: Header<ReflectionExtensions> = match decode_cbor(&self.header[..]) {
+            Ok(header) => header,
+            Err(error) => {
+                warn!("Failed to decode operation header: {error}");
+                return None;
+            }
+        };
+
+        Some(Operation {
+            hash: header.hash(),
+            header,
+            body: self.body.as_deref().map(Body::new),
+        })
+    }
+}
+
+impl TopicBundle {
+    pub(crate) fn new(id: TopicId, operations: Vec<Operation<ReflectionExtensions>>) -> Self {
+        let operations = operations.iter().map(BundleOperation::from).collect();
+
+        Self {
+            version: BUNDLE_VERSION,
+            id,
+            operations,
+        }
+    }
+
+    /// The operations of the bundle, operations which can't be decoded are skipped.
+    pub(crate) fn operations(self) -> impl Iterator<Item = Operation<ReflectionExtensions>> {
+        self.operations
+            .into_iter()
+            .filter_map(BundleOperation::into_operation)
+    }
+
+    /// Encode and sign the bundle.
+    pub(crate) fn to_bytes(&self, private_key: &PrivateKey) -> Result<Vec<u8>, BundleError> {
+        let content = encode_cbor(self)?;
+        let bundle = SignedBundle {
+            signature: private_key.sign(&content),
+            exported_by: private_key.public_key(),
+            content,
+        };
+
+        Ok(encode_cbor(&bundle)?)
+    }
+
+    /// Decode a bundle after checking its signature, returns the bundle and the author who
+    /// exported it.
+    ///
+    /// The signature only shows that the bundle wasn't changed after it was exported, callers
+    /// need to check that the author who exported it belongs to the topic.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<(Self, PublicKey), BundleError> {
+        let SignedBundle {
+            content,
+            exported_by,
+            signature,
+        } = decode_cbor(bytes)?;
+
+        if !exported_by.verify(&content, &signature) {
+            return Err(BundleError::InvalidSignature);
+        }
+
+        let bundle: TopicBundle = decode_cbor(&content[..])?;
+        if bundle.version != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(bundle.version));
+        }
+
+        Ok((bundle, exported_by))
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use p2panda_net::addrs::NodeInfo;
+use p2panda_net::iroh_endpoint::{EndpointAddr, RelayUrl};
+use thiserror::Error;
+
+/// Time after which an author is considered offline when we didn't hear from them.
+const DEFAULT_OFFLINE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Invalid relay URL: {0}")]
+    InvalidRelayUrl(String),
+    #[error("Invalid bootstrap node id: {0}")]
+    InvalidNodeId(String),
+}
+
+/// Configuration of the network of a [`Node`](crate::node::Node).
+///
+/// The default configuration uses neither relays nor bootstrap nodes, so peers can only be
+/// found in the local network via mDNS.
+#[derive(Clone, Debug)]
+pub struct NodeConfig {
+    pub(crate) relay_urls: Vec<RelayUrl>,
+    pub(crate) bootstrap_nodes: Vec<NodeInfo>,
+    pub(crate) mdns: bool,
+    pub(crate) offline_timeout: Duration,
+    pub(crate) sync_directory: Option<PathBuf>,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            relay_urls: Vec::new(),
+            bootstrap_nodes: Vec::new(),
+            mdns: true,
+            offline_timeout: DEFAULT_OFFLINE_TIMEOUT,
+            sync_directory: None,
+        }
+    }
+}
+
+impl NodeConfig {
+    pub fn builder() -> NodeConfigBuilder {
+        NodeConfigBuilder::default()
+    }
+
+    pub fn relay_urls(&self) -> &[RelayUrl] {
+        &self.relay_urls
+    }
+
+    pub fn bootstrap_nodes(&self) -> &[NodeInfo] {
+        &self.bootstrap_nodes
+    }
+
+    pub fn mdns(&self) -> bool {
+        self.mdns
+    }
+
+    pub fn offline_timeout(&self) -> Duration {
+        self.offline_timeout
+    }
+
+    pub fn sync_directory(&self) -> Option<&Path> {
+        self.sync_directory.as_deref()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NodeConfigBuilder {
+    relay_urls: Vec<String>,
+    bootstrap_nodes: Vec<(String, Option<String>)>,
+    mdns: bool,
+    offline_timeout: Duration,
+    sync_directory: Option<PathBuf>,
+}
+
+impl Default for NodeConfigBuilder {
+    fn default() -> Self {
+        Self {
+            relay_urls: Vec::new(),
+            bootstrap_nodes: Vec::new(),
+            mdns: true,
+            offline_timeout: DEFAULT_OFFLINE_TIMEOUT,
+            sync_directory: None,
+        }
+    }
+}
+
+impl NodeConfigBuilder {
+    /// Add a relay server used to reach peers which aren't directly reachable.
+    pub fn relay_url(mut self, url: impl Into<String>) -> Self {
+        self.relay_urls.push(url.into());
+        self
+    }
+
+    /// Add a node used to discover other peers, optionally reachable via the given relay.
+    pub fn bootstrap_node(mut self, node_id: impl Into<String>, relay_url: Option<String>) -> Self {
+        self.bootstrap_nodes.push((node_id.into(), relay_url));
+        self
+    }
+
+    /// Whether peers in the local network are discovered via mDNS, enabled by default.
+    pub fn mdns(mut self, mdns: bool) -> Self {
+        self.mdns = mdns;
+        self
+    }
+
+    /// Time after which an author is considered offline when we didn't hear from them.
+    pub fn offline_timeout(mut self, offline_timeout: Duration) -> Self {
+        self.offline_timeout = offline_timeout;
+        self
+    }
+
+    /// Directory used to sync topics in
+    /// [`ConnectionMode::Directory`](crate::node::ConnectionMode::Directory), e.g. on a USB drive.
+    pub fn sync_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.sync_directory = Some(directory.into());
+        self
+    }
+
+    pub fn build(self) -> Result<NodeConfig, ConfigError> {
+        let relay_urls = self
+            .relay_urls
+            .into_iter()
+            .map(|url| parse_relay_url(&url))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let bootstrap_nodes = self
+            .bootstrap_nodes
+            .into_iter()
+            .map(|(node_id, relay_url)| {
+                let mut endpoint_addr = EndpointAddr::new(
+                    node_id
+                        .parse()
+                        .map_err(|_| ConfigError::InvalidNodeId(node_id.clone()))?,
+                );
+                if let Some(relay_url) = relay_url {
+                    endpoint_addr = endpoint_addr.with_relay_url(parse_relay_url(&relay_url)?);
+                }
+
+                Ok(NodeInfo::from(endpoint_addr).bootstrap())
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+
+        Ok(NodeConfig {
+            relay_urls,
+            bootstrap_nodes,
+            mdns: self.mdns,
+            offline_timeout: self.offline_timeout,
+            sync_directory: self.sync_directory,
+        })
+    }
+}
+
+fn parse_relay_url(url: &str) -> Result<RelayUrl, ConfigError> {
+    url.parse()
+        .map_err(|_| ConfigError::InvalidRelayUrl(url.to_owned()))
+}
@@ -0,0 +1,437 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use p2panda_core::cbor::{decode_cbor, encode_cbor};
+use p2panda_core::{Hash, Operation, PublicKey};
+use p2panda_net::TopicId;
+use p2panda_store::SqliteStore;
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, spawn, spawn_blocking};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{error, info, warn};
+
+use crate::bundle::BundleOperation;
+use crate::operation::{LogType, ReflectionExtensions};
+use crate::operation_store::OperationStore;
+use crate::topic_store::{LogId, TopicStore};
+use crate::transport::{
+    BoxFuture, BoxStream, EphemeralHandle, PeerInfo, SyncEvent, SyncHandle, TopicHandle, Transport,
+    TransportError,
+};
+
+/// Interval in which the sync directory is read from and written to.
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time after which operations we pulled in but which didn't end up in our store are pulled in
+/// again, e.g. because the access-control list they depend on wasn't known yet.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of times operations are pulled in before they count as rejected, e.g. because their
+/// author isn't allowed to write, and are never pulled in again.
+const MAX_PULL_ATTEMPTS: u32 = 3;
+
+/// Transport which syncs operations via a shared directory, e.g. on a USB drive or a network
+/// share.
+///
+/// Every log of every author of a topic is stored in its own file, which is only ever appended
+/// to. Nodes periodically append the operations they know and pull in the ones they don't know
+/// yet, so passing a drive around is enough to merge topics. Nodes take a lock on the directory
+/// of the topic while writing, so entries of different nodes or tasks never interleave.
+pub struct DirectoryTransport {
+    directory: PathBuf,
+    operation_store: SqliteStore<LogId, ReflectionExtensions>,
+    topic_store: TopicStore,
+}
+
+impl DirectoryTransport {
+    pub fn new(
+        directory: PathBuf,
+        topic_store: &TopicStore,
+        operation_store: &OperationStore,
+    ) -> Self {
+        Self {
+            directory,
+            operation_store: operation_store.clone_inner(),
+            topic_store: topic_store.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for DirectoryTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DirectoryTransport")
+            .field("directory", &self.directory)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transport for DirectoryTransport {
+    fn sync_stream(&self, id: TopicId) -> BoxFuture<'_, Result<SyncHandle, TransportError>> {
+        Box::pin(async move {
+            let topic_directory = self.directory.join(hex::encode(id));
+            let (tx, rx) = mpsc::unbounded_channel();
+
+            let operation_store = self.operation_store.clone();
+            let topic_store = self.topic_store.clone();
+            let topic_directory_clone = topic_directory.clone();
+            let task = spawn(async move {
+                // Operations we pulled in, they are only known once they are in our store
+                let pulled_in = Arc::new(Mutex::new(HashMap::new()));
+                let mut interval = tokio::time::interval(SYNC_INTERVAL);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+                loop {
+                    interval.tick().await;
+
+                    let operations =
+                        match topic_store.operations_for_sync(&operation_store, &id).await {
+                            Ok(operations) => operations,
+                            Err(error) => {
+                                error!("Failed to load operations for the sync directory: {error}");
+                                continue;
+                            }
+                        };
+
+                    let topic_directory = topic_directory_clone.clone();
+                    let pulled_in = pulled_in.clone();
+                    let result = spawn_blocking(move || {
+                        sync_topic_directory(
+                            &topic_directory,
+                            operations,
+                            &mut pulled_in.lock().unwrap(),
+                        )
+                    })
+                    .await;
+
+                    let received = match result.map_err(io::Error::other).and_then(|result| result)
+                    {
+                        Ok(received) => received,
+                        Err(error) => {
+                            error!("Failed to sync with the sync directory: {error}");
+                            continue;
+                        }
+                    };
+
+                    for (peer, operations) in received {
+                        info!(
+                            "Pulled in {} operations by {peer} from the sync directory",
+                            operations.len()
+                        );
+
+                        let _ = tx.send(SyncEvent::Started { peer });
+                        for operation in operations {
+                            let _ = tx.send(SyncEvent::Operation { peer, operation });
+                        }
+                        let _ = tx.send(SyncEvent::Completed { peer });
+                    }
+
+                    // Nobody is interested in the operations anymore
+                    if tx.is_closed() {
+                        break;
+                    }
+                }
+            })
+            .abort_handle();
+
+            Ok(Arc::new(DirectoryTopic {
+                topic_directory,
+                rx: Mutex::new(Some(rx)),
+                task,
+            }) as SyncHandle)
+        })
+    }
+
+    fn ephemeral_stream(
+        &self,
+        _id: TopicId,
+    ) -> BoxFuture<'_, Result<EphemeralHandle, TransportError>> {
+        Box::pin(async move { Ok(Arc::new(DirectoryEphemeralTopic) as EphemeralHandle) })
+    }
+
+    fn peers(&self) -> BoxFuture<'_, Result<Vec<PeerInfo>, TransportError>> {
+        // Other nodes are never connected to us, they just left their operations behind
+        Box::pin(async move { Ok(Vec::new()) })
+    }
+}
+
+struct DirectoryTopic {
+    topic_directory: PathBuf,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<SyncEvent>>>,
+    /// Task which periodically syncs with the directory.
+    task: AbortHandle,
+}
+
+impl Drop for DirectoryTopic {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl TopicHandle<Operation<ReflectionExtensions>, SyncEvent> for DirectoryTopic {
+    fn publish(
+        &self,
+        operation: Operation<ReflectionExtensions>,
+    ) -> BoxFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            let topic_directory = self.topic_directory.clone();
+            spawn_blocking(move || {
+                let _lock = lock_directory(&topic_directory)?;
+                let path = log_path(
+                    &topic_directory.join(operation.header.public_key.to_hex()),
+                    operation.header.extensions.log_type,
+                );
+                let log = read_log(&path)?;
+                if log
+                    .operations
+                    .iter()
+                    .all(|stored| stored.hash != operation.hash)
+                {
+                    append_to_log(&path, log.end, &[operation])?;
+                }
+
+                io::Result::Ok(())
+            })
+            .await
+            .map_err(io::Error::other)??;
+
+            Ok(())
+        })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<SyncEvent>, TransportError>> {
+        Box::pin(async move {
+            let rx = self
+                .rx
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or(TransportError::AlreadySubscribed)?;
+
+            Ok(Box::pin(UnboundedReceiverStream::new(rx)) as BoxStream<_>)
+        })
+    }
+}
+
+/// Ephemeral messages are only meaningful while other nodes are online, therefore they are
+/// never written to the directory.
+struct DirectoryEphemeralTopic;
+
+impl TopicHandle<Vec<u8>> for DirectoryEphemeralTopic {
+    fn publish(&self, _bytes: Vec<u8>) -> BoxFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<Vec<u8>>, TransportError>> {
+        Box::pin(async move { Ok(Box::pin(tokio_stream::pending()) as BoxStream<_>) })
+    }
+}
+
+/// An operation we pulled in from the sync directory.
+struct PulledIn {
+    at: Instant,
+    attempts: u32,
+}
+
+/// Append the `operations` we know to the logs in `topic_directory` and return the operations
+/// of all logs which we don't know yet, grouped by author.
+///
+/// Operations we returned in an earlier sync are kept in `pulled_in` until they show up in our
+/// store, they are only returned again after [`RETRY_INTERVAL`] and at most
+/// [`MAX_PULL_ATTEMPTS`] times.
+fn sync_topic_directory(
+    topic_directory: &Path,
+    operations: Vec<Operation<ReflectionExtensions>>,
+    pulled_in: &mut HashMap<Hash, PulledIn>,
+) -> io::Result<Vec<(PublicKey, Vec<Operation<ReflectionExtensions>>)>> {
+    let _lock = lock_directory(topic_directory)?;
+
+    let mut known = HashSet::new();
+    let mut logs: HashMap<_, Vec<_>> = HashMap::new();
+    for operation in operations {
+        known.insert(operation.hash);
+        let log = (
+            operation.header.public_key,
+            operation.header.extensions.log_type,
+        );
+        logs.entry(log).or_default().push(operation);
+    }
+    pulled_in.retain(|hash, _| !known.contains(hash));
+
+    for ((author, log_type), mut operations) in logs {
+        let path = log_path(&topic_directory.join(author.to_hex()), log_type);
+        let log = read_log(&path)?;
+        let stored: HashSet<Hash> = log
+            .operations
+            .iter()
+            .map(|operation| operation.hash)
+            .collect();
+        operations.retain(|operation| !stored.contains(&operation.hash));
+        operations.sort_by_key(|operation| operation.header.seq_num);
+
+        if !operations.is_empty() {
+            append_to_log(&path, log.end, &operations)?;
+        }
+    }
+
+    let author_directories = match fs::read_dir(topic_directory) {
+        Ok(author_directories) => author_directories,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut author_directories: Vec<PathBuf> = author_directories
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    author_directories.retain(|path| path.is_dir());
+
+    // Pull in the access-control lists of all authors before the content they apply to, so the
+    // write permissions of the content are checked against them
+    let mut received: Vec<(PublicKey, Vec<_>)> = Vec::new();
+    for log_type in [LogType::Acl, LogType::Snapshot, LogType::Delta] {
+        for author_directory in &author_directories {
+            for operation in read_log(&log_path(author_directory, log_type))?.operations {
+                if known.contains(&operation.hash) {
+                    continue;
+                }
+                match pulled_in.get_mut(&operation.hash) {
+                    Some(earlier)
+                        if earlier.attempts >= MAX_PULL_ATTEMPTS
+                            || earlier.at.elapsed() < RETRY_INTERVAL =>
+                    {
+                        continue;
+                    }
+                    Some(earlier) => {
+                        earlier.at = Instant::now();
+                        earlier.attempts += 1;
+                    }
+                    None => {
+                        pulled_in.insert(
+                            operation.hash,
+                            PulledIn {
+                                at: Instant::now(),
+                                attempts: 1,
+                            },
+                        );
+                    }
+                }
+
+                let author = operation.header.public_key;
+                match received.iter_mut().find(|(peer, _)| *peer == author) {
+                    Some((_, operations)) => operations.push(operation),
+                    None => received.push((author, vec![operation])),
+                }
+            }
+        }
+    }
+
+    Ok(received)
+}
+
+/// Take an exclusive lock on `topic_directory`, which is released when the returned file is
+/// dropped.
+fn lock_directory(topic_directory: &Path) -> io::Result<File> {
+    fs::create_dir_all(topic_directory)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(topic_directory.join(".lock"))?;
+    file.lock()?;
+
+    Ok(file)
+}
+
+/// Path of the file containing the log of the author with the given type.
+fn log_path(author_directory: &Path, log_type: LogType) -> PathBuf {
+    let name = match log_type {
+        LogType::Snapshot => "snapshot.log",
+        LogType::Delta => "delta.log",
+        LogType::Acl => "acl.log",
+    };
+
+    author_directory.join(name)
+}
+
+/// The operations of a log file.
+struct Log {
+    operations: Vec<Operation<ReflectionExtensions>>,
+    /// Offset after the last complete entry, new entries are written from there.
+    end: u64,
+}
+
+/// Read all operations of a log file.
+///
+/// Each entry is prefixed with its length. Entries which can't be decoded are skipped, a
+/// truncated entry at the end of the file, e.g. when the drive was removed while writing, is
+/// ignored and overwritten by the next append.
+fn read_log(path: &Path) -> io::Result<Log> {
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(Log {
+                operations: Vec::new(),
+                end: 0,
+            });
+        }
+        Err(error) => return Err(error),
+    };
+
+    let mut operations = Vec::new();
+    let mut end = 0;
+    loop {
+        let mut length = [0; 4];
+        if reader.read_exact(&mut length).is_err() {
+            break;
+        }
+
+        let length = u32::from_be_bytes(length) as usize;
+        let mut entry = Vec::with_capacity(length.min(64 * 1024));
+        (&mut reader).take(length as u64).read_to_end(&mut entry)?;
+        if entry.len() != length {
+            break;
+        }
+        end += (length + 4) as u64;
+
+        match decode_cbor::<BundleOperation, _>(&entry[..]) {
+            Ok(operation) => operations.extend(operation.into_operation()),
+            Err(error) => warn!("Skipping invalid entry in {}: {error}", path.display()),
+        }
+    }
+
+    Ok(Log { operations, end })
+}
+
+/// Append `operations` to the log file at `path`, after the last complete entry at `end`.
+///
+/// A truncated entry after `end` is removed first. If it was one of ours, it's written again
+/// with the next sync, since it's still missing from the log.
+fn append_to_log(
+    path: &Path,
+    end: u64,
+    operations: &[Operation<ReflectionExtensions>],
+) -> io::Result<()> {
+    if let Some(author_directory) = path.parent() {
+        fs::create_dir_all(author_directory)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    file.set_len(end)?;
+    file.seek(SeekFrom::Start(end))?;
+
+    let mut writer = BufWriter::new(file);
+    for operation in operations {
+        let entry = encode_cbor(&BundleOperation::from(operation)).map_err(io::Error::other)?;
+        writer.write_all(&(entry.len() as u32).to_be_bytes())?;
+        writer.write_all(&entry)?;
+    }
+
+    // The drive may be removed right after syncing
+    writer.into_inner()?.sync_all()
+}
@@ -0,0 +1,199 @@
+use std::sync::{Arc, RwLock};
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use p2panda_core::{Hash, PrivateKey, PublicKey};
+use thiserror::Error;
+use x25519_dalek::StaticSecret;
+
+/// Length of the random nonce prepended to every encrypted payload.
+const NONCE_LEN: usize = 24;
+
+/// Domain separation for deriving the topic key from the topic secret.
+const KEY_CONTEXT: &[u8] = b"reflection-topic-key-v1";
+
+/// Domain separation for deriving the key used to share a topic key with another author.
+const KEY_SHARE_CONTEXT: &[u8] = b"reflection-key-share-v1";
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("Failed to encrypt payload")]
+    Encrypt,
+    #[error("Failed to decrypt payload")]
+    Decrypt,
+    #[error("Encrypted payload is too short")]
+    InvalidLength,
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+}
+
+/// Symmetric key used to encrypt all payloads of a topic.
+///
+/// Operation bodies and ephemeral messages are encrypted with XChaCha20-Poly1305 before they
+/// leave the node, so that peers, relays or anyone else who only knows the topic id can't read
+/// the content of a topic.
+#[derive(Clone)]
+pub struct TopicKey([u8; 32]);
+
+impl TopicKey {
+    /// Derives the topic key from the secret shared between all members of a topic.
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        let hash = Hash::new([KEY_CONTEXT, secret.as_slice()].concat());
+        Self(*hash.as_bytes())
+    }
+
+    /// Generates a new random key, used when a topic gets re-keyed.
+    pub fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Encrypts this key so that only `recipient` can read it with [`TopicKey::from_share`].
+    pub fn share_with(
+        &self,
+        private_key: &PrivateKey,
+        recipient: &PublicKey,
+    ) -> Result<Vec<u8>, EncryptionError> {
+        shared_key(private_key, recipient)?.encrypt(&self.0)
+    }
+
+    /// Decrypts a key `sender` shared with us via [`TopicKey::share_with`].
+    pub fn from_share(
+        private_key: &PrivateKey,
+        sender: &PublicKey,
+        share: &[u8],
+    ) -> Result<Self, EncryptionError> {
+        let bytes = shared_key(private_key, sender)?.decrypt(share)?;
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| EncryptionError::InvalidLength)?;
+
+        Ok(Self(bytes))
+    }
+
+    /// Encrypts the given payload, the resulting bytes contain the nonce followed by the
+    /// ciphertext.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.0));
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| EncryptionError::Encrypt)?;
+
+        let mut bytes = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(nonce.as_slice());
+        bytes.extend_from_slice(&ciphertext);
+
+        Ok(bytes)
+    }
+
+    /// Decrypts a payload previously encrypted with [`TopicKey::encrypt`].
+    pub fn decrypt(&self, bytes: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        if bytes.len() < NONCE_LEN {
+            return Err(EncryptionError::InvalidLength);
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.0));
+        cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| EncryptionError::Decrypt)
+    }
+}
+
+// Never print the key material
+impl std::fmt::Debug for TopicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopicKey").finish_non_exhaustive()
+    }
+}
+
+/// Derives a key only `private_key` and the owner of `public_key` know.
+///
+/// Both ed25519 keys are converted to their X25519 form and combined with a Diffie-Hellman key
+/// exchange.
+fn shared_key(
+    private_key: &PrivateKey,
+    public_key: &PublicKey,
+) -> Result<TopicKey, EncryptionError> {
+    let secret =
+        StaticSecret::from(SigningKey::from_bytes(private_key.as_bytes()).to_scalar_bytes());
+    let public = VerifyingKey::from_bytes(public_key.as_bytes())
+        .map_err(|_| EncryptionError::InvalidPublicKey)?
+        .to_montgomery();
+    let shared = secret.diffie_hellman(&public.to_bytes().into());
+    let hash = Hash::new([KEY_SHARE_CONTEXT, shared.as_bytes().as_slice()].concat());
+
+    Ok(TopicKey(*hash.as_bytes()))
+}
+
+/// All keys a topic was encrypted with over time.
+///
+/// New payloads are always encrypted with the most recent key, received payloads are decrypted
+/// with whichever key fits.
+#[derive(Clone, Debug)]
+pub struct Keyring(Arc<RwLock<Vec<TopicKey>>>);
+
+impl Keyring {
+    /// Creates a keyring from the given keys, ordered from oldest to most recent.
+    ///
+    /// Without keys payloads are passed through unencrypted, that's only the case for topics
+    /// created before payloads were encrypted.
+    pub fn new(keys: Vec<TopicKey>) -> Self {
+        Self(Arc::new(RwLock::new(keys)))
+    }
+
+    /// Adds a new key which is used for all future payloads.
+    pub fn add(&self, key: TopicKey) {
+        self.0.write().unwrap().push(key);
+    }
+
+    pub fn contains(&self, key: &TopicKey) -> bool {
+        self.0.read().unwrap().iter().any(|other| other.0 == key.0)
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        match self.0.read().unwrap().last() {
+            Some(key) => key.encrypt(plaintext),
+            None => Ok(plaintext.to_vec()),
+        }
+    }
+
+    /// Encrypts the payload with the oldest key, which is derived from the topic secret.
+    ///
+    /// Everyone who knows the secret can decrypt the payload, even if they joined after the
+    /// topic was re-keyed. That's needed for the access-control list, since it contains the keys
+    /// later members don't have yet.
+    pub fn encrypt_with_initial_key(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        match self.0.read().unwrap().first() {
+            Some(key) => key.encrypt(plaintext),
+            None => Ok(plaintext.to_vec()),
+        }
+    }
+
+    pub fn decrypt(&self, bytes: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let keys = self.0.read().unwrap();
+        if keys.is_empty() {
+            return Ok(bytes.to_vec());
+        }
+
+        let mut result = Err(EncryptionError::Decrypt);
+        // Most payloads are encrypted with the most recent key
+        for key in keys.iter().rev() {
+            result = key.decrypt(bytes);
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        result
+    }
+}
@@ -1,5 +1,11 @@
+mod acl;
 mod author_tracker;
+mod bundle;
+mod config;
+mod directory_transport;
+mod encryption;
 mod ephemerial_operation;
+mod memory_transport;
 mod network;
 pub mod node;
 mod node_inner;
@@ -8,33 +14,47 @@ mod operation_store;
 mod subscription_inner;
 pub mod topic;
 mod topic_store;
+mod transport;
 mod utils;
 
+pub use chrono;
 pub use p2panda_core;
 pub use topic::SubscribableTopic;
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::Duration;
 
+    use chrono::{DateTime, Utc};
     use p2panda_core::Hash;
     use p2panda_core::PrivateKey;
     use p2panda_core::PublicKey;
     use tokio::sync::{Mutex, mpsc};
+    use tokio_stream::StreamExt;
 
+    use crate::bundle::BundleError;
+    use crate::encryption::TopicKey;
     use crate::node::ConnectionMode;
-    use crate::node::Node;
-    use crate::topic::SubscribableTopic;
+    use crate::node::{Node, NodeConfig, PeerConnectionType, Role};
+    use crate::operation::LogType;
+    use crate::topic::{SubscribableTopic, TopicError, TopicSecret};
+
+    /// Time after which tests waiting for other nodes fail.
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
 
     #[tokio::test]
     #[test_log::test]
     async fn create_topic() {
         let private_key = PrivateKey::new();
         let network_id = Hash::new(b"reflection");
-        let node = Node::new(private_key, network_id, None).await.unwrap();
+        let node = Node::new(private_key, network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
 
-        let id: [u8; 32] = [0; 32];
-        let _sub = node.subscribe(id, TestTopic::new()).await;
+        let secret = TopicSecret::new([1; 32], PrivateKey::new().public_key());
+        let id = secret.id();
+        let _sub = node.subscribe(secret, TestTopic::new()).await;
         let topics = node.topics::<[u8; 32]>().await.unwrap();
 
         assert_eq!(topics.len(), 1);
@@ -47,20 +67,29 @@ mod tests {
     struct TestTopic {
         tx: mpsc::UnboundedSender<Vec<u8>>,
         rx: Arc<Mutex<mpsc::UnboundedReceiver<Vec<u8>>>>,
+        synced_tx: mpsc::UnboundedSender<PublicKey>,
+        synced_rx: Arc<Mutex<mpsc::UnboundedReceiver<PublicKey>>>,
     }
 
     impl TestTopic {
         fn new() -> Self {
             let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+            let (synced_tx, synced_rx) = mpsc::unbounded_channel::<PublicKey>();
             TestTopic {
                 tx,
                 rx: Arc::new(Mutex::new(rx)),
+                synced_tx,
+                synced_rx: Arc::new(Mutex::new(synced_rx)),
             }
         }
 
         async fn wait_for_bytes(&self) -> Vec<u8> {
             self.rx.lock().await.recv().await.unwrap()
         }
+
+        async fn wait_for_sync(&self) -> PublicKey {
+            self.synced_rx.lock().await.recv().await.unwrap()
+        }
     }
 
     impl SubscribableTopic for TestTopic {
@@ -71,6 +100,13 @@ mod tests {
         fn author_joined(&self, _author: PublicKey) {}
         fn author_left(&self, _author: PublicKey) {}
         fn ephemeral_bytes_received(&self, _author: PublicKey, _data: Vec<u8>) {}
+        fn author_removed(&self, _author: PublicKey, _removed_at: DateTime<Utc>) {}
+        fn sync_started(&self, _peer: PublicKey) {}
+        fn sync_progress(&self, _peer: PublicKey, _operations: u64) {}
+        fn sync_completed(&self, peer: PublicKey) {
+            self.synced_tx.send(peer).unwrap();
+        }
+        fn sync_failed(&self, _peer: PublicKey) {}
         fn error(&self, _error: crate::topic::SubscriptionError) {}
     }
 
@@ -79,15 +115,18 @@ mod tests {
     async fn subscribe_topic() {
         let private_key = PrivateKey::new();
         let network_id = Hash::new(b"reflection");
-        let node = Node::new(private_key, network_id, None).await.unwrap();
+        let node = Node::new(private_key, network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
         node.set_connection_mode(ConnectionMode::Network)
             .await
             .unwrap();
 
         let test_topic = TestTopic::new();
 
-        let id: [u8; 32] = [0; 32];
-        let subscription = node.subscribe(id, test_topic).await.unwrap();
+        let secret = TopicSecret::new([1; 32], PrivateKey::new().public_key());
+        let id = secret.id();
+        let subscription = node.subscribe(secret, test_topic).await.unwrap();
 
         let topics = node.topics::<[u8; 32]>().await.unwrap();
         assert_eq!(topics.len(), 1);
@@ -95,7 +134,9 @@ mod tests {
 
         let private_key2 = PrivateKey::new();
         let network_id2 = Hash::new(b"reflection");
-        let node2 = Node::new(private_key2, network_id2, None).await.unwrap();
+        let node2 = Node::new(private_key2, network_id2, None, NodeConfig::default())
+            .await
+            .unwrap();
         node2
             .set_connection_mode(ConnectionMode::Network)
             .await
@@ -103,7 +144,7 @@ mod tests {
 
         let test_topic2 = TestTopic::new();
 
-        let _subscription2 = node2.subscribe(id, test_topic2.clone()).await.unwrap();
+        let _subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
 
         let topics2 = node2.topics::<[u8; 32]>().await.unwrap();
         assert_eq!(topics2.len(), 1);
@@ -120,4 +161,872 @@ mod tests {
         node.shutdown().await.unwrap();
         node2.shutdown().await.unwrap();
     }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn local_network() {
+        let secret = TopicSecret::new([2; 32], PrivateKey::new().public_key());
+        let network_id = Hash::new(b"reflection");
+        // Relays and bootstrap nodes are ignored in the local network mode
+        let config = NodeConfig::builder()
+            .relay_url("https://euc1-1.relay.n0.iroh-canary.iroh.link")
+            .mdns(false)
+            .build()
+            .unwrap();
+
+        let node = Node::new(PrivateKey::new(), network_id, None, config.clone())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::LocalNetwork)
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, config)
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::LocalNetwork)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+
+        let test_snapshot = "test".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn in_memory_transport() {
+        let secret = TopicSecret::new([3; 32], PrivateKey::new().public_key());
+        // Only nodes with the same network id are connected to each other
+        let network_id = Hash::new(b"in_memory_transport");
+
+        let private_key = PrivateKey::new();
+        let node = Node::new(private_key.clone(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        // Operations created before the other node joined are synced up
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+        assert_eq!(test_topic2.wait_for_sync().await, private_key.public_key());
+
+        let test_delta = "delta".as_bytes().to_vec();
+        subscription.send_delta(test_delta.clone()).await.unwrap();
+
+        assert_eq!(test_topic2.wait_for_bytes().await, test_delta);
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn background_subscription() {
+        let secret = TopicSecret::new([7; 32], PrivateKey::new().public_key());
+        let network_id = Hash::new(b"background_subscription");
+
+        let private_key = PrivateKey::new();
+        let node = Node::new(private_key.clone(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let subscription2 = node2
+            .subscribe_in_background(secret, test_topic2.clone())
+            .await
+            .unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+        subscription2.unsubscribe().await.unwrap();
+
+        // Operations we have already aren't passed on again, even when they are synced again
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2
+            .subscribe_in_background(secret, test_topic2.clone())
+            .await
+            .unwrap();
+        assert_eq!(test_topic2.wait_for_sync().await, private_key.public_key());
+        let test_delta = "delta".as_bytes().to_vec();
+        subscription.send_delta(test_delta.clone()).await.unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_delta);
+        assert!(test_topic2.rx.lock().await.try_recv().is_err());
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn unseen_changes() {
+        let secret = TopicSecret::new([8; 32], PrivateKey::new().public_key());
+        let network_id = Hash::new(b"unseen_changes");
+
+        let node = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+        let bookmark = "bookmark".as_bytes().to_vec();
+        subscription2
+            .set_bookmark(Some(bookmark.clone()))
+            .await
+            .unwrap();
+        subscription2.unsubscribe().await.unwrap();
+
+        // Everything was seen while subscribed
+        let topics = node2.topics::<[u8; 32]>().await.unwrap();
+        assert!(!topics[0].has_unseen_changes());
+        assert_eq!(topics[0].bookmark, Some(bookmark));
+
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2
+            .subscribe_in_background(secret, test_topic2.clone())
+            .await
+            .unwrap();
+        let test_delta = "delta".as_bytes().to_vec();
+        subscription.send_delta(test_delta.clone()).await.unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_delta);
+
+        // Snapshots only repeat the changes of deltas
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+
+        let topics = node2.topics::<[u8; 32]>().await.unwrap();
+        assert_eq!(topics[0].unseen_changes, 1);
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn topic_content() {
+        let secret = TopicSecret::new([9; 32], PrivateKey::new().public_key());
+        let private_key = PrivateKey::new();
+        let node = Node::new(
+            private_key.clone(),
+            Hash::new(b"topic_content"),
+            None,
+            NodeConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        // Nothing is stored for unknown topics
+        assert!(node.topic_content(secret).await.unwrap().is_empty());
+
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+        subscription.unsubscribe().await.unwrap();
+
+        let content = node.topic_content(secret).await.unwrap();
+        assert_eq!(content, vec![(private_key.public_key(), test_snapshot)]);
+
+        // Only the latest snapshot of each author is read
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let test_snapshot2 = "snapshot2".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot2.clone())
+            .await
+            .unwrap();
+        subscription
+            .send_delta("delta".as_bytes().to_vec())
+            .await
+            .unwrap();
+        subscription.unsubscribe().await.unwrap();
+
+        let snapshots = node.latest_snapshots(secret).await.unwrap();
+        assert_eq!(snapshots, vec![(private_key.public_key(), test_snapshot2)]);
+
+        node.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn peers() {
+        let secret = TopicSecret::new([4; 32], PrivateKey::new().public_key());
+        let id = secret.id();
+        let network_id = Hash::new(b"peers");
+
+        let node = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        // Without a connection there are no peers
+        assert!(node.peers().await.unwrap().is_empty());
+
+        node.set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let _subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        let peer_key = PrivateKey::new();
+        let node2 = Node::new(peer_key.clone(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let _subscription2 = node2.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        let peers = node.peers().await.unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].id, peer_key.public_key());
+        assert_eq!(peers[0].connection_type, PeerConnectionType::InMemory);
+
+        // The shared topic is known once the other node said hello
+        let mut peers_changed = node.peers_changed();
+        tokio::time::timeout(WAIT_TIMEOUT, async {
+            while let Some(peers) = peers_changed.next().await {
+                if peers.first().is_some_and(|peer| peer.topics == vec![id]) {
+                    break;
+                }
+            }
+        })
+        .await
+        .expect("topic of the peer to be known");
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn export_import_topic() {
+        let secret = TopicSecret::new([5; 32], PrivateKey::new().public_key());
+        let id = secret.id();
+        let network_id = Hash::new(b"reflection");
+
+        let node = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+        let bundle = node.export_topic(id).await.unwrap();
+
+        // Bundles are only accepted with a valid signature
+        let mut tampered_bundle = bundle.clone();
+        let last = tampered_bundle.len() - 1;
+        tampered_bundle[last] ^= 1;
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        assert!(node2.import_topic(tampered_bundle, secret).await.is_err());
+
+        // Bundles don't contain the secret, they need the one of their topic
+        let other_secret = TopicSecret::new([6; 32], PrivateKey::new().public_key());
+        assert!(matches!(
+            node2.import_topic(bundle.clone(), other_secret).await,
+            Err(BundleError::WrongTopic)
+        ));
+        node2.import_topic(bundle, secret).await.unwrap();
+
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+
+        // Bundles of nodes which didn't author anything in the topic aren't accepted
+        let node3 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        let _subscription3 = node3.subscribe(secret, TestTopic::new()).await.unwrap();
+        let stranger_bundle = node3.export_topic(id).await.unwrap();
+        let node4 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            node4.import_topic(stranger_bundle, secret).await,
+            Err(BundleError::UnknownExporter)
+        ));
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+        node3.shutdown().await.unwrap();
+        node4.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn directory_transport() {
+        let secret = TopicSecret::new([6; 32], PrivateKey::new().public_key());
+        let network_id = Hash::new(b"reflection");
+
+        let private_key = PrivateKey::new();
+        let directory =
+            std::env::temp_dir().join(format!("reflection-{}", private_key.public_key()));
+        let config = NodeConfig::builder()
+            .sync_directory(&directory)
+            .build()
+            .unwrap();
+
+        let node = Node::new(private_key.clone(), network_id, None, config.clone())
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        // Operations created while offline are written once the directory is used
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::Directory)
+            .await
+            .unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, config)
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::Directory)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+        assert_eq!(test_topic2.wait_for_sync().await, private_key.public_key());
+
+        // Without a directory there is nothing to sync with
+        let node3 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        assert!(
+            node3
+                .set_connection_mode(ConnectionMode::Directory)
+                .await
+                .is_err()
+        );
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+        node3.shutdown().await.unwrap();
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn directory_transport_damaged_log() {
+        let secret = TopicSecret::new([11; 32], PrivateKey::new().public_key());
+        let network_id = Hash::new(b"reflection");
+
+        let private_key = PrivateKey::new();
+        let directory =
+            std::env::temp_dir().join(format!("reflection-{}", private_key.public_key()));
+        let config = NodeConfig::builder()
+            .sync_directory(&directory)
+            .build()
+            .unwrap();
+
+        // A log with an entry which can't be decoded, followed by a truncated entry
+        let author_directory = directory
+            .join(hex::encode(secret.id()))
+            .join(private_key.public_key().to_hex());
+        std::fs::create_dir_all(&author_directory).unwrap();
+        let mut log = Vec::new();
+        log.extend(3u32.to_be_bytes());
+        log.extend(b"bad");
+        log.extend(100u32.to_be_bytes());
+        log.extend(b"torn");
+        std::fs::write(author_directory.join("snapshot.log"), log).unwrap();
+
+        let node = Node::new(private_key.clone(), network_id, None, config.clone())
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let test_snapshot = "snapshot".as_bytes().to_vec();
+        subscription
+            .send_snapshot(test_snapshot.clone())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::Directory)
+            .await
+            .unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, config)
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::Directory)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let _subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+
+        assert_eq!(test_topic2.wait_for_bytes().await, test_snapshot);
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn bluetooth_unsupported() {
+        let network_id = Hash::new(b"reflection");
+        let node = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+
+        // Bluetooth isn't implemented, the node goes offline instead
+        assert!(
+            node.set_connection_mode(ConnectionMode::Bluetooth)
+                .await
+                .is_err()
+        );
+        assert!(node.inner.network.read().await.is_none());
+
+        node.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encrypt_payloads() {
+        let private_key = PrivateKey::new();
+        let network_id = Hash::new(b"reflection");
+        let node = Node::new(private_key, network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+
+        let secret = TopicSecret::new([1; 32], PrivateKey::new().public_key());
+        let id = secret.id();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        let test_delta = "confidential meeting notes".as_bytes().to_vec();
+        subscription.send_delta(test_delta.clone()).await.unwrap();
+
+        // Only the encrypted payload ends up in the operation store
+        let operations = node
+            .inner
+            .topic_store
+            .operations_for_topic(&node.inner.operation_store, &id)
+            .await
+            .unwrap();
+        let body = operations
+            .into_iter()
+            .find_map(|operation| operation.body)
+            .unwrap()
+            .to_bytes();
+        assert_ne!(body, test_delta);
+
+        let key = TopicKey::from_secret(secret.secret().unwrap());
+        assert_eq!(key.decrypt(&body).unwrap(), test_delta);
+
+        // A key derived from another secret can't read the payload
+        let other_key = TopicKey::from_secret(&[2; 32]);
+        assert!(other_key.decrypt(&body).is_err());
+
+        node.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn legacy_topic() {
+        let private_key = PrivateKey::new();
+        let network_id = Hash::new(b"reflection");
+        let node = Node::new(private_key, network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+
+        // Topics created before payloads were encrypted only have an id
+        let id = [3; 32];
+        let secret = TopicSecret::legacy(id);
+        assert_eq!(secret.id(), id);
+        assert_eq!(
+            TopicSecret::try_from(&secret.to_bytes()[..]).unwrap(),
+            secret
+        );
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+
+        let test_delta = "old meeting notes".as_bytes().to_vec();
+        subscription.send_delta(test_delta.clone()).await.unwrap();
+
+        // Their payloads stay readable without a key
+        let operations = node
+            .inner
+            .topic_store
+            .operations_for_topic(&node.inner.operation_store, &id)
+            .await
+            .unwrap();
+        let body = operations
+            .into_iter()
+            .find_map(|operation| operation.body)
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, test_delta);
+
+        let topics = node.topics::<[u8; 32]>().await.unwrap();
+        assert_eq!(topics[0].secret, Some(secret));
+        assert!(topics[0].acl.is_none());
+
+        node.shutdown().await.unwrap();
+    }
+
+    #[test]
+    fn share_topic_key() {
+        let owner = PrivateKey::new();
+        let member = PrivateKey::new();
+        let removed_member = PrivateKey::new();
+
+        let key = TopicKey::generate();
+        let share = key.share_with(&owner, &member.public_key()).unwrap();
+
+        let received_key = TopicKey::from_share(&member, &owner.public_key(), &share).unwrap();
+        assert_eq!(received_key.as_bytes(), key.as_bytes());
+
+        // Nobody else can read the shared key
+        assert!(TopicKey::from_share(&removed_member, &owner.public_key(), &share).is_err());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn share_keys_with_later_authors() {
+        let network_id = Hash::new(b"share_keys_with_later_authors");
+        let private_key = PrivateKey::new();
+        let secret = TopicSecret::new([10; 32], private_key.public_key());
+        let id = secret.id();
+
+        let node = Node::new(private_key, network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node.set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        subscription
+            .revoke(PrivateKey::new().public_key())
+            .await
+            .unwrap();
+
+        let node2 = Node::new(PrivateKey::new(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        node2
+            .set_connection_mode(ConnectionMode::InMemory)
+            .await
+            .unwrap();
+        let test_topic2 = TestTopic::new();
+        let subscription2 = node2.subscribe(secret, test_topic2.clone()).await.unwrap();
+        assert!(matches!(
+            subscription2.revoke(PrivateKey::new().public_key()).await,
+            Err(TopicError::NotOwner)
+        ));
+
+        // The owner shares the new key once it learns about the new author
+        subscription2
+            .send_delta("hello".as_bytes().to_vec())
+            .await
+            .unwrap();
+        tokio::time::timeout(WAIT_TIMEOUT, async {
+            while node2
+                .inner
+                .topic_store
+                .topic_keys(&id)
+                .await
+                .unwrap()
+                .is_empty()
+            {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("key to be shared with the new author");
+
+        let test_delta = "after re-key".as_bytes().to_vec();
+        subscription.send_delta(test_delta.clone()).await.unwrap();
+        assert_eq!(test_topic2.wait_for_bytes().await, test_delta);
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn revoke_member() {
+        let network_id = Hash::new(b"revoke_member");
+        let owner_key = PrivateKey::new();
+        let secret = TopicSecret::new([11; 32], owner_key.public_key());
+        let id = secret.id();
+
+        let mut nodes = Vec::new();
+        let mut topics = Vec::new();
+        let mut subscriptions = Vec::new();
+        let member_key = PrivateKey::new();
+        let removed_key = PrivateKey::new();
+        for private_key in [owner_key, member_key.clone(), removed_key.clone()] {
+            let node = Node::new(private_key, network_id, None, NodeConfig::default())
+                .await
+                .unwrap();
+            node.set_connection_mode(ConnectionMode::InMemory)
+                .await
+                .unwrap();
+            let test_topic = TestTopic::new();
+            subscriptions.push(node.subscribe(secret, test_topic.clone()).await.unwrap());
+            topics.push(test_topic);
+            nodes.push(node);
+        }
+
+        // The owner only shares new keys with authors it knows about
+        let before_revoke = "before revoke".as_bytes().to_vec();
+        subscriptions[1]
+            .send_delta(b"hello".to_vec())
+            .await
+            .unwrap();
+        subscriptions[2]
+            .send_delta(before_revoke.clone())
+            .await
+            .unwrap();
+        tokio::time::timeout(WAIT_TIMEOUT, async {
+            loop {
+                let authors = nodes[0].inner.topic_store.authors(&id).await.unwrap();
+                if authors.contains(&member_key.public_key())
+                    && authors.contains(&removed_key.public_key())
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("owner to know the other authors");
+
+        subscriptions[0]
+            .revoke(removed_key.public_key())
+            .await
+            .unwrap();
+        let after_revoke = "after revoke".as_bytes().to_vec();
+        subscriptions[0]
+            .send_delta(after_revoke.clone())
+            .await
+            .unwrap();
+
+        // Remaining members receive the new key and can read what's written afterwards
+        tokio::time::timeout(WAIT_TIMEOUT, async {
+            while topics[1].wait_for_bytes().await != after_revoke {}
+        })
+        .await
+        .expect("remaining member to read content written after revoking");
+
+        // The removed member never gets the new key, so only the initial key is left to them
+        assert!(
+            nodes[2]
+                .inner
+                .topic_store
+                .topic_keys(&id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let operations = nodes[0]
+            .inner
+            .topic_store
+            .operations_for_topic(&nodes[0].inner.operation_store, &id)
+            .await
+            .unwrap();
+        let initial_key = TopicKey::from_secret(secret.secret().unwrap());
+        let readable: Vec<_> = operations
+            .iter()
+            .filter_map(|operation| operation.body.as_ref())
+            .filter_map(|body| initial_key.decrypt(&body.to_bytes()).ok())
+            .collect();
+        assert!(readable.contains(&before_revoke));
+        assert!(!readable.contains(&after_revoke));
+
+        for node in nodes {
+            node.shutdown().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn write_permissions() {
+        let private_key = PrivateKey::new();
+        let network_id = Hash::new(b"reflection");
+        let node = Node::new(private_key.clone(), network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+
+        let owner = private_key.public_key();
+        let secret = TopicSecret::new([1; 32], owner);
+        let id = secret.id();
+        let subscription = node.subscribe(secret, TestTopic::new()).await.unwrap();
+        let can_write_at = async |author: &PublicKey, log_type: LogType, timestamp: u64| {
+            node.inner
+                .topic_store
+                .can_write(&id, author, log_type, timestamp)
+                .await
+                .unwrap()
+        };
+        let can_write = async |author: &PublicKey, log_type: LogType| {
+            can_write_at(author, log_type, Utc::now().timestamp() as u64).await
+        };
+        let member_key = PrivateKey::new();
+        let member = member_key.public_key();
+
+        // The owner is part of the secret, everyone else can write content by default
+        assert_eq!(
+            node.inner.topic_store.owner(&id).await.unwrap(),
+            Some(owner)
+        );
+        assert!(can_write(&owner, LogType::Acl).await);
+        assert!(can_write(&member, LogType::Delta).await);
+        assert!(!can_write(&member, LogType::Acl).await);
+
+        // Read-only topic
+        subscription
+            .set_default_role(Some(Role::Read))
+            .await
+            .unwrap();
+        assert!(can_write(&owner, LogType::Delta).await);
+        assert!(!can_write(&member, LogType::Delta).await);
+
+        subscription
+            .set_member_role(member, Some(Role::Write))
+            .await
+            .unwrap();
+        assert!(can_write(&member, LogType::Snapshot).await);
+
+        // Removed members can't write even if everyone else can
+        subscription
+            .set_default_role(Some(Role::Write))
+            .await
+            .unwrap();
+        subscription.set_member_role(member, None).await.unwrap();
+        let acl = node.inner.topic_store.acl(&id).await.unwrap().unwrap();
+        let removed_at = acl.removed_at[&member].timestamp() as u64;
+        assert!(!can_write_at(&member, LogType::Delta, removed_at + 1).await);
+
+        // What removed members wrote before they were removed is still accepted
+        assert!(can_write_at(&member, LogType::Delta, removed_at).await);
+        assert!(can_write_at(&member, LogType::Snapshot, removed_at - 1).await);
+        assert!(!can_write_at(&member, LogType::Delta, removed_at + 1).await);
+        assert!(!can_write_at(&member, LogType::Acl, removed_at - 1).await);
+
+        // Members know the owner without syncing and can't change the access-control list
+        let node2 = Node::new(member_key, network_id, None, NodeConfig::default())
+            .await
+            .unwrap();
+        let subscription2 = node2.subscribe(secret, TestTopic::new()).await.unwrap();
+        assert_eq!(
+            node2.inner.topic_store.owner(&id).await.unwrap(),
+            Some(owner)
+        );
+        assert!(matches!(
+            subscription2.set_default_role(Some(Role::Write)).await,
+            Err(TopicError::NotOwner)
+        ));
+
+        node.shutdown().await.unwrap();
+        node2.shutdown().await.unwrap();
+    }
+
+    #[test]
+    fn node_config() {
+        let config = NodeConfig::builder()
+            .relay_url("https://euc1-1.relay.n0.iroh-canary.iroh.link")
+            .bootstrap_node(
+                "9f63a15ab95959a992af96bf72fbc3e7dc98eeb4799f788bb07b20125053e795",
+                Some("https://euc1-1.relay.n0.iroh-canary.iroh.link".to_owned()),
+            )
+            .mdns(false)
+            .offline_timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+
+        assert_eq!(config.relay_urls().len(), 1);
+        assert_eq!(config.bootstrap_nodes().len(), 1);
+        assert!(!config.mdns());
+        assert_eq!(config.offline_timeout(), Duration::from_secs(10));
+
+        assert!(
+            NodeConfig::builder()
+                .relay_url("not a url")
+                .build()
+                .is_err()
+        );
+        assert!(
+            NodeConfig::builder()
+                .bootstrap_node("not a node id", None)
+                .build()
+                .is_err()
+        );
+    }
 }
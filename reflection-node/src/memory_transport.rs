@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+
+use p2panda_core::{Hash, Operation, PublicKey};
+use p2panda_net::TopicId;
+use p2panda_store::SqliteStore;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::operation::ReflectionExtensions;
+use crate::operation_store::OperationStore;
+use crate::topic_store::{LogId, TopicStore};
+use crate::transport::{
+    BoxFuture, BoxStream, EphemeralHandle, PeerConnectionType, PeerInfo, SyncEvent, SyncHandle,
+    TopicHandle, Transport, TransportError,
+};
+
+/// Hubs of all in-memory networks of this process by network id.
+static HUBS: LazyLock<Mutex<HashMap<[u8; 32], Weak<MemoryHub>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_TRANSPORT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Transport which connects all nodes with the same network id within this process.
+///
+/// Messages never leave the process, so nodes sync deterministically without opening any
+/// sockets.
+pub struct MemoryTransport {
+    id: u64,
+    public_key: PublicKey,
+    stores: Stores,
+    hub: Arc<MemoryHub>,
+}
+
+impl MemoryTransport {
+    pub fn new(
+        network_id: &Hash,
+        public_key: PublicKey,
+        topic_store: &TopicStore,
+        operation_store: &OperationStore,
+    ) -> Self {
+        let hub = {
+            let mut hubs = HUBS.lock().unwrap();
+            hubs.retain(|_, hub| hub.strong_count() > 0);
+            if let Some(hub) = hubs.get(network_id.as_bytes()).and_then(Weak::upgrade) {
+                hub
+            } else {
+                let hub = Arc::new(MemoryHub::default());
+                hubs.insert(*network_id.as_bytes(), Arc::downgrade(&hub));
+                hub
+            }
+        };
+
+        let id = NEXT_TRANSPORT_ID.fetch_add(1, Ordering::Relaxed);
+        let stores = Stores {
+            public_key,
+            operation_store: operation_store.clone_inner(),
+            topic_store: topic_store.clone(),
+        };
+        hub.stores.lock().unwrap().insert(id, stores.clone());
+
+        Self {
+            id,
+            public_key,
+            stores,
+            hub,
+        }
+    }
+}
+
+impl Drop for MemoryTransport {
+    fn drop(&mut self) {
+        self.hub.stores.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl std::fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryTransport")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn sync_stream(&self, id: TopicId) -> BoxFuture<'_, Result<SyncHandle, TransportError>> {
+        Box::pin(async move {
+            let topic = MemoryTopic::new(self.id, self.public_key, id, &self.hub.sync_topics);
+            let others = self.hub.sync_topics.join(&topic);
+
+            // Sync up the operations which were created before the topic was joined, in both
+            // directions.
+            let operations = self.stores.operations(&id).await?;
+            for other in others {
+                let stores = self
+                    .hub
+                    .stores
+                    .lock()
+                    .unwrap()
+                    .get(&other.transport)
+                    .cloned();
+                let Some(stores) = stores else {
+                    continue;
+                };
+
+                topic.sync(other.peer, stores.operations(&id).await?);
+                other.sync(self.public_key, operations.clone());
+            }
+
+            Ok(topic as SyncHandle)
+        })
+    }
+
+    fn ephemeral_stream(
+        &self,
+        id: TopicId,
+    ) -> BoxFuture<'_, Result<EphemeralHandle, TransportError>> {
+        Box::pin(async move {
+            let topic = MemoryTopic::new(self.id, self.public_key, id, &self.hub.ephemeral_topics);
+            self.hub.ephemeral_topics.join(&topic);
+
+            Ok(topic as EphemeralHandle)
+        })
+    }
+
+    fn peers(&self) -> BoxFuture<'_, Result<Vec<PeerInfo>, TransportError>> {
+        Box::pin(async move {
+            let peers = self
+                .hub
+                .stores
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(id, _)| **id != self.id)
+                .map(|(_, stores)| PeerInfo {
+                    id: stores.public_key,
+                    connection_type: PeerConnectionType::InMemory,
+                    addresses: Vec::new(),
+                    round_trip_time: None,
+                    topics: Vec::new(),
+                })
+                .collect();
+
+            Ok(peers)
+        })
+    }
+}
+
+#[derive(Default)]
+struct MemoryHub {
+    /// Stores of all transports connected to the hub by transport id.
+    stores: Mutex<HashMap<u64, Stores>>,
+    sync_topics: Arc<Topics<SyncEvent>>,
+    ephemeral_topics: Arc<Topics<Vec<u8>>>,
+}
+
+#[derive(Clone)]
+struct Stores {
+    /// Public key of the node the stores belong to.
+    public_key: PublicKey,
+    operation_store: SqliteStore<LogId, ReflectionExtensions>,
+    topic_store: TopicStore,
+}
+
+impl Stores {
+    async fn operations(
+        &self,
+        id: &TopicId,
+    ) -> Result<Vec<Operation<ReflectionExtensions>>, TransportError> {
+        Ok(self
+            .topic_store
+            .operations_for_sync(&self.operation_store, id)
+            .await?)
+    }
+}
+
+/// All joined topics of one kind by topic id.
+struct Topics<T>(Mutex<HashMap<TopicId, Vec<Weak<MemoryTopic<T>>>>>);
+
+impl<T> Default for Topics<T> {
+    fn default() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+}
+
+impl<T> Topics<T> {
+    /// Adds `topic` and returns the topics other transports joined with the same id.
+    fn join(&self, topic: &Arc<MemoryTopic<T>>) -> Vec<Arc<MemoryTopic<T>>> {
+        let others = self.others(topic);
+        self.0
+            .lock()
+            .unwrap()
+            .entry(topic.id)
+            .or_default()
+            .push(Arc::downgrade(topic));
+
+        others
+    }
+
+    /// The topics other transports joined with the same id as `topic`.
+    fn others(&self, topic: &MemoryTopic<T>) -> Vec<Arc<MemoryTopic<T>>> {
+        let mut topics = self.0.lock().unwrap();
+        let Some(list) = topics.get_mut(&topic.id) else {
+            return Vec::new();
+        };
+
+        list.retain(|other| other.strong_count() > 0);
+        list.iter()
+            .filter_map(Weak::upgrade)
+            .filter(|other| other.transport != topic.transport)
+            .collect()
+    }
+}
+
+struct MemoryTopic<T> {
+    /// Id of the transport which joined the topic.
+    transport: u64,
+    /// Public key of the node which joined the topic.
+    peer: PublicKey,
+    id: TopicId,
+    topics: Arc<Topics<T>>,
+    tx: mpsc::UnboundedSender<T>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<T>>>,
+}
+
+impl<T> MemoryTopic<T> {
+    fn new(transport: u64, peer: PublicKey, id: TopicId, topics: &Arc<Topics<T>>) -> Arc<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Arc::new(Self {
+            transport,
+            peer,
+            id,
+            topics: topics.clone(),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        })
+    }
+
+    fn deliver(&self, message: T) {
+        // The subscriber may be gone already, the topic will be dropped soon then as well
+        let _ = self.tx.send(message);
+    }
+
+    fn take_stream(&self) -> Result<BoxStream<T>, TransportError>
+    where
+        T: Send + 'static,
+    {
+        let rx = self
+            .rx
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(TransportError::AlreadySubscribed)?;
+
+        Ok(Box::pin(UnboundedReceiverStream::new(rx)))
+    }
+}
+
+impl MemoryTopic<SyncEvent> {
+    /// Deliver all `operations` of `peer` as one sync session.
+    fn sync(&self, peer: PublicKey, operations: Vec<Operation<ReflectionExtensions>>) {
+        self.deliver(SyncEvent::Started { peer });
+        for operation in operations {
+            self.deliver(SyncEvent::Operation { peer, operation });
+        }
+        self.deliver(SyncEvent::Completed { peer });
+    }
+}
+
+impl TopicHandle<Operation<ReflectionExtensions>, SyncEvent> for MemoryTopic<SyncEvent> {
+    fn publish(
+        &self,
+        operation: Operation<ReflectionExtensions>,
+    ) -> BoxFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            for other in self.topics.others(self) {
+                other.deliver(SyncEvent::Operation {
+                    peer: self.peer,
+                    operation: operation.clone(),
+                });
+            }
+
+            Ok(())
+        })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<SyncEvent>, TransportError>> {
+        Box::pin(async move { self.take_stream() })
+    }
+}
+
+impl TopicHandle<Vec<u8>> for MemoryTopic<Vec<u8>> {
+    fn publish(&self, bytes: Vec<u8>) -> BoxFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            for other in self.topics.others(self) {
+                other.deliver(bytes.clone());
+            }
+
+            Ok(())
+        })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<Vec<u8>>, TransportError>> {
+        Box::pin(async move { self.take_stream() })
+    }
+}
@@ -1,37 +1,28 @@
-use std::sync::LazyLock;
+use std::net::IpAddr;
+use std::sync::Arc;
 
-use p2panda_net::Discovery;
 use p2panda_net::discovery::DiscoveryError;
+use p2panda_net::{Discovery, TopicId};
+use p2panda_sync::protocols::TopicLogSyncEvent as Event;
 use thiserror::Error;
-use tracing::error;
+use tokio_stream::StreamExt;
+use tracing::{error, warn};
 
-use p2panda_core::Hash;
 use p2panda_core::PrivateKey;
+use p2panda_core::{Hash, Operation};
 use p2panda_net::address_book::{AddressBook, AddressBookError};
-use p2panda_net::addrs::NodeInfo;
-use p2panda_net::gossip::{Gossip, GossipError};
-use p2panda_net::iroh_endpoint::{Endpoint, EndpointAddr, EndpointError, RelayUrl};
+use p2panda_net::gossip::{Gossip, GossipError, GossipHandle};
+use p2panda_net::iroh_endpoint::{ConnectionType, Endpoint, EndpointError};
 use p2panda_net::iroh_mdns::{MdnsDiscovery, MdnsDiscoveryError, MdnsDiscoveryMode};
 
+use crate::config::NodeConfig;
 use crate::operation::ReflectionExtensions;
 use crate::operation_store::OperationStore;
 use crate::topic_store::{LogId, TopicStore};
-
-static RELAY_URL: LazyLock<RelayUrl> = LazyLock::new(|| {
-    "https://euc1-1.relay.n0.iroh-canary.iroh.link"
-        .parse()
-        .expect("valid relay URL")
-});
-
-static BOOTSTRAP_NODE: LazyLock<NodeInfo> = LazyLock::new(|| {
-    let endpoint_addr = EndpointAddr::new(
-        "9f63a15ab95959a992af96bf72fbc3e7dc98eeb4799f788bb07b20125053e795"
-            .parse()
-            .expect("valid bootstrap node id"),
-    )
-    .with_relay_url(RELAY_URL.clone());
-    NodeInfo::from(endpoint_addr).bootstrap()
-});
+use crate::transport::{
+    BoxFuture, BoxStream, EphemeralHandle, PeerConnectionType, PeerInfo, SyncEvent, SyncHandle,
+    TopicHandle, Transport, TransportError,
+};
 
 pub type LogSync = p2panda_net::sync::LogSync<
     p2panda_store::SqliteStore<LogId, ReflectionExtensions>,
@@ -40,6 +31,8 @@ pub type LogSync = p2panda_net::sync::LogSync<
     TopicStore,
 >;
 pub type LogSyncError = p2panda_net::sync::LogSyncError<ReflectionExtensions>;
+pub type LogSyncHandle =
+    p2panda_net::sync::SyncHandle<Operation<ReflectionExtensions>, Event<ReflectionExtensions>>;
 
 #[derive(Error, Debug)]
 pub enum NetworkError {
@@ -55,12 +48,17 @@ pub enum NetworkError {
     Discovery(#[from] DiscoveryError),
     #[error(transparent)]
     Endpoint(#[from] EndpointError),
+    #[error("No sync directory configured")]
+    NoSyncDirectory,
+    #[error("Syncing via Bluetooth isn't supported yet")]
+    BluetoothUnsupported,
 }
 
 #[allow(dead_code)]
 pub struct Network {
-    pub(crate) mdns_discovery: MdnsDiscovery,
-    pub(crate) discovery: Discovery,
+    pub(crate) address_book: AddressBook,
+    pub(crate) mdns_discovery: Option<MdnsDiscovery>,
+    pub(crate) discovery: Option<Discovery>,
     pub(crate) gossip: Gossip,
     pub(crate) log_sync: LogSync,
     pub(crate) endpoint: Endpoint,
@@ -74,38 +72,58 @@ impl std::fmt::Debug for Network {
 }
 
 impl Network {
+    /// Spawn the network of a node.
+    ///
+    /// If `local_only` is set only peers in the local network are discovered via mDNS, no
+    /// traffic is sent to relays or bootstrap nodes.
     pub async fn new(
         private_key: &PrivateKey,
         network_id: &Hash,
         topic_store: &TopicStore,
         operation_store: &OperationStore,
+        config: &NodeConfig,
+        local_only: bool,
     ) -> Result<Self, NetworkError> {
         let address_book = AddressBook::builder().spawn().await?;
 
-        if cfg!(not(any(test, feature = "test_utils")))
-            && let Err(error) = address_book.insert_node_info(BOOTSTRAP_NODE.clone()).await
-        {
-            error!("Failed to add bootstrap node to the address book: {error}");
+        if !local_only {
+            for node_info in config.bootstrap_nodes() {
+                if let Err(error) = address_book.insert_node_info(node_info.clone()).await {
+                    error!("Failed to add bootstrap node to the address book: {error}");
+                }
+            }
         }
 
         let mut builder = Endpoint::builder(address_book.clone())
             .network_id(network_id.into())
             .private_key(private_key.clone());
 
-        if cfg!(not(any(test, feature = "test_utils"))) {
-            builder = builder.relay_url(RELAY_URL.clone());
+        if !local_only {
+            for relay_url in config.relay_urls() {
+                builder = builder.relay_url(relay_url.clone());
+            }
         }
 
         let endpoint = builder.spawn().await?;
 
-        let mdns_discovery = MdnsDiscovery::builder(address_book.clone(), endpoint.clone())
-            .mode(MdnsDiscoveryMode::Active)
-            .spawn()
-            .await?;
+        let mdns_discovery = if config.mdns() || local_only {
+            let mdns_discovery = MdnsDiscovery::builder(address_book.clone(), endpoint.clone())
+                .mode(MdnsDiscoveryMode::Active)
+                .spawn()
+                .await?;
+            Some(mdns_discovery)
+        } else {
+            None
+        };
 
-        let discovery = Discovery::builder(address_book.clone(), endpoint.clone())
-            .spawn()
-            .await?;
+        let discovery = if local_only {
+            None
+        } else {
+            let discovery = Discovery::builder(address_book.clone(), endpoint.clone())
+                .spawn()
+                .await?;
+            Some(discovery)
+        };
 
         let gossip = Gossip::builder(address_book.clone(), endpoint.clone())
             .spawn()
@@ -121,6 +139,7 @@ impl Network {
         .await?;
 
         Ok(Network {
+            address_book,
             mdns_discovery,
             discovery,
             gossip,
@@ -129,3 +148,151 @@ impl Network {
         })
     }
 }
+
+impl Transport for Network {
+    fn sync_stream(&self, id: TopicId) -> BoxFuture<'_, Result<SyncHandle, TransportError>> {
+        Box::pin(async move {
+            let handle = self.log_sync.stream(id, true).await?;
+            Ok(Arc::new(handle) as SyncHandle)
+        })
+    }
+
+    fn ephemeral_stream(
+        &self,
+        id: TopicId,
+    ) -> BoxFuture<'_, Result<EphemeralHandle, TransportError>> {
+        Box::pin(async move {
+            let handle = self.gossip.stream(id).await?;
+            Ok(Arc::new(handle) as EphemeralHandle)
+        })
+    }
+
+    fn peers(&self) -> BoxFuture<'_, Result<Vec<PeerInfo>, TransportError>> {
+        Box::pin(async move {
+            let this_node = self.endpoint.node_id();
+            let mut peers = Vec::new();
+
+            for node_info in self.address_book.all_node_infos().await? {
+                let id = node_info.node_id;
+                if id == this_node {
+                    continue;
+                }
+
+                // The address book knows all peers we discovered, the endpoint only the ones
+                // we were in contact with
+                let Some(remote_info) = self.endpoint.remote_info(id) else {
+                    peers.push(PeerInfo {
+                        id,
+                        connection_type: PeerConnectionType::None,
+                        addresses: Vec::new(),
+                        round_trip_time: None,
+                        topics: Vec::new(),
+                    });
+                    continue;
+                };
+
+                let connection_type = match remote_info.conn_type {
+                    ConnectionType::Direct(addr) if is_local_address(addr.ip()) => {
+                        PeerConnectionType::LocalNetwork
+                    }
+                    ConnectionType::Direct(_) => PeerConnectionType::Direct,
+                    ConnectionType::Relay(_) => PeerConnectionType::Relay,
+                    ConnectionType::Mixed(..) => PeerConnectionType::Mixed,
+                    ConnectionType::None => PeerConnectionType::None,
+                };
+
+                let mut addresses: Vec<String> = remote_info
+                    .addrs
+                    .iter()
+                    .map(|addr_info| addr_info.addr.to_string())
+                    .collect();
+                if let Some(relay_url_info) = remote_info.relay_url {
+                    addresses.push(relay_url_info.relay_url.to_string());
+                }
+
+                peers.push(PeerInfo {
+                    id,
+                    connection_type,
+                    addresses,
+                    round_trip_time: remote_info.latency,
+                    topics: Vec::new(),
+                });
+            }
+
+            Ok(peers)
+        })
+    }
+}
+
+/// Whether `ip` belongs to the local network, so the peer was most likely found via mDNS.
+fn is_local_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_link_local() || ip.is_loopback(),
+        IpAddr::V6(ip) => ip.is_unique_local() || ip.is_unicast_link_local() || ip.is_loopback(),
+    }
+}
+
+impl TopicHandle<Operation<ReflectionExtensions>, SyncEvent> for LogSyncHandle {
+    fn publish(
+        &self,
+        operation: Operation<ReflectionExtensions>,
+    ) -> BoxFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            LogSyncHandle::publish(self, operation).await?;
+            Ok(())
+        })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<SyncEvent>, TransportError>> {
+        Box::pin(async move {
+            let stream = LogSyncHandle::subscribe(self).await?.filter_map(|event| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(error) => {
+                        error!("Error while receiving sync message: {error}");
+                        return None;
+                    }
+                };
+                let peer = event.remote();
+                match event.event() {
+                    Event::Operation(operation) => Some(SyncEvent::Operation {
+                        peer,
+                        operation: operation.as_ref().to_owned(),
+                    }),
+                    Event::SyncStarted { .. } => Some(SyncEvent::Started { peer }),
+                    Event::SyncFinished { .. } => Some(SyncEvent::Completed { peer }),
+                    Event::Failed { .. } => {
+                        warn!("Sync with {peer} failed: {:?}", event.event());
+                        Some(SyncEvent::Failed { peer })
+                    }
+                    // Status updates and live mode don't change what we know about the
+                    // progress of the sync
+                    _ => None,
+                }
+            });
+            Ok(Box::pin(stream) as BoxStream<_>)
+        })
+    }
+}
+
+impl TopicHandle<Vec<u8>> for GossipHandle {
+    fn publish(&self, bytes: Vec<u8>) -> BoxFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            GossipHandle::publish(self, bytes).await?;
+            Ok(())
+        })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<Vec<u8>>, TransportError>> {
+        Box::pin(async move {
+            let stream = GossipHandle::subscribe(self).filter_map(|bytes| match bytes {
+                Ok(bytes) => Some(bytes.to_vec()),
+                Err(error) => {
+                    error!("Error while receiving ephemeral message: {error}");
+                    None
+                }
+            });
+            Ok(Box::pin(stream) as BoxStream<_>)
+        })
+    }
+}
@@ -1,17 +1,30 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use p2panda_core::{Hash, PrivateKey};
+use p2panda_core::{Hash, PrivateKey, PublicKey};
 use p2panda_net::TopicId;
 use thiserror::Error;
-use tracing::info;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{error, info};
 
+pub use crate::acl::{Acl, Role};
+pub use crate::bundle::BundleError;
+pub use crate::config::{ConfigError, NodeConfig, NodeConfigBuilder};
 use crate::network::NetworkError;
 use crate::node_inner::NodeInner;
-use crate::topic::{SubscribableTopic, Subscription, TopicError};
+use crate::topic::{SubscribableTopic, Subscription, TopicError, TopicSecret};
 pub use crate::topic_store::Author;
 use crate::topic_store::StoreTopic;
+use crate::transport::TransportError;
+pub use crate::transport::{PeerConnectionType, PeerInfo};
+
+/// Interval in which [`Node::peers_changed`] polls the peers for changes it isn't notified
+/// about, like new round-trip times or peers the transport discovered.
+const PEERS_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Error)]
 pub enum NodeError {
@@ -25,6 +38,8 @@ pub enum NodeError {
     DatebaseMigration(#[from] sqlx::migrate::MigrateError),
     #[error(transparent)]
     Network(#[from] NetworkError),
+    #[error(transparent)]
+    Transport(#[from] TransportError),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
@@ -33,14 +48,38 @@ pub enum ConnectionMode {
     None,
     Bluetooth,
     Network,
+    /// Only connect to peers in the local network, without using relays or bootstrap nodes.
+    LocalNetwork,
+    /// Only connect to other nodes with the same network id in this process.
+    InMemory,
+    /// Sync topics via the sync directory of the [`NodeConfig`], without any network connection.
+    Directory,
 }
 
 #[derive(Clone, Debug)]
 pub struct Topic<ID> {
     pub id: ID,
     pub name: Option<String>,
+    /// Secret shared between all members of the topic, `None` if the stored secret is
+    /// incomplete and the topic can't be read.
+    pub secret: Option<TopicSecret>,
     pub last_accessed: Option<DateTime<Utc>>,
+    /// Bytes stored with [`Subscription::set_bookmark`], if any.
+    pub bookmark: Option<Vec<u8>>,
     pub authors: Vec<Author>,
+    /// Access-control list of the topic, `None` if everyone can write to the topic.
+    pub acl: Option<Acl>,
+    /// Number of operations other authors added since we last unsubscribed from the topic.
+    ///
+    /// Syncing in the background doesn't count as seeing the changes.
+    pub unseen_changes: u64,
+}
+
+impl<ID> Topic<ID> {
+    /// Whether other authors changed the topic since we last unsubscribed from it.
+    pub fn has_unseen_changes(&self) -> bool {
+        self.unseen_changes > 0
+    }
 }
 
 #[derive(Debug)]
@@ -62,7 +101,7 @@ impl std::ops::Deref for OwnedRuntimeOrHandle {
 
 #[derive(Debug)]
 pub struct Node {
-    inner: Arc<NodeInner>,
+    pub(crate) inner: Arc<NodeInner>,
     runtime: OwnedRuntimeOrHandle,
 }
 
@@ -71,6 +110,7 @@ impl Node {
         private_key: PrivateKey,
         network_id: Hash,
         db_location: Option<&Path>,
+        config: NodeConfig,
     ) -> Result<Self, NodeError> {
         let runtime = if let Ok(handle) = tokio::runtime::Handle::try_current() {
             OwnedRuntimeOrHandle::Handle(handle)
@@ -84,7 +124,7 @@ impl Node {
 
         let db_file = db_location.map(|location| location.join("database.sqlite"));
         let inner = runtime
-            .spawn(async move { NodeInner::new(network_id, private_key, db_file).await })
+            .spawn(async move { NodeInner::new(network_id, private_key, db_file, config).await })
             .await??;
 
         Ok(Self {
@@ -105,6 +145,13 @@ impl Node {
         Ok(())
     }
 
+    /// Change the directory used in [`ConnectionMode::Directory`].
+    ///
+    /// Takes effect the next time the connection mode is set.
+    pub fn set_sync_directory(&self, directory: Option<PathBuf>) {
+        *self.inner.sync_directory.lock().unwrap() = directory;
+    }
+
     pub async fn shutdown(&self) -> Result<(), NodeError> {
         let inner_clone = self.inner.clone();
         self.runtime
@@ -116,11 +163,62 @@ impl Node {
         Ok(())
     }
 
+    /// All peers known via the current connection mode.
+    pub async fn peers(&self) -> Result<Vec<PeerInfo>, NodeError> {
+        let inner_clone = self.inner.clone();
+        let peers = self
+            .runtime
+            .spawn(async move { inner_clone.peers().await })
+            .await??;
+
+        Ok(peers)
+    }
+
+    /// Stream of the peers known via the current connection mode.
+    ///
+    /// The current peers are sent right away, afterwards they are sent whenever they changed.
+    /// Changes the node is notified about, like a new connection mode or a peer joining a
+    /// topic, are sent right away. The transports don't report new round-trip times or peers
+    /// they discovered, so the peers are also polled every [`PEERS_REFRESH_INTERVAL`] and only
+    /// sent when they differ from the last ones.
+    pub fn peers_changed(&self) -> impl Stream<Item = Vec<PeerInfo>> + Send + Unpin + use<> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let inner_clone = self.inner.clone();
+        self.runtime.spawn(async move {
+            let mut last_peers = None;
+
+            // Stop once the stream was dropped
+            while !tx.is_closed() {
+                // Don't miss changes while we are looking at the peers
+                let mut notified = std::pin::pin!(inner_clone.peers_notifier.notified());
+                notified.as_mut().enable();
+
+                match inner_clone.peers().await {
+                    Ok(peers) if last_peers.as_ref() != Some(&peers) => {
+                        if tx.send(peers.clone()).is_err() {
+                            break;
+                        }
+                        last_peers = Some(peers);
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        error!("Failed to get peers: {error}");
+                    }
+                }
+
+                // Not every change is reported, so poll again after a while
+                let _ = tokio::time::timeout(PEERS_REFRESH_INTERVAL, notified).await;
+            }
+        });
+
+        UnboundedReceiverStream::new(rx)
+    }
+
     pub async fn topics<ID: From<[u8; 32]>>(&self) -> Result<Vec<Topic<ID>>, TopicError> {
         let inner_clone = self.inner.clone();
         let topics = self
             .runtime
-            .spawn(async move { inner_clone.topic_store.topics().await })
+            .spawn(async move { inner_clone.topics().await })
             .await??;
 
         let topics = topics
@@ -129,14 +227,22 @@ impl Node {
                 let StoreTopic {
                     id,
                     name,
+                    secret,
                     last_accessed,
+                    bookmark,
                     authors,
+                    acl,
+                    unseen_changes,
                 } = topic;
                 Topic {
                     id: id.into(),
                     name,
+                    secret,
                     last_accessed,
+                    bookmark,
                     authors,
+                    acl,
+                    unseen_changes,
                 }
             })
             .collect();
@@ -144,25 +250,124 @@ impl Node {
         Ok(topics)
     }
 
-    pub async fn subscribe<ID: Into<[u8; 32]>, T: SubscribableTopic + 'static>(
+    /// Subscribe to the topic belonging to `secret`.
+    ///
+    /// The topic id is derived from the secret, see [`TopicSecret::id`], and all payloads of the
+    /// topic are encrypted with a key derived from it. Therefore the secret needs to be shared
+    /// with everyone who should be able to read the topic.
+    pub async fn subscribe<S: Into<TopicSecret>, T: SubscribableTopic + 'static>(
         &self,
-        id: ID,
+        secret: S,
         topic_handle: T,
     ) -> Result<Subscription<T>, TopicError> {
-        let id: TopicId = id.into();
+        self.subscribe_inner(secret.into(), topic_handle, false)
+            .await
+    }
+
+    /// Subscribe to the topic belonging to `secret` to keep it in sync in the background.
+    ///
+    /// Unlike [`Node::subscribe`] the stored operations aren't passed to `topic_handle`, it only
+    /// receives operations which are new to us. We also don't join the gossip of the topic, so
+    /// other authors don't see us as online and no ephemeral messages are received. A topic
+    /// shouldn't be subscribed to in the background and in the foreground at the same time.
+    pub async fn subscribe_in_background<S: Into<TopicSecret>, T: SubscribableTopic + 'static>(
+        &self,
+        secret: S,
+        topic_handle: T,
+    ) -> Result<Subscription<T>, TopicError> {
+        self.subscribe_inner(secret.into(), topic_handle, true)
+            .await
+    }
+
+    async fn subscribe_inner<T: SubscribableTopic + 'static>(
+        &self,
+        secret: TopicSecret,
+        topic_handle: T,
+        background: bool,
+    ) -> Result<Subscription<T>, TopicError> {
+        let id: TopicId = secret.id();
         let topic_handle = Arc::new(topic_handle);
         let inner_clone = self.inner.clone();
         let inner_subscription = self
             .runtime
-            .spawn(async move { inner_clone.subscribe(id, topic_handle).await })
+            .spawn(async move {
+                inner_clone
+                    .subscribe(id, secret, topic_handle, background)
+                    .await
+            })
             .await??;
 
         let subscription = Subscription::new(self.runtime.clone(), inner_subscription).await;
-        info!("Subscribed to topic {}", hex::encode(id));
+        if background {
+            info!("Subscribed to topic {} in the background", hex::encode(id));
+        } else {
+            info!("Subscribed to topic {}", hex::encode(id));
+        }
 
         Ok(subscription)
     }
 
+    /// The payloads of all stored operations of the topic belonging to `secret`, with their
+    /// authors.
+    ///
+    /// Unlike [`Node::subscribe`] this doesn't join the topic, it only reads what we know
+    /// already, e.g. to look at a topic without announcing ourselves to the other authors.
+    pub async fn topic_content<S: Into<TopicSecret>>(
+        &self,
+        secret: S,
+    ) -> Result<Vec<(PublicKey, Vec<u8>)>, TopicError> {
+        let secret = secret.into();
+        let id: TopicId = secret.id();
+        let inner_clone = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner_clone.topic_content(id, secret).await })
+            .await?
+    }
+
+    /// The payload of the latest snapshot of every author of the topic belonging to `secret`.
+    ///
+    /// Like [`Node::topic_content`] this doesn't join the topic. Since a snapshot contains
+    /// everything its author knew at the time, this is enough to get an overview of a topic
+    /// without reading all of its operations.
+    pub async fn latest_snapshots<S: Into<TopicSecret>>(
+        &self,
+        secret: S,
+    ) -> Result<Vec<(PublicKey, Vec<u8>)>, TopicError> {
+        let secret = secret.into();
+        let id: TopicId = secret.id();
+        let inner_clone = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner_clone.latest_snapshots(id, secret).await })
+            .await?
+    }
+
+    /// Export all operations of the topic `id` into a signed bundle.
+    ///
+    /// The bundle doesn't contain the secret of the topic, it's needed again to import it, see
+    /// [`Node::import_topic`].
+    pub async fn export_topic<ID: Into<[u8; 32]>>(&self, id: ID) -> Result<Vec<u8>, BundleError> {
+        let id: TopicId = id.into();
+        let inner_clone = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner_clone.export_topic(id).await })
+            .await?
+    }
+
+    /// Import a bundle created with [`Node::export_topic`] for the topic with `secret`.
+    ///
+    /// Subscriptions to the topic which already exist don't receive the imported operations,
+    /// subscribe again to receive them.
+    pub async fn import_topic(
+        &self,
+        bundle: Vec<u8>,
+        secret: TopicSecret,
+    ) -> Result<(), BundleError> {
+        let inner_clone = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner_clone.import_topic(&bundle, secret).await })
+            .await?
+    }
+
     pub async fn delete_topic<ID: Into<[u8; 32]>>(&self, id: ID) -> Result<(), TopicError> {
         let id: TopicId = id.into();
         let inner_clone = self.inner.clone();
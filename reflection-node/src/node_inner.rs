@@ -1,21 +1,31 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use crate::bundle::{BundleError, TopicBundle};
+use crate::config::NodeConfig;
+use crate::directory_transport::DirectoryTransport;
+use crate::encryption::{Keyring, TopicKey};
 use crate::ephemerial_operation::EphemerialOperation;
+use crate::memory_transport::MemoryTransport;
 use crate::network::{Network, NetworkError};
 use crate::node::{ConnectionMode, NodeError};
+use crate::operation::{LogType, ReflectionExtensions};
 use crate::operation_store::OperationStore;
-use crate::subscription_inner::SubscriptionInner;
-use crate::topic::{SubscribableTopic, TopicError};
-use crate::topic_store::TopicStore;
+use crate::subscription_inner::{SubscriptionInner, check_operation, handle_ingested_operation};
+use crate::topic::{SubscribableTopic, TopicError, TopicSecret};
+use crate::topic_store::{StoreTopic, TopicStore};
+use crate::transport::{PeerInfo, Transport, TransportError};
 use crate::utils::CombinedMigrationSource;
 
-use p2panda_core::{Hash, PrivateKey};
+use p2panda_core::{Hash, PrivateKey, PublicKey};
 use p2panda_net::TopicId;
 use p2panda_store::sqlite::store::migrations as operation_store_migrations;
+use p2panda_stream::IngestExt;
 use sqlx::{migrate::Migrator, sqlite};
 use tokio::sync::{Notify, RwLock};
-use tracing::info;
+use tokio_stream::StreamExt;
+use tracing::{error, info, warn};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) enum MessageType {
@@ -29,8 +39,16 @@ pub struct NodeInner {
     pub(crate) topic_store: TopicStore,
     pub(crate) private_key: PrivateKey,
     pub(crate) network_id: Hash,
-    pub(crate) network: RwLock<Option<Network>>,
+    pub(crate) config: NodeConfig,
+    pub(crate) network: RwLock<Option<Box<dyn Transport>>>,
     pub(crate) network_notifier: Notify,
+    /// Topics we share with each online peer, as reported by the author trackers.
+    pub(crate) peer_topics: Mutex<HashMap<PublicKey, HashSet<TopicId>>>,
+    /// Notified when the peers changed, e.g. because the connection mode changed or a peer
+    /// joined a topic.
+    pub(crate) peers_notifier: Notify,
+    /// Directory used in [`ConnectionMode::Directory`], initially the one of the config.
+    pub(crate) sync_directory: Mutex<Option<PathBuf>>,
 }
 
 impl NodeInner {
@@ -38,6 +56,7 @@ impl NodeInner {
         network_id: Hash,
         private_key: PrivateKey,
         db_file: Option<PathBuf>,
+        config: NodeConfig,
     ) -> Result<Self, NodeError> {
         let connection_options = sqlx::sqlite::SqliteConnectOptions::new()
             .shared_cache(true)
@@ -65,14 +84,19 @@ impl NodeInner {
 
         let operation_store = OperationStore::new(pool.clone());
         let topic_store = TopicStore::new(pool);
+        topic_store.seed_seen(&operation_store).await?;
 
         Ok(Self {
             operation_store,
             topic_store,
             private_key,
             network_id,
+            sync_directory: Mutex::new(config.sync_directory.clone()),
+            config,
             network: RwLock::new(None),
             network_notifier: Notify::new(),
+            peer_topics: Mutex::new(HashMap::new()),
+            peers_notifier: Notify::new(),
         })
     }
 
@@ -91,21 +115,52 @@ impl NodeInner {
                 *network_guard = None;
             }
             ConnectionMode::Bluetooth => {
-                unimplemented!("Bluetooth is currently not implemented")
+                // Stay offline instead of keeping the previous connection
+                *network_guard = None;
+                drop(network_guard);
+                self.peers_notifier.notify_waiters();
+                return Err(NetworkError::BluetoothUnsupported);
             }
-            ConnectionMode::Network => {
+            ConnectionMode::Network | ConnectionMode::LocalNetwork => {
                 let network = Network::new(
                     &self.private_key,
                     &self.network_id,
                     &self.topic_store,
                     &self.operation_store,
+                    &self.config,
+                    connection_mode == ConnectionMode::LocalNetwork,
                 )
                 .await?;
 
-                *network_guard = Some(network);
+                *network_guard = Some(Box::new(network));
+            }
+            ConnectionMode::InMemory => {
+                let transport = MemoryTransport::new(
+                    &self.network_id,
+                    self.private_key.public_key(),
+                    &self.topic_store,
+                    &self.operation_store,
+                );
+
+                *network_guard = Some(Box::new(transport));
+            }
+            ConnectionMode::Directory => {
+                let directory = self
+                    .sync_directory
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .ok_or(NetworkError::NoSyncDirectory)?;
+                let transport =
+                    DirectoryTransport::new(directory, &self.topic_store, &self.operation_store);
+
+                *network_guard = Some(Box::new(transport));
             }
         }
 
+        drop(network_guard);
+        self.peers_notifier.notify_waiters();
+
         Ok(())
     }
 
@@ -115,30 +170,233 @@ impl NodeInner {
         self.network.write().await.take();
     }
 
+    pub async fn peers(&self) -> Result<Vec<PeerInfo>, TransportError> {
+        let network_guard = self.network.read().await;
+        let Some(network) = network_guard.as_ref() else {
+            return Ok(Vec::new());
+        };
+
+        let mut peers = network.peers().await?;
+        let peer_topics = self.peer_topics.lock().unwrap();
+        for peer in &mut peers {
+            if let Some(topics) = peer_topics.get(&peer.id) {
+                peer.topics = topics.iter().copied().collect();
+                peer.topics.sort();
+            }
+        }
+        peers.sort_by_key(|peer| *peer.id.as_bytes());
+
+        Ok(peers)
+    }
+
+    pub(crate) fn peer_joined_topic(&self, peer: PublicKey, id: TopicId) {
+        if peer != self.private_key.public_key() {
+            self.peer_topics
+                .lock()
+                .unwrap()
+                .entry(peer)
+                .or_default()
+                .insert(id);
+            self.peers_notifier.notify_waiters();
+        }
+    }
+
+    pub(crate) fn peer_left_topic(&self, peer: PublicKey, id: TopicId) {
+        let mut peer_topics = self.peer_topics.lock().unwrap();
+        if let Some(topics) = peer_topics.get_mut(&peer) {
+            topics.remove(&id);
+            if topics.is_empty() {
+                peer_topics.remove(&peer);
+            }
+            self.peers_notifier.notify_waiters();
+        }
+    }
+
+    /// All topics with the number of their unseen changes.
+    pub async fn topics(&self) -> sqlx::Result<Vec<StoreTopic>> {
+        let this_author = self.private_key.public_key();
+        let mut topics = self.topic_store.topics().await?;
+        for topic in &mut topics {
+            topic.unseen_changes = self
+                .topic_store
+                .unseen_changes(&self.operation_store, &topic.id, &this_author)
+                .await?;
+        }
+
+        Ok(topics)
+    }
+
     pub async fn subscribe<T: SubscribableTopic + 'static>(
         self: Arc<Self>,
         id: TopicId,
+        secret: TopicSecret,
         subscribable_topic: Arc<T>,
+        background: bool,
     ) -> Result<SubscriptionInner<T>, TopicError> {
-        self.topic_store.add_topic(&id).await?;
+        self.topic_store.add_topic(&id, &secret).await?;
+        let keyring = self.keyring(&id, &secret).await?;
         // Add ourselves as an author to the topic store.
         self.topic_store
             .add_author(&id, &self.private_key.public_key())
             .await?;
-        let stored_operations = self
-            .topic_store
-            .operations_for_topic(&self.operation_store, &id)
-            .await?;
 
-        for operation in stored_operations {
+        // In the background only new operations are of interest
+        if !background {
             // Send all stored operation bytes to the app,
             // it doesn't matter if the app already knows some or all of them
-            if let Some(body) = operation.body {
-                subscribable_topic.bytes_received(operation.header.public_key, body.to_bytes());
+            for (author, data) in self.stored_payloads(&id, &keyring).await? {
+                subscribable_topic.bytes_received(author, data);
+            }
+        }
+
+        Ok(SubscriptionInner::new(
+            self.clone(),
+            id,
+            keyring,
+            subscribable_topic,
+            background,
+        ))
+    }
+
+    /// Decrypted payloads of all stored operations of topic `id`, without subscribing to it.
+    pub async fn topic_content(
+        &self,
+        id: TopicId,
+        secret: TopicSecret,
+    ) -> Result<Vec<(PublicKey, Vec<u8>)>, TopicError> {
+        let keyring = self.keyring(&id, &secret).await?;
+        Ok(self.stored_payloads(&id, &keyring).await?)
+    }
+
+    /// Decrypted payloads of the latest snapshot of every author of topic `id`.
+    pub async fn latest_snapshots(
+        &self,
+        id: TopicId,
+        secret: TopicSecret,
+    ) -> Result<Vec<(PublicKey, Vec<u8>)>, TopicError> {
+        let keyring = self.keyring(&id, &secret).await?;
+        let operations = self
+            .topic_store
+            .latest_snapshots(&self.operation_store, &id)
+            .await?;
+
+        Ok(decrypt_payloads(operations, &keyring))
+    }
+
+    async fn stored_payloads(
+        &self,
+        id: &TopicId,
+        keyring: &Keyring,
+    ) -> sqlx::Result<Vec<(PublicKey, Vec<u8>)>> {
+        let operations = self
+            .topic_store
+            .operations_for_topic(&self.operation_store, id)
+            .await?;
+
+        Ok(decrypt_payloads(operations, keyring))
+    }
+
+    /// Export all operations of topic `id` we share with other nodes into a signed bundle.
+    pub async fn export_topic(&self, id: TopicId) -> Result<Vec<u8>, BundleError> {
+        if self.topic_store.secret(&id).await?.is_none() {
+            return Err(BundleError::UnknownTopic);
+        }
+        let operations = self
+            .topic_store
+            .operations_for_sync(self.operation_store.inner(), &id)
+            .await?;
+
+        TopicBundle::new(id, operations).to_bytes(&self.private_key)
+    }
+
+    /// Import a bundle created by [`Self::export_topic`] for the topic with `secret`.
+    ///
+    /// The operations of the bundle take the same path as operations received from the
+    /// network, so invalid operations and operations of authors without write permission are
+    /// dropped.
+    ///
+    /// Bundles are only accepted from the owner of the topic or an author of one of its
+    /// operations, who wasn't removed from the topic. Keys the topic was re-keyed with are
+    /// taken from the access-control list of the bundle when they were shared with us, other
+    /// members receive them from the owner once they sync.
+    pub async fn import_topic(&self, bytes: &[u8], secret: TopicSecret) -> Result<(), BundleError> {
+        let (bundle, exported_by) = TopicBundle::from_bytes(bytes)?;
+        let id = secret.id();
+        if bundle.id != id {
+            return Err(BundleError::WrongTopic);
+        }
+        let operations: Vec<_> = bundle.operations().collect();
+
+        let exported_by_owner = secret.owner() == Some(exported_by);
+        let exported_by_author = operations.iter().any(|operation| {
+            operation.header.public_key == exported_by && operation.header.verify()
+        });
+        if !exported_by_owner && !exported_by_author {
+            return Err(BundleError::UnknownExporter);
+        }
+        if let Some(acl) = self.topic_store.acl(&id).await? {
+            if acl.role(&exported_by).is_none() {
+                return Err(BundleError::UnknownExporter);
+            }
+        }
+
+        self.topic_store.add_topic(&id, &secret).await?;
+        let keyring = self.keyring(&id, &secret).await?;
+
+        // Apply the access-control list first, so the write permissions of the content are
+        // checked against it
+        let (acl_operations, content_operations): (Vec<_>, Vec<_>) = operations
+            .into_iter()
+            .partition(|operation| operation.header.extensions.log_type == LogType::Acl);
+
+        for operations in [acl_operations, content_operations] {
+            let mut checked_operations = Vec::with_capacity(operations.len());
+            for operation in operations {
+                if let Some(operation) = check_operation(self, id, operation).await {
+                    checked_operations.push(operation);
+                }
+            }
+
+            let mut stream = tokio_stream::iter(checked_operations)
+                .ingest(self.operation_store.clone_inner(), 128)
+                .filter_map(|result| match result {
+                    Ok(operation) => Some(operation),
+                    Err(err) => {
+                        error!("ingesting operation failed: {err}");
+                        None
+                    }
+                });
+
+            while let Some(operation) = stream.next().await {
+                // Removed authors are stored with the access-control list, the app learns about
+                // them when it subscribes to the topic
+                handle_ingested_operation(self, &id, &keyring, operation, |_| {}, |_, _| {}).await;
             }
         }
 
-        Ok(SubscriptionInner::new(self.clone(), id, subscribable_topic))
+        info!("Imported bundle for topic {}", hex::encode(id));
+
+        Ok(())
+    }
+
+    /// The keyring of topic `id`.
+    async fn keyring(&self, id: &TopicId, secret: &TopicSecret) -> sqlx::Result<Keyring> {
+        // The first key is derived from the secret, later ones were shared with us when the
+        // topic got re-keyed. Topics created before payloads were encrypted have no keys.
+        let mut keys: Vec<TopicKey> = secret
+            .secret()
+            .map(TopicKey::from_secret)
+            .into_iter()
+            .collect();
+        keys.extend(
+            self.topic_store
+                .topic_keys(id)
+                .await?
+                .into_iter()
+                .map(TopicKey::from_bytes),
+        );
+
+        Ok(Keyring::new(keys))
     }
 
     pub async fn delete_topic(self: Arc<Self>, id: TopicId) -> Result<(), TopicError> {
@@ -146,3 +404,27 @@ impl NodeInner {
         Ok(())
     }
 }
+
+/// The decrypted payloads of `operations` with their authors, operations which can't be
+/// decrypted are skipped.
+fn decrypt_payloads(
+    operations: Vec<p2panda_core::Operation<ReflectionExtensions>>,
+    keyring: &Keyring,
+) -> Vec<(PublicKey, Vec<u8>)> {
+    let mut payloads = Vec::new();
+    for operation in operations {
+        if let Some(body) = operation.body {
+            match keyring.decrypt(&body.to_bytes()) {
+                Ok(data) => payloads.push((operation.header.public_key, data)),
+                Err(error) => {
+                    warn!(
+                        "Failed to decrypt stored operation {}: {error}",
+                        operation.hash
+                    );
+                }
+            }
+        }
+    }
+
+    payloads
+}
@@ -28,7 +28,8 @@ pub struct ReflectionExtensions {
     ///
     /// We organise two logs per author per topic, one for "short lived" / ephemeral deltas
     /// (small text changes) and one for persisted snapshots (full topic history). These are two
-    /// distinct "log types".
+    /// distinct "log types". Additionally the owner of a topic maintains a third log containing
+    /// the access-control list of the topic.
     #[serde(rename = "t")]
     pub log_type: LogType,
 
@@ -42,6 +43,7 @@ pub enum LogType {
     Snapshot,
     #[default]
     Delta,
+    Acl,
 }
 
 impl Extension<PruneFlag> for ReflectionExtensions {
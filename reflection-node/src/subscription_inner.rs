@@ -1,16 +1,15 @@
+use std::collections::HashMap;
 use std::mem::take;
 use std::ops::{Deref, DerefMut, Drop};
 use std::sync::Arc;
 
-use chrono::Utc;
-use p2panda_core::Operation;
+use chrono::{DateTime, Utc};
 use p2panda_core::{
-    Body, Header,
+    Body, Header, Operation, PublicKey,
     cbor::{decode_cbor, encode_cbor},
 };
-use p2panda_net::{TopicId, gossip::GossipHandle};
+use p2panda_net::TopicId;
 use p2panda_stream::IngestExt;
-use p2panda_sync::protocols::TopicLogSyncEvent as Event;
 use tokio::{
     sync::{RwLock, mpsc},
     task::{AbortHandle, spawn},
@@ -18,25 +17,27 @@ use tokio::{
 use tokio_stream::{StreamExt, wrappers::ReceiverStream};
 use tracing::{error, info, warn};
 
+use crate::acl::{AclMessage, Role};
 use crate::author_tracker::{AuthorMessage, AuthorTracker};
+use crate::encryption::{Keyring, TopicKey};
 use crate::ephemerial_operation::EphemerialOperation;
-use crate::network::Network;
 use crate::node_inner::MessageType;
 use crate::node_inner::NodeInner;
 use crate::operation::{LogType, ReflectionExtensions};
 use crate::topic::{SubscribableTopic, SubscriptionError, TopicError};
-
-pub type SyncHandle =
-    p2panda_net::sync::SyncHandle<Operation<ReflectionExtensions>, Event<ReflectionExtensions>>;
+use crate::transport::{EphemeralHandle, SyncEvent, SyncHandle, Transport};
 
 pub struct SubscriptionInner<T> {
-    ephemeral_tx: RwLock<Option<GossipHandle>>,
+    ephemeral_tx: RwLock<Option<EphemeralHandle>>,
     tx: RwLock<Option<SyncHandle>>,
     pub(crate) node: Arc<NodeInner>,
     pub(crate) id: TopicId,
+    pub(crate) keyring: Keyring,
     pub(crate) subscribable_topic: Arc<T>,
     author_tracker: Arc<AuthorTracker<T>>,
     abort_handles: RwLock<Vec<AbortHandle>>,
+    /// Whether the topic is only kept in sync, without announcing ourselves to other authors.
+    background: bool,
 }
 
 impl<T> Drop for SubscriptionInner<T> {
@@ -48,16 +49,24 @@ impl<T> Drop for SubscriptionInner<T> {
 }
 
 impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
-    pub fn new(node: Arc<NodeInner>, id: TopicId, subscribable_topic: Arc<T>) -> Self {
-        let author_tracker = AuthorTracker::new(node.clone(), subscribable_topic.clone());
+    pub fn new(
+        node: Arc<NodeInner>,
+        id: TopicId,
+        keyring: Keyring,
+        subscribable_topic: Arc<T>,
+        background: bool,
+    ) -> Self {
+        let author_tracker = AuthorTracker::new(node.clone(), id, subscribable_topic.clone());
         SubscriptionInner {
             tx: RwLock::new(None),
             ephemeral_tx: RwLock::new(None),
             node,
             id,
+            keyring,
             abort_handles: RwLock::new(Vec::new()),
             subscribable_topic,
             author_tracker,
+            background,
         }
     }
 
@@ -71,15 +80,17 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
             if let Some(network) = network_guard.as_ref().unwrap().deref() {
                 match setup_network(
                     &self.node,
-                    network,
+                    network.as_ref(),
                     self.id,
+                    &self.keyring,
                     &self.subscribable_topic,
                     &self.author_tracker,
+                    !self.background,
                 )
                 .await
                 {
                     Ok((sync_handle, gossip_handle, abort_handles)) => {
-                        (Some(sync_handle), Some(gossip_handle), abort_handles)
+                        (Some(sync_handle), gossip_handle, abort_handles)
                     }
                     Err(error) => {
                         self.subscribable_topic.error(error);
@@ -110,6 +121,7 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
             teardown_network(
                 &self.id,
                 &self.author_tracker,
+                !self.background,
                 old_tx,
                 old_ephemeral_tx,
                 old_abort_handles,
@@ -124,15 +136,17 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
                 if let Some(network) = network_guard.as_ref().unwrap().deref() {
                     match setup_network(
                         &self.node,
-                        network,
+                        network.as_ref(),
                         self.id,
+                        &self.keyring,
                         &self.subscribable_topic,
                         &self.author_tracker,
+                        !self.background,
                     )
                     .await
                     {
                         Ok((sync_handle, gossip_handle, abort_handles)) => {
-                            (Some(sync_handle), Some(gossip_handle), abort_handles)
+                            (Some(sync_handle), gossip_handle, abort_handles)
                         }
                         Err(error) => {
                             self.subscribable_topic.error(error);
@@ -158,14 +172,22 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
         let ephemeral_tx = take(ephemeral_tx_guard.deref_mut());
         let abort_handles = take(abort_handles_guard.deref_mut());
 
-        self.node
-            .topic_store
-            .set_last_accessed_for_topic(&self.id, Some(Utc::now()))
-            .await?;
+        // Syncing in the background doesn't count as accessing the topic
+        if !self.background {
+            self.node
+                .topic_store
+                .set_last_accessed_for_topic(&self.id, Some(Utc::now()))
+                .await?;
+            self.node
+                .topic_store
+                .set_seen(&self.node.operation_store, &self.id)
+                .await?;
+        }
 
         teardown_network(
             &self.id,
             &self.author_tracker,
+            !self.background,
             tx,
             ephemeral_tx,
             abort_handles,
@@ -176,6 +198,7 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
     }
 
     pub async fn send_delta(&self, data: Vec<u8>) -> Result<(), TopicError> {
+        let data = self.keyring.encrypt(&data)?;
         let operation =
                 // Append one operation to our "ephemeral" delta log.
                 self.node.operation_store
@@ -206,6 +229,7 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
         //
         // Snapshots are not broadcasted on the gossip overlay as they would be
         // too large. Peers will sync them up when they join the topic.
+        let data = self.keyring.encrypt(&data)?;
         self.node
             .operation_store
             .create_operation(
@@ -241,6 +265,7 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
 
     pub async fn send_ephemeral(&self, data: Vec<u8>) -> Result<(), TopicError> {
         if let Some(ephemeral_tx) = self.ephemeral_tx.read().await.as_ref() {
+            let data = self.keyring.encrypt(&data)?;
             let operation = EphemerialOperation::new(data, &self.node.private_key);
             let bytes = encode_cbor(&MessageType::Ephemeral(operation))?;
             ephemeral_tx.publish(bytes).await?;
@@ -260,47 +285,266 @@ impl<T: SubscribableTopic + 'static> SubscriptionInner<T> {
 
         Ok(())
     }
+
+    pub async fn set_bookmark(&self, bookmark: Option<Vec<u8>>) -> Result<(), TopicError> {
+        self.node
+            .topic_store
+            .set_bookmark_for_topic(&self.id, bookmark)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Add `member` with the given role to this topic, or remove them if `role` is `None`.
+    ///
+    /// Only the owner of the topic can change its members.
+    pub async fn set_member_role(
+        &self,
+        member: PublicKey,
+        role: Option<Role>,
+    ) -> Result<(), TopicError> {
+        let owner = self.node.topic_store.owner(&self.id).await?;
+        if owner != Some(self.node.private_key.public_key()) {
+            return Err(TopicError::NotOwner);
+        }
+
+        self.send_acl_message(AclMessage::SetRole { member, role })
+            .await
+    }
+
+    /// Change the role of authors who aren't explicitly members of this topic.
+    ///
+    /// Only the owner of the topic can change the default role.
+    pub async fn set_default_role(&self, role: Option<Role>) -> Result<(), TopicError> {
+        let owner = self.node.topic_store.owner(&self.id).await?;
+        if owner != Some(self.node.private_key.public_key()) {
+            return Err(TopicError::NotOwner);
+        }
+
+        self.send_acl_message(AclMessage::SetDefaultRole { role })
+            .await
+    }
+
+    /// Remove `member` from this topic and re-key it.
+    ///
+    /// Only the owner of the topic can remove members. Operations the member writes afterwards
+    /// aren't ingested anymore and all future payloads are encrypted with a new key, what they
+    /// wrote before stays part of the topic. The new
+    /// key is shared with every other known author who still has access to the topic, authors
+    /// who join later get all keys from the owner once the owner learns about them.
+    pub async fn revoke(&self, member: PublicKey) -> Result<(), TopicError> {
+        self.set_member_role(member, None).await?;
+
+        let Some(acl) = self.node.topic_store.acl(&self.id).await? else {
+            return Err(TopicError::NotOwner);
+        };
+
+        let this_author = self.node.private_key.public_key();
+        let key = TopicKey::generate();
+        // The key is shared with ourselves as well, so it can be restored from the
+        // access-control list, e.g. when importing a bundle. Applying the message switches us to
+        // the new key only once everyone else can read it.
+        let mut keys = vec![(
+            this_author,
+            key.share_with(&self.node.private_key, &this_author)?,
+        )];
+        for author in self.node.topic_store.authors(&self.id).await? {
+            if author != this_author && acl.role(&author).is_some() {
+                keys.push((author, key.share_with(&self.node.private_key, &author)?));
+            }
+        }
+
+        self.send_acl_message(AclMessage::ShareKey { keys }).await
+    }
+
+    async fn send_acl_message(&self, message: AclMessage) -> Result<(), TopicError> {
+        let tx = self.tx.read().await;
+        send_acl_message(
+            &self.node,
+            &self.id,
+            &self.keyring,
+            tx.as_ref(),
+            message,
+            |member, removed_at| self.subscribable_topic.author_removed(member, removed_at),
+        )
+        .await
+    }
+}
+
+/// Create a message of the access-control list of topic `id`, apply it and publish it via `tx`.
+///
+/// `author_removed` is called when the message removed a member from the topic.
+async fn send_acl_message(
+    node: &NodeInner,
+    id: &TopicId,
+    keyring: &Keyring,
+    tx: Option<&SyncHandle>,
+    message: AclMessage,
+    author_removed: impl FnOnce(PublicKey, DateTime<Utc>),
+) -> Result<(), TopicError> {
+    // Members who joined after the topic was re-keyed need to read the access-control list to
+    // receive the new keys
+    let data = keyring.encrypt_with_initial_key(&message.to_bytes()?)?;
+    let operation = node
+        .operation_store
+        .create_operation(&node.private_key, LogType::Acl, *id, Some(&data), false)
+        .await?;
+
+    apply_acl_message(
+        node,
+        id,
+        keyring,
+        &operation.header,
+        &message,
+        author_removed,
+    )
+    .await?;
+
+    info!("{message} sent for topic with id {}", hex::encode(id));
+
+    if let Some(tx) = tx {
+        tx.publish(operation).await?;
+    }
+
+    Ok(())
+}
+
+/// Share the keys topic `id` was re-keyed with with `author`, who we learned about after the
+/// topic was re-keyed.
+///
+/// Only the owner shares keys, and only with authors who have access to the topic.
+async fn share_topic_keys(
+    node: &NodeInner,
+    id: &TopicId,
+    keyring: &Keyring,
+    tx: &SyncHandle,
+    author: PublicKey,
+) -> Result<(), TopicError> {
+    let Some(acl) = node.topic_store.acl(id).await? else {
+        return Ok(());
+    };
+    if acl.owner != node.private_key.public_key()
+        || acl.owner == author
+        || acl.role(&author).is_none()
+    {
+        return Ok(());
+    }
+
+    // Keys are shared from oldest to most recent, so the most recent key ends up last in the
+    // keyring of the author
+    for key in node.topic_store.topic_keys(id).await? {
+        let share = TopicKey::from_bytes(key).share_with(&node.private_key, &author)?;
+        let message = AclMessage::ShareKey {
+            keys: vec![(author, share)],
+        };
+        send_acl_message(node, id, keyring, Some(tx), message, |_, _| {}).await?;
+    }
+
+    Ok(())
+}
+
+/// Apply a message of the access-control list of topic `id`.
+///
+/// `author_removed` is called when the message removed a member from the topic.
+async fn apply_acl_message(
+    node: &NodeInner,
+    id: &TopicId,
+    keyring: &Keyring,
+    header: &Header<ReflectionExtensions>,
+    message: &AclMessage,
+    author_removed: impl FnOnce(PublicKey, DateTime<Utc>),
+) -> Result<(), TopicError> {
+    let author = &header.public_key;
+    let owner = node.topic_store.owner(id).await?;
+    match message {
+        AclMessage::SetDefaultRole { role } if owner.as_ref() == Some(author) => {
+            node.topic_store.set_default_role(id, *role).await?;
+        }
+        AclMessage::SetRole { member, role } if owner.as_ref() == Some(author) => {
+            let changed_at =
+                DateTime::from_timestamp(header.timestamp as i64, 0).unwrap_or_else(Utc::now);
+            node.topic_store
+                .set_member_role(id, member, *role, changed_at)
+                .await?;
+
+            if role.is_none() {
+                author_removed(*member, changed_at);
+            }
+        }
+        AclMessage::ShareKey { keys } if owner.as_ref() == Some(author) => {
+            let this_author = node.private_key.public_key();
+            let Some((_, share)) = keys.iter().find(|(member, _)| member == &this_author) else {
+                return Ok(());
+            };
+
+            let key = TopicKey::from_share(&node.private_key, author, share)?;
+            if !keyring.contains(&key) {
+                node.topic_store.add_topic_key(id, key.as_bytes()).await?;
+                keyring.add(key);
+                info!("Topic {} was re-keyed", hex::encode(id));
+            }
+        }
+        _ => {
+            warn!(
+                "Ignoring {message} by {author} for topic {}",
+                hex::encode(id)
+            );
+        }
+    }
+
+    Ok(())
 }
 
 async fn setup_network<T: SubscribableTopic + 'static>(
     node: &Arc<NodeInner>,
-    network: &Network,
+    network: &dyn Transport,
     id: TopicId,
+    keyring: &Keyring,
     subscribable_topic: &Arc<T>,
     author_tracker: &Arc<AuthorTracker<T>>,
-) -> Result<(SyncHandle, GossipHandle, Vec<AbortHandle>), SubscriptionError> {
+    announce: bool,
+) -> Result<(SyncHandle, Option<EphemeralHandle>, Vec<AbortHandle>), SubscriptionError> {
     let mut abort_handles = Vec::with_capacity(3);
 
-    let stream = network.log_sync.stream(id, true).await?;
-    let mut topic_rx = stream.subscribe().await?;
-    let topic_tx = stream;
+    let topic_tx = network.sync_stream(id).await?;
+    let mut topic_rx = topic_tx.subscribe().await?;
 
     let (persistent_tx, persistent_rx) =
         mpsc::channel::<(Header<ReflectionExtensions>, Option<Body>, Vec<u8>)>(128);
 
+    let node_clone = node.clone();
+    let subscribable_topic_clone = subscribable_topic.clone();
     let abort_handle = spawn(async move {
+        // Number of operations received from each peer we are currently syncing with
+        let mut sync_sessions = HashMap::new();
         while let Some(event) = topic_rx.next().await {
-            let event = match event {
-                Ok(event) => event,
-                Err(error) => {
-                    error!("Error while receiving sync message: {error}");
+            let operation = match event {
+                SyncEvent::Started { peer } => {
+                    sync_sessions.insert(peer, 0);
+                    subscribable_topic_clone.sync_started(peer);
                     continue;
                 }
-            };
-            match event.event() {
-                Event::Operation(operation) => {
-                    match validate_and_unpack(operation.as_ref().to_owned(), id) {
-                        Ok(data) => {
-                            persistent_tx.send(data).await.unwrap();
-                        }
-                        Err(err) => {
-                            error!("Failed to unpack operation: {err}");
-                        }
-                    }
+                SyncEvent::Completed { peer } => {
+                    sync_sessions.remove(&peer);
+                    subscribable_topic_clone.sync_completed(peer);
+                    continue;
                 }
-                _ => {
-                    // TODO: Handle sync events
+                SyncEvent::Failed { peer } => {
+                    sync_sessions.remove(&peer);
+                    subscribable_topic_clone.sync_failed(peer);
+                    continue;
                 }
+                SyncEvent::Operation { peer, operation } => {
+                    if let Some(operations) = sync_sessions.get_mut(&peer) {
+                        *operations += 1;
+                        subscribable_topic_clone.sync_progress(peer, *operations);
+                    }
+                    operation
+                }
+            };
+
+            if let Some(data) = check_operation(&node_clone, id, operation).await {
+                persistent_tx.send(data).await.unwrap();
             }
         }
     })
@@ -308,54 +552,64 @@ async fn setup_network<T: SubscribableTopic + 'static>(
 
     abort_handles.push(abort_handle);
 
-    let ephemeral_stream = network.gossip.stream(id).await?;
-    let mut ephemeral_rx = ephemeral_stream.subscribe();
-    let ephemeral_tx = ephemeral_stream;
+    // In the background we neither tell other authors about us nor follow them, so the gossip of
+    // the topic is only joined when announcing ourselves
+    let ephemeral_tx = if announce {
+        let ephemeral_tx = network.ephemeral_stream(id).await?;
+        let mut ephemeral_rx = ephemeral_tx.subscribe().await?;
 
-    author_tracker.set_topic_tx(Some(ephemeral_tx)).await;
+        author_tracker
+            .set_topic_tx(Some(ephemeral_tx.clone()))
+            .await;
 
-    let author_tracker_clone = author_tracker.clone();
-    let subscribable_topic_clone = subscribable_topic.clone();
-    let abort_handle = spawn(async move {
-        while let Some(bytes) = ephemeral_rx.next().await {
-            let bytes = match bytes {
-                Ok(bytes) => bytes,
-                Err(error) => {
-                    error!("Error while receiving ephemeral message: {error}");
-                    continue;
-                }
-            };
-            match decode_cbor(&bytes[..]) {
-                Ok(MessageType::Ephemeral(operation)) => {
-                    if let Some((author, body)) = operation.validate_and_unpack() {
-                        subscribable_topic_clone.ephemeral_bytes_received(author, body);
-                    } else {
-                        warn!("Got ephemeral operation with a bad signature");
-                    }
-                }
-                Ok(MessageType::AuthorEphemeral(operation)) => {
-                    if let Some((author, body)) = operation.validate_and_unpack() {
-                        match AuthorMessage::try_from(&body[..]) {
-                            Ok(message) => {
-                                author_tracker_clone.received(message, author).await;
+        let author_tracker_clone = author_tracker.clone();
+        let subscribable_topic_clone = subscribable_topic.clone();
+        let keyring_clone = keyring.clone();
+        let abort_handle = spawn(async move {
+            while let Some(bytes) = ephemeral_rx.next().await {
+                match decode_cbor(&bytes[..]) {
+                    Ok(MessageType::Ephemeral(operation)) => {
+                        if let Some((author, body)) = operation.validate_and_unpack() {
+                            match keyring_clone.decrypt(&body) {
+                                Ok(data) => {
+                                    subscribable_topic_clone.ephemeral_bytes_received(author, data);
+                                }
+                                Err(error) => {
+                                    warn!("Failed to decrypt ephemeral operation: {error}");
+                                }
                             }
-                            Err(error) => {
-                                warn!("Failed to deserialize AuthorMessage: {error}");
+                        } else {
+                            warn!("Got ephemeral operation with a bad signature");
+                        }
+                    }
+                    Ok(MessageType::AuthorEphemeral(operation)) => {
+                        if let Some((author, body)) = operation.validate_and_unpack() {
+                            match AuthorMessage::try_from(&body[..]) {
+                                Ok(message) => {
+                                    author_tracker_clone.received(message, author).await;
+                                }
+                                Err(error) => {
+                                    warn!("Failed to deserialize AuthorMessage: {error}");
+                                }
                             }
+                        } else {
+                            warn!("Got internal ephemeral operation with a bad signature");
                         }
-                    } else {
-                        warn!("Got internal ephemeral operation with a bad signature");
                     }
-                }
-                Err(err) => {
-                    error!("Failed to decode gossip message: {err}");
+                    Err(err) => {
+                        error!("Failed to decode gossip message: {err}");
+                    }
                 }
             }
-        }
-    })
-    .abort_handle();
+        })
+        .abort_handle();
 
-    abort_handles.push(abort_handle);
+        abort_handles.push(abort_handle);
+
+        Some(ephemeral_tx)
+    } else {
+        None
+    };
 
     let stream = ReceiverStream::new(persistent_rx);
 
@@ -381,55 +635,171 @@ async fn setup_network<T: SubscribableTopic + 'static>(
 
     let node = node.clone();
     let subscribable_topic_clone = subscribable_topic.clone();
+    let keyring_clone = keyring.clone();
+    let topic_tx_clone = topic_tx.clone();
     // Send checked and ingested operations for this topic to application layer.
     let abort_handle = spawn(async move {
         while let Some(operation) = stream.next().await {
-            // When we discover a new author we need to add them to our topic store.
-            if let Err(error) = node
-                .topic_store
-                .add_author(&id, &operation.header.public_key)
-                .await
-            {
-                error!("Can't store author to database: {error}");
+            let mut new_author = None;
+            let content = handle_ingested_operation(
+                &node,
+                &id,
+                &keyring_clone,
+                operation,
+                |author| new_author = Some(author),
+                |member, at| subscribable_topic_clone.author_removed(member, at),
+            )
+            .await;
+
+            if let Some((author, data)) = content {
+                subscribable_topic_clone.bytes_received(author, data);
             }
 
-            // Forward the payload up to the app.
-            if let Some(body) = operation.body {
-                subscribable_topic_clone
-                    .bytes_received(operation.header.public_key, body.to_bytes());
+            // Authors who join after the topic was re-keyed need the new keys
+            if let Some(author) = new_author {
+                let result =
+                    share_topic_keys(&node, &id, &keyring_clone, &topic_tx_clone, author).await;
+                if let Err(error) = result {
+                    error!("Failed to share topic keys with {author}: {error}");
+                }
             }
         }
     })
     .abort_handle();
 
     abort_handles.push(abort_handle);
-    let author_tracker_clone = author_tracker.clone();
-    let abort_handle = spawn(async move {
-        author_tracker_clone.spawn().await;
-    })
-    .abort_handle();
 
-    abort_handles.push(abort_handle);
+    if announce {
+        let author_tracker_clone = author_tracker.clone();
+        let abort_handle = spawn(async move {
+            author_tracker_clone.spawn().await;
+        })
+        .abort_handle();
 
-    info!("Network subscription set up for topic {}", hex::encode(id));
+        abort_handles.push(abort_handle);
+    }
 
-    let ephemeral_tx = network.gossip.stream(id).await?;
+    info!("Network subscription set up for topic {}", hex::encode(id));
 
     Ok((topic_tx, ephemeral_tx, abort_handles))
 }
 
+/// Check an operation of topic `id` before it gets ingested.
+///
+/// Returns `None` for invalid operations and operations of authors who aren't allowed to write,
+/// so they never get persisted.
+pub(crate) async fn check_operation(
+    node: &NodeInner,
+    id: TopicId,
+    operation: Operation<ReflectionExtensions>,
+) -> Option<OperationWithRawHeader> {
+    let data = match validate_and_unpack(operation, id) {
+        Ok(data) => data,
+        Err(err) => {
+            error!("Failed to unpack operation: {err}");
+            return None;
+        }
+    };
+
+    let (header, _, _) = &data;
+    let author = header.public_key;
+    let can_write = node
+        .topic_store
+        .can_write(&id, &author, header.extensions.log_type, header.timestamp)
+        .await;
+    match can_write {
+        Ok(true) => Some(data),
+        Ok(false) => {
+            warn!("Dropping operation by {author} without write permission");
+            None
+        }
+        Err(error) => {
+            error!("Failed to check write permission: {error}");
+            None
+        }
+    }
+}
+
+/// Handle an operation of topic `id` after it was ingested.
+///
+/// Changes to the access-control list are applied by the node itself, for all other operations
+/// the author and the decrypted content are returned so they can be forwarded to the app.
+///
+/// `author_added` is called when the operation is the first one we know of its author.
+pub(crate) async fn handle_ingested_operation(
+    node: &NodeInner,
+    id: &TopicId,
+    keyring: &Keyring,
+    operation: Operation<ReflectionExtensions>,
+    author_added: impl FnOnce(PublicKey),
+    author_removed: impl FnOnce(PublicKey, DateTime<Utc>),
+) -> Option<(PublicKey, Vec<u8>)> {
+    // When we discover a new author we need to add them to our topic store.
+    match node
+        .topic_store
+        .add_author(id, &operation.header.public_key)
+        .await
+    {
+        Ok(true) => author_added(operation.header.public_key),
+        Ok(false) => {}
+        Err(error) => {
+            error!("Can't store author to database: {error}");
+        }
+    }
+
+    let body = operation.body?;
+
+    let data = match keyring.decrypt(&body.to_bytes()) {
+        Ok(data) => data,
+        Err(error) => {
+            warn!("Failed to decrypt operation {}: {error}", operation.hash);
+            return None;
+        }
+    };
+
+    if operation.header.extensions.log_type != LogType::Acl {
+        return Some((operation.header.public_key, data));
+    }
+
+    let message = match AclMessage::try_from(&data[..]) {
+        Ok(message) => message,
+        Err(error) => {
+            warn!("Failed to deserialize AclMessage: {error}");
+            return None;
+        }
+    };
+
+    if let Err(error) = apply_acl_message(
+        node,
+        id,
+        keyring,
+        &operation.header,
+        &message,
+        author_removed,
+    )
+    .await
+    {
+        error!("Failed to apply {message}: {error}");
+    }
+
+    None
+}
+
 async fn teardown_network<T: SubscribableTopic + 'static>(
     id: &TopicId,
     author_tracker: &Arc<AuthorTracker<T>>,
+    announced: bool,
     tx: Option<SyncHandle>,
-    ephemeral_tx: Option<GossipHandle>,
+    ephemeral_tx: Option<EphemeralHandle>,
     abort_handles: Vec<AbortHandle>,
 ) {
     for handle in abort_handles {
         handle.abort();
     }
 
-    author_tracker.set_topic_tx(None).await;
+    if announced {
+        author_tracker.set_topic_tx(None).await;
+    }
 
     if tx.is_some() {
         info!(
@@ -441,7 +811,7 @@ async fn teardown_network<T: SubscribableTopic + 'static>(
     drop(ephemeral_tx);
 }
 
-type OperationWithRawHeader = (Header<ReflectionExtensions>, Option<Body>, Vec<u8>);
+pub(crate) type OperationWithRawHeader = (Header<ReflectionExtensions>, Option<Body>, Vec<u8>);
 
 #[derive(Debug, thiserror::Error)]
 pub enum UnpackError {
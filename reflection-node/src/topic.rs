@@ -1,19 +1,148 @@
 use std::sync::Arc;
 
+use crate::acl::Role;
+use crate::encryption::EncryptionError;
 use crate::operation::ReflectionExtensions;
 use crate::operation_store::CreationError;
 
-use crate::network::LogSyncError;
 use crate::subscription_inner::SubscriptionInner;
-use p2panda_core::{Operation, PublicKey};
+use crate::transport::TransportError;
+use chrono::{DateTime, Utc};
+use p2panda_core::identity::IdentityError;
+use p2panda_core::{Hash, Operation, PublicKey};
 use p2panda_sync::protocols::TopicLogSyncEvent;
+use serde::{Deserialize, Serialize};
 
-use p2panda_net::gossip::GossipError;
 use thiserror::Error;
-use tokio::sync::mpsc;
 use tokio::task::{AbortHandle, JoinError};
 use tracing::info;
 
+/// Domain separation for deriving the topic id from the topic secret.
+const TOPIC_ID_CONTEXT: &[u8] = b"reflection-topic-id-v2";
+
+/// Number of bytes of an encoded [`TopicSecret`].
+pub const TOPIC_SECRET_LENGTH: usize = 64;
+
+/// Number of bytes of an encoded [`TopicSecret`] of a topic created before payloads were
+/// encrypted, it's just the id of the topic.
+pub const LEGACY_TOPIC_SECRET_LENGTH: usize = 32;
+
+/// Secret shared between all members of a topic.
+///
+/// It consists of random bytes the topic key is derived from and the public key of the owner of
+/// the topic. Both are part of the topic id, so every member agrees on who owns the topic from
+/// the start, regardless of the order in which they receive messages, and nobody else can claim
+/// to own it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TopicSecret(SecretKind);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum SecretKind {
+    Shared {
+        secret: [u8; 32],
+        owner: PublicKey,
+    },
+    /// Topics created before payloads were encrypted only have an id. Their payloads stay
+    /// unencrypted and they have no owner, so everyone can write to them.
+    Legacy {
+        id: [u8; 32],
+    },
+}
+
+impl TopicSecret {
+    pub fn new(secret: [u8; 32], owner: PublicKey) -> Self {
+        Self(SecretKind::Shared { secret, owner })
+    }
+
+    /// The secret of a topic created before payloads were encrypted.
+    pub fn legacy(id: [u8; 32]) -> Self {
+        Self(SecretKind::Legacy { id })
+    }
+
+    /// Whether the topic was created before payloads were encrypted.
+    pub fn is_legacy(&self) -> bool {
+        matches!(self.0, SecretKind::Legacy { .. })
+    }
+
+    /// The random bytes the topic key is derived from, `None` for legacy topics.
+    pub fn secret(&self) -> Option<&[u8; 32]> {
+        match &self.0 {
+            SecretKind::Shared { secret, .. } => Some(secret),
+            SecretKind::Legacy { .. } => None,
+        }
+    }
+
+    /// The author who created the topic and is the only one who can change its members, `None`
+    /// for legacy topics.
+    pub fn owner(&self) -> Option<PublicKey> {
+        match self.0 {
+            SecretKind::Shared { owner, .. } => Some(owner),
+            SecretKind::Legacy { .. } => None,
+        }
+    }
+
+    /// Derives the id of the topic.
+    ///
+    /// Only the id is announced on the network. Since it's a one-way hash of the secret, peers
+    /// who observe the id can neither derive the secret nor join the topic.
+    pub fn id(&self) -> [u8; 32] {
+        match &self.0 {
+            SecretKind::Shared { secret, owner } => {
+                let hash = Hash::new(
+                    [
+                        TOPIC_ID_CONTEXT,
+                        secret.as_slice(),
+                        owner.as_bytes().as_slice(),
+                    ]
+                    .concat(),
+                );
+                *hash.as_bytes()
+            }
+            SecretKind::Legacy { id } => *id,
+        }
+    }
+
+    /// The secret as [`TOPIC_SECRET_LENGTH`] bytes, or [`LEGACY_TOPIC_SECRET_LENGTH`] bytes for
+    /// legacy topics.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match &self.0 {
+            SecretKind::Shared { secret, owner } => {
+                [secret.as_slice(), owner.as_bytes().as_slice()].concat()
+            }
+            SecretKind::Legacy { id } => id.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for TopicSecret {
+    type Error = IdentityError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        match bytes.len() {
+            TOPIC_SECRET_LENGTH => {
+                let (secret, owner) = bytes.split_at(32);
+                Ok(Self::new(
+                    secret.try_into().expect("secret to be 32 bytes"),
+                    PublicKey::try_from(owner)?,
+                ))
+            }
+            LEGACY_TOPIC_SECRET_LENGTH => {
+                Ok(Self::legacy(bytes.try_into().expect("id to be 32 bytes")))
+            }
+            len => Err(IdentityError::InvalidLength(len, TOPIC_SECRET_LENGTH)),
+        }
+    }
+}
+
+// Never print the secret by accident
+impl std::fmt::Debug for TopicSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopicSecret")
+            .field("owner", &self.owner())
+            .finish_non_exhaustive()
+    }
+}
+
 pub type SyncHandleError = p2panda_net::sync::SyncHandleError<
     Operation<ReflectionExtensions>,
     TopicLogSyncEvent<ReflectionExtensions>,
@@ -28,21 +157,19 @@ pub enum TopicError {
     #[error(transparent)]
     Encode(#[from] p2panda_core::cbor::EncodeError),
     #[error(transparent)]
-    Publish(#[from] SyncHandleError),
+    Encryption(#[from] EncryptionError),
     #[error(transparent)]
-    PublishEphemeral(#[from] mpsc::error::SendError<Vec<u8>>),
+    Publish(#[from] TransportError),
     #[error(transparent)]
     Runtime(#[from] JoinError),
+    #[error("Only the owner of a topic can change its members")]
+    NotOwner,
 }
 
 #[derive(Debug, Error)]
 pub enum SubscriptionError {
     #[error(transparent)]
-    Gossip(#[from] GossipError),
-    #[error(transparent)]
-    LogSync(#[from] LogSyncError),
-    #[error(transparent)]
-    SyncHandle(#[from] SyncHandleError),
+    Transport(#[from] TransportError),
 }
 
 pub trait SubscribableTopic: Sync + Send {
@@ -50,6 +177,14 @@ pub trait SubscribableTopic: Sync + Send {
     fn author_joined(&self, author: PublicKey);
     fn author_left(&self, author: PublicKey);
     fn ephemeral_bytes_received(&self, author: PublicKey, data: Vec<u8>);
+    fn author_removed(&self, author: PublicKey, removed_at: DateTime<Utc>);
+    /// A sync session with `peer` started.
+    fn sync_started(&self, peer: PublicKey);
+    /// `operations` were received from `peer` so far in the current sync session.
+    fn sync_progress(&self, peer: PublicKey, operations: u64);
+    /// All operations `peer` had when the sync session started were received.
+    fn sync_completed(&self, peer: PublicKey);
+    fn sync_failed(&self, peer: PublicKey);
     fn error(&self, error: SubscriptionError);
 }
 
@@ -127,4 +262,54 @@ impl<T: SubscribableTopic + 'static> Subscription<T> {
             .spawn(async move { inner.set_name(name).await })
             .await?
     }
+
+    /// Store opaque `bookmark` bytes with this topic
+    ///
+    /// The node doesn't interpret them, they are returned with the topic by [`Node::topics`],
+    /// e.g. to remember which state of the content was seen last.
+    ///
+    /// [`Node::topics`]: crate::node::Node::topics
+    pub async fn set_bookmark(&self, bookmark: Option<Vec<u8>>) -> Result<(), TopicError> {
+        let inner = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner.set_bookmark(bookmark).await })
+            .await?
+    }
+
+    /// Add `member` with the given role to this topic, or remove them if `role` is `None`
+    ///
+    /// Only the owner of the topic can change its members.
+    pub async fn set_member_role(
+        &self,
+        member: PublicKey,
+        role: Option<Role>,
+    ) -> Result<(), TopicError> {
+        let inner = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner.set_member_role(member, role).await })
+            .await?
+    }
+
+    /// Remove `member` from this topic and re-key it
+    ///
+    /// Only the owner of the topic can remove members. The member can't read content encrypted
+    /// after this call, however the caller should send a fresh snapshot afterwards to make sure
+    /// the current state of the topic is readable by all remaining members without relying on
+    /// old keys. Authors who join later receive the new key from the owner.
+    pub async fn revoke(&self, member: PublicKey) -> Result<(), TopicError> {
+        let inner = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner.revoke(member).await })
+            .await?
+    }
+
+    /// Change the role of authors who aren't explicitly members of this topic
+    ///
+    /// Only the owner of the topic can change the default role.
+    pub async fn set_default_role(&self, role: Option<Role>) -> Result<(), TopicError> {
+        let inner = self.inner.clone();
+        self.runtime
+            .spawn(async move { inner.set_default_role(role).await })
+            .await?
+    }
 }
@@ -4,15 +4,17 @@ use std::hash::Hash as StdHash;
 use chrono::{DateTime, Utc};
 use p2panda_core::PublicKey;
 use p2panda_net::TopicId;
-use p2panda_store::LogStore;
+use p2panda_store::{LogStore, SqliteStore};
 use p2panda_sync::protocols::Logs;
 use p2panda_sync::traits::TopicMap;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, Row};
 use tracing::error;
 
+use crate::acl::{Acl, Role};
 use crate::operation::{LogType, ReflectionExtensions};
 use crate::operation_store::OperationStore;
+use crate::topic::TopicSecret;
 
 #[derive(Debug, FromRow)]
 pub struct StoreTopic {
@@ -20,15 +22,27 @@ pub struct StoreTopic {
     pub id: TopicId,
     #[sqlx(default)]
     pub name: Option<String>,
+    #[sqlx(skip)]
+    pub secret: Option<TopicSecret>,
     pub last_accessed: Option<DateTime<Utc>>,
+    /// Opaque bytes the app stored with the topic, see [`TopicStore::set_bookmark_for_topic`].
+    #[sqlx(default)]
+    pub bookmark: Option<Vec<u8>>,
     #[sqlx(skip)]
     pub authors: Vec<Author>,
+    #[sqlx(skip)]
+    pub acl: Option<Acl>,
+    /// Number of operations other authors added since the topic was last seen.
+    #[sqlx(skip)]
+    pub unseen_changes: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct Author {
     pub public_key: PublicKey,
     pub last_seen: Option<DateTime<Utc>>,
+    /// When the author was removed from the topic, `None` if they weren't removed.
+    pub removed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone, Debug)]
@@ -41,7 +55,7 @@ impl TopicStore {
         Self { pool }
     }
 
-    async fn authors(&self, id: &TopicId) -> sqlx::Result<Vec<PublicKey>> {
+    pub async fn authors(&self, id: &TopicId) -> sqlx::Result<Vec<PublicKey>> {
         let list = sqlx::query("SELECT public_key FROM authors WHERE topic_id = ?")
             .bind(id.as_slice())
             .fetch_all(&self.pool)
@@ -55,12 +69,19 @@ impl TopicStore {
 
     pub async fn topics(&self) -> sqlx::Result<Vec<StoreTopic>> {
         let mut topics: Vec<StoreTopic> =
-            sqlx::query_as("SELECT id, name, last_accessed FROM topics")
+            sqlx::query_as("SELECT id, name, last_accessed, bookmark FROM topics")
                 .fetch_all(&self.pool)
                 .await?;
-        let authors = sqlx::query("SELECT public_key, topic_id, last_seen FROM authors")
-            .fetch_all(&self.pool)
-            .await?;
+        let authors = sqlx::query(
+            "
+            SELECT authors.public_key, authors.topic_id, authors.last_seen, members.removed_at
+            FROM authors
+            LEFT JOIN members
+            ON members.public_key = authors.public_key AND members.topic_id = authors.topic_id
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
         let mut authors_per_topic = authors.iter().fold(HashMap::new(), |mut acc, row| {
             let Ok(id) = TopicId::try_from(row.get::<&[u8], _>("topic_id")) else {
@@ -72,9 +93,13 @@ impl TopicStore {
             let Ok(last_seen) = row.try_get::<Option<DateTime<Utc>>, _>("last_seen") else {
                 return acc;
             };
+            let Ok(removed_at) = row.try_get::<Option<DateTime<Utc>>, _>("removed_at") else {
+                return acc;
+            };
             acc.entry(id).or_insert_with(Vec::new).push(Author {
                 public_key,
                 last_seen,
+                removed_at,
             });
             acc
         });
@@ -83,19 +108,79 @@ impl TopicStore {
             if let Some(authors) = authors_per_topic.remove(&topic.id) {
                 topic.authors = authors;
             }
+            topic.secret = self.secret(&topic.id).await?;
+            topic.acl = self.acl(&topic.id).await?;
         }
 
         Ok(topics)
     }
 
-    pub async fn add_topic(&self, id: &TopicId) -> sqlx::Result<()> {
-        // The id is the primary key in the table therefore ignore insertion when the topic exists already
+    pub async fn owner(&self, id: &TopicId) -> sqlx::Result<Option<PublicKey>> {
+        let owner: Option<Option<Vec<u8>>> =
+            sqlx::query_scalar("SELECT owner FROM topics WHERE id = ?")
+                .bind(id.as_slice())
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(owner
+            .flatten()
+            .and_then(|owner| PublicKey::try_from(owner.as_slice()).ok()))
+    }
+
+    /// The access-control list of the topic, `None` if the topic doesn't have one.
+    pub async fn acl(&self, id: &TopicId) -> sqlx::Result<Option<Acl>> {
+        let Some(row) = sqlx::query("SELECT owner, default_role FROM topics WHERE id = ?")
+            .bind(id.as_slice())
+            .fetch_optional(&self.pool)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let Some(owner) = row
+            .get::<Option<&[u8]>, _>("owner")
+            .and_then(|owner| PublicKey::try_from(owner).ok())
+        else {
+            return Ok(None);
+        };
+        let default_role = row
+            .get::<Option<&str>, _>("default_role")
+            .and_then(Role::parse);
+        let members = self.members(id).await?;
+        let removed_at = sqlx::query(
+            "
+            SELECT public_key, removed_at FROM members
+            WHERE topic_id = ? AND role IS NULL AND removed_at IS NOT NULL
+            ",
+        )
+        .bind(id.as_slice())
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .filter_map(|row| {
+            let public_key = PublicKey::try_from(row.get::<&[u8], _>("public_key")).ok()?;
+            let removed_at = row.try_get::<DateTime<Utc>, _>("removed_at").ok()?;
+            Some((public_key, removed_at))
+        })
+        .collect();
+
+        Ok(Some(Acl {
+            owner,
+            default_role,
+            members,
+            removed_at,
+        }))
+    }
+
+    pub async fn set_default_role(&self, id: &TopicId, role: Option<Role>) -> sqlx::Result<()> {
         sqlx::query(
             "
-            INSERT OR IGNORE INTO topics ( id )
-            VALUES ( ? )
+            UPDATE topics
+            SET default_role = ?
+            WHERE id = ?
             ",
         )
+        .bind(role.as_ref().map(Role::as_str))
         .bind(id.as_slice())
         .execute(&self.pool)
         .await?;
@@ -103,6 +188,156 @@ impl TopicStore {
         Ok(())
     }
 
+    pub async fn members(&self, id: &TopicId) -> sqlx::Result<HashMap<PublicKey, Option<Role>>> {
+        let list = sqlx::query("SELECT public_key, role FROM members WHERE topic_id = ?")
+            .bind(id.as_slice())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(list
+            .iter()
+            .filter_map(|row| {
+                let public_key = PublicKey::try_from(row.get::<&[u8], _>("public_key")).ok()?;
+                let role = row.get::<Option<&str>, _>("role").and_then(Role::parse);
+                Some((public_key, role))
+            })
+            .collect())
+    }
+
+    /// Set the role of a member, `changed_at` is stored as the time of removal if `role` is `None`.
+    pub async fn set_member_role(
+        &self,
+        id: &TopicId,
+        public_key: &PublicKey,
+        role: Option<Role>,
+        changed_at: DateTime<Utc>,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO members ( public_key, topic_id, role, removed_at )
+            VALUES ( ?, ?, ?, ? )
+            ON CONFLICT ( public_key, topic_id )
+            DO UPDATE SET role = excluded.role, removed_at = excluded.removed_at
+            ",
+        )
+        .bind(public_key.as_bytes().as_slice())
+        .bind(id.as_slice())
+        .bind(role.as_ref().map(Role::as_str))
+        .bind(role.is_none().then_some(changed_at))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whether `public_key` was removed from the topic.
+    pub async fn is_removed(&self, id: &TopicId, public_key: &PublicKey) -> sqlx::Result<bool> {
+        let removed: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM members WHERE public_key = ? AND topic_id = ? AND role IS NULL",
+        )
+        .bind(public_key.as_bytes().as_slice())
+        .bind(id.as_slice())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(removed.is_some())
+    }
+
+    /// The secret of the topic, `None` for unknown topics and topics whose stored secret is
+    /// incomplete.
+    ///
+    /// Topics created before payloads were encrypted don't have a secret, they get a legacy
+    /// secret which is just their id.
+    pub async fn secret(&self, id: &TopicId) -> sqlx::Result<Option<TopicSecret>> {
+        let Some(row) = sqlx::query("SELECT secret, owner FROM topics WHERE id = ?")
+            .bind(id.as_slice())
+            .fetch_optional(&self.pool)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let secret = row.get::<Option<&[u8]>, _>("secret");
+        if secret.is_none() {
+            return Ok(Some(TopicSecret::legacy(*id)));
+        }
+
+        Ok(topic_secret(secret, row.get::<Option<&[u8]>, _>("owner")))
+    }
+
+    /// Keys the topic was re-keyed with, ordered from oldest to most recent.
+    pub async fn topic_keys(&self, id: &TopicId) -> sqlx::Result<Vec<[u8; 32]>> {
+        let keys: Vec<Vec<u8>> = sqlx::query_scalar(
+            "SELECT key FROM topic_keys WHERE topic_id = ? ORDER BY created_at, rowid",
+        )
+        .bind(id.as_slice())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| key.try_into().ok())
+            .collect())
+    }
+
+    pub async fn add_topic_key(&self, id: &TopicId, key: &[u8; 32]) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT OR IGNORE INTO topic_keys ( topic_id, key, created_at )
+            VALUES ( ?, ?, ? )
+            ",
+        )
+        .bind(id.as_slice())
+        .bind(key.as_slice())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whether operations of the given log type authored by `public_key` at `timestamp` are
+    /// accepted.
+    ///
+    /// Topics without an access-control list accept operations from everyone.
+    pub async fn can_write(
+        &self,
+        id: &TopicId,
+        public_key: &PublicKey,
+        log_type: LogType,
+        timestamp: u64,
+    ) -> sqlx::Result<bool> {
+        Ok(self
+            .acl(id)
+            .await?
+            .is_none_or(|acl| acl.can_write_at(public_key, log_type, timestamp)))
+    }
+
+    pub async fn add_topic(&self, id: &TopicId, secret: &TopicSecret) -> sqlx::Result<()> {
+        // The id is the primary key in the table, topics which exist already only get the parts
+        // of the secret they are missing. The id is derived from the secret, so it can't be
+        // replaced by a different one. The owner is part of the secret, so everyone agrees on it
+        // from the start.
+        sqlx::query(
+            "
+            INSERT INTO topics ( id, secret, owner, default_role )
+            VALUES ( ?, ?, ?, ? )
+            ON CONFLICT(id) DO UPDATE SET
+                secret = COALESCE(secret, excluded.secret),
+                owner = COALESCE(owner, excluded.owner),
+                default_role = COALESCE(default_role, excluded.default_role)
+            ",
+        )
+        .bind(id.as_slice())
+        .bind(secret.secret().map(|secret| secret.as_slice()))
+        .bind(secret.owner().map(|owner| owner.as_bytes().to_vec()))
+        .bind(secret.owner().map(|_| Role::Write.as_str()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn delete_topic(&self, id: &TopicId) -> sqlx::Result<()> {
         sqlx::query("DELETE FROM topics WHERE id = ?")
             .bind(id.as_slice())
@@ -112,9 +347,10 @@ impl TopicStore {
         Ok(())
     }
 
-    pub async fn add_author(&self, id: &TopicId, public_key: &PublicKey) -> sqlx::Result<()> {
+    /// Add `public_key` as author of topic `id`, returns whether the author was new.
+    pub async fn add_author(&self, id: &TopicId, public_key: &PublicKey) -> sqlx::Result<bool> {
         // The author/id pair is required to be unique therefore ignore if the insertion fails
-        sqlx::query(
+        let result = sqlx::query(
             "
             INSERT OR IGNORE INTO authors ( public_key, topic_id )
             VALUES ( ?, ? )
@@ -125,7 +361,7 @@ impl TopicStore {
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
     pub async fn set_last_seen_for_author(
@@ -184,6 +420,28 @@ impl TopicStore {
         Ok(())
     }
 
+    /// Store opaque `bookmark` bytes with the topic, e.g. the state of the content which was
+    /// seen last.
+    pub async fn set_bookmark_for_topic(
+        &self,
+        id: &TopicId,
+        bookmark: Option<Vec<u8>>,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            UPDATE topics
+            SET bookmark = ?
+            WHERE id = ?
+            ",
+        )
+        .bind(bookmark)
+        .bind(id.as_slice())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn operations_for_topic(
         &self,
         operation_store: &OperationStore,
@@ -199,18 +457,36 @@ impl TopicStore {
 
         let mut result = Vec::new();
 
+        let acl = self.acl(id).await?;
+
         for author in authors.iter() {
+            // Content of authors who aren't allowed to write is never handed to the app, members
+            // who were removed keep what they wrote before
+            if acl
+                .as_ref()
+                .is_some_and(|acl| !acl.may_have_written(author, LogType::Delta))
+            {
+                continue;
+            }
+
             for log_id in &log_ids {
                 let operations = match operation_store.get_log(author, log_id, None).await {
-                    Ok(Some(operations)) => {
-                        operations
-                            .into_iter()
-                            .map(|(header, body)| p2panda_core::Operation {
-                                hash: header.hash(),
-                                header,
-                                body,
+                    Ok(Some(operations)) => operations
+                        .into_iter()
+                        .filter(|(header, _)| {
+                            acl.as_ref().is_none_or(|acl| {
+                                acl.can_write_at(
+                                    author,
+                                    header.extensions.log_type,
+                                    header.timestamp,
+                                )
                             })
-                    }
+                        })
+                        .map(|(header, body)| p2panda_core::Operation {
+                            hash: header.hash(),
+                            header,
+                            body,
+                        }),
                     Ok(None) => {
                         continue;
                     }
@@ -228,6 +504,213 @@ impl TopicStore {
 
         Ok(result)
     }
+
+    /// The latest snapshot of every author of topic `id` who is allowed to write, for members who
+    /// were removed the latest one they wrote before they were removed.
+    pub async fn latest_snapshots(
+        &self,
+        operation_store: &OperationStore,
+        id: &TopicId,
+    ) -> sqlx::Result<Vec<p2panda_core::Operation<ReflectionExtensions>>> {
+        let operation_store = operation_store.inner();
+        let log_id = LogId::new(LogType::Snapshot, id);
+        let acl = self.acl(id).await?;
+
+        let mut result = Vec::new();
+        for author in self.authors(id).await? {
+            let snapshot = match &acl {
+                Some(acl) if !acl.can_write(&author, LogType::Snapshot) => {
+                    if !acl.may_have_written(&author, LogType::Snapshot) {
+                        continue;
+                    }
+
+                    operation_store
+                        .get_log(&author, &log_id, None)
+                        .await
+                        .map(|operations| {
+                            operations?.into_iter().rev().find(|(header, _)| {
+                                acl.can_write_at(&author, LogType::Snapshot, header.timestamp)
+                            })
+                        })
+                }
+                _ => operation_store.latest_operation(&author, &log_id).await,
+            };
+
+            match snapshot {
+                Ok(Some((header, body))) => result.push(p2panda_core::Operation {
+                    hash: header.hash(),
+                    header,
+                    body,
+                }),
+                Ok(None) => {}
+                Err(error) => {
+                    error!(
+                        "Failed to load latest operation for {author} with log type {log_id:?}: {error}"
+                    );
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Remember the current heights of the delta logs of all authors of topic `id` as seen.
+    pub async fn set_seen(
+        &self,
+        operation_store: &OperationStore,
+        id: &TopicId,
+    ) -> sqlx::Result<()> {
+        for (author, delta_seq_num) in self.delta_heights(operation_store, id).await? {
+            sqlx::query(
+                "
+                INSERT INTO seen_logs ( public_key, topic_id, delta_seq_num )
+                VALUES ( ?, ?, ? )
+                ON CONFLICT ( public_key, topic_id )
+                DO UPDATE SET delta_seq_num = excluded.delta_seq_num
+                ",
+            )
+            .bind(author.as_bytes().as_slice())
+            .bind(id.as_slice())
+            .bind(delta_seq_num.map(|seq_num| seq_num as i64))
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Mark the logs of topics which existed before unseen changes were tracked as seen, so
+    /// they don't show up as changed as a whole.
+    pub async fn seed_seen(&self, operation_store: &OperationStore) -> sqlx::Result<()> {
+        let ids: Vec<Vec<u8>> = sqlx::query_scalar("SELECT topic_id FROM unseeded_topics")
+            .fetch_all(&self.pool)
+            .await?;
+
+        for id in ids {
+            if let Ok(topic_id) = TopicId::try_from(&id[..]) {
+                self.set_seen(operation_store, &topic_id).await?;
+            }
+
+            sqlx::query("DELETE FROM unseeded_topics WHERE topic_id = ?")
+                .bind(&id)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Number of deltas authors other than `this_author` added to topic `id` since
+    /// [`Self::set_seen`] was called.
+    ///
+    /// Snapshots only repeat changes made in deltas, so they aren't counted. Logs of authors we
+    /// haven't seen anything of count as a whole.
+    pub async fn unseen_changes(
+        &self,
+        operation_store: &OperationStore,
+        id: &TopicId,
+        this_author: &PublicKey,
+    ) -> sqlx::Result<u64> {
+        let seen =
+            sqlx::query("SELECT public_key, delta_seq_num FROM seen_logs WHERE topic_id = ?")
+                .bind(id.as_slice())
+                .fetch_all(&self.pool)
+                .await?;
+
+        let seen: HashMap<PublicKey, Option<i64>> = seen
+            .iter()
+            .filter_map(|row| {
+                let public_key = PublicKey::try_from(row.get::<&[u8], _>("public_key")).ok()?;
+                let delta_seq_num = row.try_get("delta_seq_num").ok()?;
+                Some((public_key, delta_seq_num))
+            })
+            .collect();
+
+        // Sequence numbers start at 0, so the height of a log is its latest sequence number + 1
+        let mut unseen_changes = 0;
+        for (author, delta_seq_num) in self.delta_heights(operation_store, id).await? {
+            if &author == this_author {
+                continue;
+            }
+
+            let height = delta_seq_num.map_or(0, |seq_num| seq_num + 1);
+            let seen_height = seen
+                .get(&author)
+                .copied()
+                .flatten()
+                .map_or(0, |seq_num| seq_num as u64 + 1);
+            unseen_changes += height.saturating_sub(seen_height);
+        }
+
+        Ok(unseen_changes)
+    }
+
+    /// The latest sequence number of the delta log of all authors of topic `id`.
+    async fn delta_heights(
+        &self,
+        operation_store: &OperationStore,
+        id: &TopicId,
+    ) -> sqlx::Result<Vec<(PublicKey, Option<u64>)>> {
+        let operation_store = operation_store.inner();
+        let log_id = LogId::new(LogType::Delta, id);
+        let mut heights = Vec::new();
+
+        for author in self.authors(id).await? {
+            match operation_store.latest_operation(&author, &log_id).await {
+                Ok(operation) => {
+                    heights.push((author, operation.map(|(header, _)| header.seq_num)));
+                }
+                Err(error) => {
+                    error!(
+                        "Failed to load latest operation for {author} with log type {log_id:?}: {error}"
+                    );
+                }
+            }
+        }
+
+        Ok(heights)
+    }
+
+    /// All operations of topic `id` we share with other nodes, these are the same logs the
+    /// log sync protocol uses.
+    pub async fn operations_for_sync(
+        &self,
+        operation_store: &SqliteStore<LogId, ReflectionExtensions>,
+        id: &TopicId,
+    ) -> sqlx::Result<Vec<p2panda_core::Operation<ReflectionExtensions>>> {
+        let mut result = Vec::new();
+
+        for (author, log_ids) in self.get(id).await? {
+            for log_id in log_ids {
+                match operation_store.get_log(&author, &log_id, None).await {
+                    Ok(Some(operations)) => {
+                        result.extend(operations.into_iter().map(|(header, body)| {
+                            p2panda_core::Operation {
+                                hash: header.hash(),
+                                header,
+                                body,
+                            }
+                        }));
+                    }
+                    Ok(None) => {}
+                    Err(error) => {
+                        error!(
+                            "Failed to load operation for {author} with log type {log_id:?}: {error}"
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// The secret of a topic from the `secret` and `owner` columns of the topics table.
+fn topic_secret(secret: Option<&[u8]>, owner: Option<&[u8]>) -> Option<TopicSecret> {
+    let secret: [u8; 32] = secret?.try_into().ok()?;
+    let owner = PublicKey::try_from(owner?).ok()?;
+    Some(TopicSecret::new(secret, owner))
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, StdHash, Serialize, Deserialize)]
@@ -245,13 +728,40 @@ impl TopicMap<TopicId, Logs<LogId>> for TopicStore {
     async fn get(&self, topic: &TopicId) -> Result<Logs<LogId>, Self::Error> {
         let authors = self.authors(topic).await?;
 
-        let log_ids = [
+        let content_log_ids = vec![
             LogId::new(LogType::Delta, topic),
             LogId::new(LogType::Snapshot, topic),
         ];
+        let acl_log_id = LogId::new(LogType::Acl, topic);
+
+        let Some(acl) = self.acl(topic).await? else {
+            // Without an access-control list we sync all logs of all authors, one of them may
+            // contain the access-control list we don't know about yet.
+            return Ok(authors
+                .into_iter()
+                .map(|author| {
+                    let mut log_ids = content_log_ids.clone();
+                    log_ids.push(acl_log_id.clone());
+                    (author, log_ids)
+                })
+                .collect());
+        };
+
+        // Logs of authors who aren't allowed to write aren't synced at all, members who were
+        // removed keep the content logs with what they wrote before
         Ok(authors
             .into_iter()
-            .map(|author| (author, log_ids.to_vec()))
+            .filter_map(|author| {
+                let mut log_ids = Vec::new();
+                if acl.may_have_written(&author, LogType::Delta) {
+                    log_ids.extend(content_log_ids.iter().cloned());
+                }
+                if acl.can_write(&author, LogType::Acl) {
+                    log_ids.push(acl_log_id.clone());
+                }
+
+                (!log_ids.is_empty()).then_some((author, log_ids))
+            })
             .collect())
     }
 }
@@ -0,0 +1,125 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use p2panda_core::{Operation, PublicKey};
+use p2panda_net::TopicId;
+use p2panda_net::address_book::AddressBookError;
+use p2panda_net::gossip::GossipError;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+
+use crate::network::LogSyncError;
+use crate::operation::ReflectionExtensions;
+use crate::topic::SyncHandleError;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+pub type BoxStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+
+/// Handle to exchange the operations of a topic with other nodes.
+pub type SyncHandle = Arc<dyn TopicHandle<Operation<ReflectionExtensions>, SyncEvent>>;
+
+/// Handle to exchange ephemeral messages of a topic with other nodes.
+pub type EphemeralHandle = Arc<dyn TopicHandle<Vec<u8>>>;
+
+/// Events received while syncing the operations of a topic with other nodes.
+#[derive(Debug)]
+pub enum SyncEvent {
+    /// A sync session with `peer` started.
+    Started { peer: PublicKey },
+    /// `peer` sent us an operation, either during a sync session or live.
+    Operation {
+        peer: PublicKey,
+        operation: Operation<ReflectionExtensions>,
+    },
+    /// We received all operations `peer` had when the sync session started.
+    Completed { peer: PublicKey },
+    /// The sync session with `peer` failed.
+    Failed { peer: PublicKey },
+}
+
+/// How we are connected to a peer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PeerConnectionType {
+    /// We know the peer but aren't connected to it.
+    #[default]
+    None,
+    /// Direct connection over the internet.
+    Direct,
+    /// Direct connection to a peer in the local network, e.g. discovered via mDNS.
+    LocalNetwork,
+    /// Connection via a relay server.
+    Relay,
+    /// Connection via a relay server while a direct connection is being established.
+    Mixed,
+    /// Connection to another node in this process.
+    InMemory,
+}
+
+/// A peer known to a [`Transport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    /// Public key of the peer, which is also the id of its endpoint.
+    pub id: PublicKey,
+    pub connection_type: PeerConnectionType,
+    /// Direct addresses and relay URLs the peer can be reached at.
+    pub addresses: Vec<String>,
+    pub round_trip_time: Option<Duration>,
+    /// Topics both we and the peer are subscribed to.
+    pub topics: Vec<TopicId>,
+}
+
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error(transparent)]
+    Gossip(#[from] GossipError),
+    #[error(transparent)]
+    LogSync(#[from] LogSyncError),
+    #[error(transparent)]
+    SyncHandle(#[from] SyncHandleError),
+    #[error(transparent)]
+    PublishEphemeral(#[from] mpsc::error::SendError<Vec<u8>>),
+    #[error(transparent)]
+    TopicStore(#[from] sqlx::Error),
+    #[error(transparent)]
+    AddressBook(#[from] AddressBookError),
+    #[error(transparent)]
+    Directory(#[from] std::io::Error),
+    #[error("Topic was already subscribed to")]
+    AlreadySubscribed,
+}
+
+/// A way to reach other nodes.
+///
+/// `NodeInner::set_connection_mode` selects the transport for each connection mode. Topics are
+/// joined once per subscription and left again when the returned handles are dropped.
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// Join the topic `id` and sync its operations with other nodes.
+    ///
+    /// Operations which were created before joining are synced up as well.
+    fn sync_stream(&self, id: TopicId) -> BoxFuture<'_, Result<SyncHandle, TransportError>>;
+
+    /// Join the topic `id` to exchange ephemeral messages with other nodes.
+    ///
+    /// Only nodes which are subscribed while a message is published receive it.
+    fn ephemeral_stream(
+        &self,
+        id: TopicId,
+    ) -> BoxFuture<'_, Result<EphemeralHandle, TransportError>>;
+
+    /// All peers this transport knows about.
+    ///
+    /// The topics of the returned peers are left empty, they are filled in by the node.
+    fn peers(&self) -> BoxFuture<'_, Result<Vec<PeerInfo>, TransportError>>;
+}
+
+/// Handle to a topic joined via a [`Transport`], publishing messages of type `T` and receiving
+/// events of type `E`.
+pub trait TopicHandle<T, E = T>: Send + Sync {
+    /// Send `message` to all other nodes subscribed to the topic.
+    fn publish(&self, message: T) -> BoxFuture<'_, Result<(), TransportError>>;
+
+    /// Messages other nodes published to the topic.
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<E>, TransportError>>;
+}